@@ -1,5 +1,4 @@
 use glam::{Mat4, Quat, Vec3, Vec4};
-use std::sync::{Arc, Mutex};
 
 #[derive(Clone)]
 pub struct Camera {
@@ -13,12 +12,11 @@ pub struct Camera {
     near: f32,
     far: f32,
 
-    // Cache
-    dirty: Arc<Mutex<bool>>,
-    cached_view_matrix: Arc<Mutex<Mat4>>,
-    cached_proj_matrix: Arc<Mutex<Mat4>>,
-    cached_frustum_planes: Arc<Mutex<[Vec4; 6]>>,
-    cached_frustum_corners: Arc<Mutex<[Vec3; 8]>>,
+    // Cache, recomputed by every method that moves the camera so the getters only need &self
+    cached_view_matrix: Mat4,
+    cached_proj_matrix: Mat4,
+    cached_frustum_planes: [Vec4; 6],
+    cached_frustum_corners: [Vec3; 8],
 }
 
 impl Camera {
@@ -35,11 +33,10 @@ impl Camera {
             near: 0.1,
             far: 50.1,
 
-            dirty: Arc::new(Mutex::new(true)),
-            cached_view_matrix: Arc::new(Mutex::new(Mat4::IDENTITY)),
-            cached_proj_matrix: Arc::new(Mutex::new(Mat4::IDENTITY)),
-            cached_frustum_planes: Arc::new(Mutex::new([Vec4::ZERO; 6])),
-            cached_frustum_corners: Arc::new(Mutex::new([Vec3::ZERO; 8])),
+            cached_view_matrix: Mat4::IDENTITY,
+            cached_proj_matrix: Mat4::IDENTITY,
+            cached_frustum_planes: [Vec4::ZERO; 6],
+            cached_frustum_corners: [Vec3::ZERO; 8],
         };
 
         // Initial cache update
@@ -48,115 +45,104 @@ impl Camera {
         cam
     }
 
-    fn update_cache(&self) {
-        // Take all locks at once in a consistent order to avoid deadlocks
-        let mut dirty = self.dirty.lock().unwrap();
-        if *dirty {
-            // Release the dirty lock before taking other locks
-            *dirty = false;
-            drop(dirty);
-
-            // Now take the other locks
-            let view_matrix = Mat4::look_at_rh(self.position, self.target, Vec3::Y);
-            let proj_matrix =
-                Mat4::perspective_rh(self.fov, self.aspect_ratio, self.near, self.far);
-
-            // Update matrices
-            *self.cached_view_matrix.lock().unwrap() = view_matrix;
-            *self.cached_proj_matrix.lock().unwrap() = proj_matrix;
-
-            // Update frustum data using the new matrices
-            // shoutout this paper
-            // https://www.gamedevs.org/uploads/fast-extraction-viewing-frustum-planes-from-world-view-projection-matrix.pdf
-            let vp = proj_matrix * view_matrix; // Matches scenario 2 "Frustum Planes in World Space"
-            let mut planes = self.cached_frustum_planes.lock().unwrap();
-
-            // Update frustum planes
-            // reminder that glam is column major, so layout in memory of the Mat4 is
-            // [M11, M21, M31, M41,  M12, M22, M32, M42,  M13, M23, M33, M43,  M14, M24, M34, M44]
-            // but these values are accessed as follows:
-
-            // Extract frustum planes - OpenGL formulas (column-major)
-            // --- Left Plane ---
-            planes[0] = Vec4::new(
-                vp.col(3)[0] + vp.col(0)[0], // a = M41 + M11
-                vp.col(3)[1] + vp.col(0)[1], // b = M42 + M12
-                vp.col(3)[2] + vp.col(0)[2], // c = M43 + M13
-                vp.col(3)[3] + vp.col(0)[3], // d = M44 + M14
-            )
-            .normalize();
-
-            // --- Right Plane ---
-            planes[1] = Vec4::new(
-                vp.col(3)[0] - vp.col(0)[0], // a = M41 - M11
-                vp.col(3)[1] - vp.col(0)[1], // b = M42 - M12
-                vp.col(3)[2] - vp.col(0)[2], // c = M43 - M13
-                vp.col(3)[3] - vp.col(0)[3], // d = M44 - M14
-            )
-            .normalize();
-
-            // --- Bottom Plane ---
-            planes[2] = Vec4::new(
-                vp.col(3)[0] + vp.col(1)[0], // a = M41 + M21
-                vp.col(3)[1] + vp.col(1)[1], // b = M42 + M22
-                vp.col(3)[2] + vp.col(1)[2], // c = M43 + M23
-                vp.col(3)[3] + vp.col(1)[3], // d = M44 + M24
-            )
-            .normalize();
-
-            // --- Top Plane ---
-            planes[3] = Vec4::new(
-                vp.col(3)[0] - vp.col(1)[0], // a = M41 - M21
-                vp.col(3)[1] - vp.col(1)[1], // b = M42 - M22
-                vp.col(3)[2] - vp.col(1)[2], // c = M43 - M23
-                vp.col(3)[3] - vp.col(1)[3], // d = M44 - M24
-            )
-            .normalize();
-
-            // --- Near Plane ---
-            planes[4] = Vec4::new(
-                vp.col(3)[0] + vp.col(2)[0], // a = M41 + M31
-                vp.col(3)[1] + vp.col(2)[1], // b = M42 + M32
-                vp.col(3)[2] + vp.col(2)[2], // c = M43 + M33
-                vp.col(3)[3] + vp.col(2)[3], // d = M44 + M34
-            )
-            .normalize();
-
-            // --- Far Plane ---
-            planes[5] = Vec4::new(
-                vp.col(3)[0] - vp.col(2)[0], // a = M41 - M31
-                vp.col(3)[1] - vp.col(2)[1], // b = M42 - M32
-                vp.col(3)[2] - vp.col(2)[2], // c = M43 - M33
-                vp.col(3)[3] - vp.col(2)[3], // d = M44 - M34
-            )
-            .normalize();
-
-            // Update frustum corners
-            let mut corners = self.cached_frustum_corners.lock().unwrap();
-            let fov_rad = self.fov;
-            let near_height = 2.0 * self.near * (fov_rad / 2.0).tan();
-            let near_width = near_height * self.aspect_ratio;
-            let far_height = 2.0 * self.far * (fov_rad / 2.0).tan();
-            let far_width = far_height * self.aspect_ratio;
-
-            let forward = self.forward();
-            let right = self.right();
-            let up = self.up();
-
-            let near_center = self.position + forward * self.near;
-            let far_center = self.position + forward * self.far;
-
-            *corners = [
-                near_center + up * (near_height / 2.0) - right * (near_width / 2.0),
-                near_center + up * (near_height / 2.0) + right * (near_width / 2.0),
-                near_center - up * (near_height / 2.0) - right * (near_width / 2.0),
-                near_center - up * (near_height / 2.0) + right * (near_width / 2.0),
-                far_center + up * (far_height / 2.0) - right * (far_width / 2.0),
-                far_center + up * (far_height / 2.0) + right * (far_width / 2.0),
-                far_center - up * (far_height / 2.0) - right * (far_width / 2.0),
-                far_center - up * (far_height / 2.0) + right * (far_width / 2.0),
-            ];
-        }
+    fn update_cache(&mut self) {
+        let view_matrix = Mat4::look_at_rh(self.position, self.target, Vec3::Y);
+        let proj_matrix = Mat4::perspective_rh(self.fov, self.aspect_ratio, self.near, self.far);
+
+        // Update matrices
+        self.cached_view_matrix = view_matrix;
+        self.cached_proj_matrix = proj_matrix;
+
+        // Update frustum data using the new matrices
+        // shoutout this paper
+        // https://www.gamedevs.org/uploads/fast-extraction-viewing-frustum-planes-from-world-view-projection-matrix.pdf
+        let vp = proj_matrix * view_matrix; // Matches scenario 2 "Frustum Planes in World Space"
+        let planes = &mut self.cached_frustum_planes;
+
+        // Update frustum planes
+        // reminder that glam is column major, so layout in memory of the Mat4 is
+        // [M11, M21, M31, M41,  M12, M22, M32, M42,  M13, M23, M33, M43,  M14, M24, M34, M44]
+        // but these values are accessed as follows:
+
+        // Extract frustum planes - OpenGL formulas (column-major)
+        // --- Left Plane ---
+        planes[0] = Vec4::new(
+            vp.col(3)[0] + vp.col(0)[0], // a = M41 + M11
+            vp.col(3)[1] + vp.col(0)[1], // b = M42 + M12
+            vp.col(3)[2] + vp.col(0)[2], // c = M43 + M13
+            vp.col(3)[3] + vp.col(0)[3], // d = M44 + M14
+        )
+        .normalize();
+
+        // --- Right Plane ---
+        planes[1] = Vec4::new(
+            vp.col(3)[0] - vp.col(0)[0], // a = M41 - M11
+            vp.col(3)[1] - vp.col(0)[1], // b = M42 - M12
+            vp.col(3)[2] - vp.col(0)[2], // c = M43 - M13
+            vp.col(3)[3] - vp.col(0)[3], // d = M44 - M14
+        )
+        .normalize();
+
+        // --- Bottom Plane ---
+        planes[2] = Vec4::new(
+            vp.col(3)[0] + vp.col(1)[0], // a = M41 + M21
+            vp.col(3)[1] + vp.col(1)[1], // b = M42 + M22
+            vp.col(3)[2] + vp.col(1)[2], // c = M43 + M23
+            vp.col(3)[3] + vp.col(1)[3], // d = M44 + M24
+        )
+        .normalize();
+
+        // --- Top Plane ---
+        planes[3] = Vec4::new(
+            vp.col(3)[0] - vp.col(1)[0], // a = M41 - M21
+            vp.col(3)[1] - vp.col(1)[1], // b = M42 - M22
+            vp.col(3)[2] - vp.col(1)[2], // c = M43 - M23
+            vp.col(3)[3] - vp.col(1)[3], // d = M44 - M24
+        )
+        .normalize();
+
+        // --- Near Plane ---
+        planes[4] = Vec4::new(
+            vp.col(3)[0] + vp.col(2)[0], // a = M41 + M31
+            vp.col(3)[1] + vp.col(2)[1], // b = M42 + M32
+            vp.col(3)[2] + vp.col(2)[2], // c = M43 + M33
+            vp.col(3)[3] + vp.col(2)[3], // d = M44 + M34
+        )
+        .normalize();
+
+        // --- Far Plane ---
+        planes[5] = Vec4::new(
+            vp.col(3)[0] - vp.col(2)[0], // a = M41 - M31
+            vp.col(3)[1] - vp.col(2)[1], // b = M42 - M32
+            vp.col(3)[2] - vp.col(2)[2], // c = M43 - M33
+            vp.col(3)[3] - vp.col(2)[3], // d = M44 - M34
+        )
+        .normalize();
+
+        // Update frustum corners
+        let fov_rad = self.fov;
+        let near_height = 2.0 * self.near * (fov_rad / 2.0).tan();
+        let near_width = near_height * self.aspect_ratio;
+        let far_height = 2.0 * self.far * (fov_rad / 2.0).tan();
+        let far_width = far_height * self.aspect_ratio;
+
+        let forward = self.forward();
+        let right = self.right();
+        let up = self.up();
+
+        let near_center = self.position + forward * self.near;
+        let far_center = self.position + forward * self.far;
+
+        self.cached_frustum_corners = [
+            near_center + up * (near_height / 2.0) - right * (near_width / 2.0),
+            near_center + up * (near_height / 2.0) + right * (near_width / 2.0),
+            near_center - up * (near_height / 2.0) - right * (near_width / 2.0),
+            near_center - up * (near_height / 2.0) + right * (near_width / 2.0),
+            far_center + up * (far_height / 2.0) - right * (far_width / 2.0),
+            far_center + up * (far_height / 2.0) + right * (far_width / 2.0),
+            far_center - up * (far_height / 2.0) - right * (far_width / 2.0),
+            far_center - up * (far_height / 2.0) + right * (far_width / 2.0),
+        ];
     }
 
     fn update_direction(&mut self) {
@@ -168,44 +154,44 @@ impl Camera {
     pub fn move_forward(&mut self, distance: f32) {
         self.position += self.forward() * distance;
         self.target += self.forward() * distance;
-        *self.dirty.lock().unwrap() = true;
+        self.update_cache();
     }
 
     pub fn move_backward(&mut self, distance: f32) {
         self.position -= self.forward() * distance;
         self.target -= self.forward() * distance;
-        *self.dirty.lock().unwrap() = true;
+        self.update_cache();
     }
 
     pub fn move_right(&mut self, amount: f32) {
         let right = self.right();
         self.position += right * amount;
         self.target += right * amount;
-        *self.dirty.lock().unwrap() = true;
+        self.update_cache();
     }
 
     pub fn move_left(&mut self, amount: f32) {
         let right = self.right();
         self.position -= right * amount;
         self.target -= right * amount;
-        *self.dirty.lock().unwrap() = true;
+        self.update_cache();
     }
 
     pub fn move_up(&mut self, amount: f32) {
         self.position += Vec3::Y * amount;
-        *self.dirty.lock().unwrap() = true;
+        self.update_cache();
     }
 
     pub fn move_down(&mut self, amount: f32) {
         self.position -= Vec3::Y * amount;
-        *self.dirty.lock().unwrap() = true;
+        self.update_cache();
     }
 
     //pub fn rotate(&mut self, pitch: f32, yaw: f32) {
     //    let pitch_rotation = Quat::from_axis_angle(self.right(), pitch);
     //    let yaw_rotation = Quat::from_axis_angle(Vec3::Y, yaw);
     //    self.orientation = yaw_rotation * pitch_rotation * self.orientation;
-    //    *self.dirty.lock().unwrap() = true;
+    //    self.update_cache();
     //}
 
     pub fn orbit(&mut self, angle: f32) {
@@ -219,28 +205,24 @@ impl Camera {
         // Update direction and orientation
         let direction = (-self.position).normalize();
         self.orientation = Quat::from_rotation_arc(Vec3::Z, direction);
-        *self.dirty.lock().unwrap() = true;
+        self.update_cache();
     }
 
     // Getters
     pub fn view_matrix(&self) -> Mat4 {
-        self.update_cache();
-        *self.cached_view_matrix.lock().unwrap()
+        self.cached_view_matrix
     }
 
     pub fn projection_matrix(&self) -> Mat4 {
-        self.update_cache();
-        *self.cached_proj_matrix.lock().unwrap()
+        self.cached_proj_matrix
     }
 
     pub fn frustum_planes(&self) -> [Vec4; 6] {
-        self.update_cache();
-        *self.cached_frustum_planes.lock().unwrap()
+        self.cached_frustum_planes
     }
 
     pub fn frustum_corners(&self) -> [Vec3; 8] {
-        self.update_cache();
-        *self.cached_frustum_corners.lock().unwrap()
+        self.cached_frustum_corners
     }
 
     pub fn forward(&self) -> Vec3 {
@@ -271,7 +253,7 @@ impl Camera {
         self.orientation = yaw_rot * pitch_rot * self.orientation;
         // Update the target based on the new forward direction.
         self.target = self.position + self.forward();
-        *self.dirty.lock().unwrap() = true;
+        self.update_cache();
     }
 
     /// Rotate the camera by a pitch angle (rotation about the camera’s right vector).
@@ -279,7 +261,7 @@ impl Camera {
         let pitch_rot = Quat::from_axis_angle(self.right(), angle);
         self.orientation = pitch_rot * self.orientation;
         self.target = self.position + self.forward();
-        *self.dirty.lock().unwrap() = true;
+        self.update_cache();
     }
 
     /// Rotate the camera by a yaw angle (rotation about the global Y axis).
@@ -287,7 +269,7 @@ impl Camera {
         let yaw_rot = Quat::from_axis_angle(Vec3::Y, angle);
         self.orientation = yaw_rot * self.orientation;
         self.target = self.position + self.forward();
-        *self.dirty.lock().unwrap() = true;
+        self.update_cache();
     }
 
     /// Rotate the camera by a roll angle (rotation about the camera’s forward axis).
@@ -295,7 +277,7 @@ impl Camera {
         let roll_rot = Quat::from_axis_angle(self.forward(), angle);
         self.orientation = roll_rot * self.orientation;
         self.target = self.position + self.forward();
-        *self.dirty.lock().unwrap() = true;
+        self.update_cache();
     }
 
     pub fn orbital_angle(&self) -> f32 {
@@ -305,7 +287,7 @@ impl Camera {
     pub fn reset(&mut self) {
         self.position = Vec3::ZERO;
         self.orientation = Quat::IDENTITY;
-        *self.dirty.lock().unwrap() = true;
+        self.update_cache();
     }
 
     pub fn target(&self) -> Vec3 {
@@ -316,6 +298,16 @@ impl Camera {
         self.fov
     }
 
+    /// Width over height of the image the projection maps to
+    pub fn aspect_ratio(&self) -> f32 {
+        self.aspect_ratio
+    }
+
+    pub fn set_aspect_ratio(&mut self, aspect_ratio: f32) {
+        self.aspect_ratio = aspect_ratio;
+        self.update_cache();
+    }
+
     pub fn orientation(&self) -> Quat {
         self.orientation
     }
@@ -8,7 +8,8 @@ pub struct Color {
     pub b: f32, // Blue component (0.0 - 1.0)
 }
 
-// NOTE: Might be better to not have it be clamped so it's clear whats being done idk
+// NOTE: Arithmetic is unclamped, lighting happens in linear HDR and only gets squashed into 0-1
+// by tone mapping right before output. The `to_*` conversions clamp for safety.
 
 impl Color {
     /// Create a new color with RGB components normalized.
@@ -75,30 +76,57 @@ impl Color {
         Self { r, g, b }
     }
 
+    #[cfg(feature = "terminal")]
     pub fn to_crossterm_color(&self) -> crossterm::style::Color {
+        let c = self.clamped();
         crossterm::style::Color::Rgb {
-            r: (self.r * 255.0) as u8,
-            g: (self.g * 255.0) as u8,
-            b: (self.b * 255.0) as u8,
+            r: (c.r * 255.0) as u8,
+            g: (c.g * 255.0) as u8,
+            b: (c.b * 255.0) as u8,
         }
     }
 
     /// Convert the color to a terminal-compatible ANSI escape sequence.
     pub fn to_ansii_escape(&self) -> String {
+        let c = self.clamped();
         format!(
             "\x1b[38;2;{};{};{}m",
-            (self.r * 255.0) as u8,
-            (self.g * 255.0) as u8,
-            (self.b * 255.0) as u8
+            (c.r * 255.0) as u8,
+            (c.g * 255.0) as u8,
+            (c.b * 255.0) as u8
         )
     }
     pub fn to_u32(&self) -> u32 {
-        let r = (self.r * 255.0) as u32;
-        let g = (self.g * 255.0) as u32;
-        let b = (self.b * 255.0) as u32;
+        let c = self.clamped();
+        let r = (c.r * 255.0) as u32;
+        let g = (c.g * 255.0) as u32;
+        let b = (c.b * 255.0) as u32;
         (r << 16) | (g << 8) | b
     }
 
+    /// Decodes an sRGB encoded color (textures, MTL files, color pickers) into linear space
+    pub fn to_linear(&self) -> Color {
+        Color {
+            r: srgb_to_linear(self.r),
+            g: srgb_to_linear(self.g),
+            b: srgb_to_linear(self.b),
+        }
+    }
+
+    /// Encodes a linear color for display, expects the color to already be in 0-1
+    pub fn to_srgb(&self) -> Color {
+        Color {
+            r: linear_to_srgb(self.r),
+            g: linear_to_srgb(self.g),
+            b: linear_to_srgb(self.b),
+        }
+    }
+
+    /// Rec. 709 relative luminance of a linear color
+    pub fn luminance(&self) -> f32 {
+        0.2126 * self.r + 0.7152 * self.g + 0.0722 * self.b
+    }
+
     pub fn lerp(self, end: &Color, t: f32) -> Color {
         self * (1.0 - t) + *end * t
     }
@@ -159,6 +187,23 @@ impl Color {
     }
 }
 
+fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(c: f32) -> f32 {
+    let c = c.clamp(0.0, 1.0);
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
 // Predefined colors
 
 impl Default for Color {
@@ -213,7 +258,6 @@ impl Add for Color {
             g: self.g + rhs.g,
             b: self.b + rhs.b,
         }
-        .clamped() // Cause 0-1
     }
 }
 
@@ -222,7 +266,6 @@ impl AddAssign for Color {
         self.r += rhs.r;
         self.g += rhs.g;
         self.b += rhs.b;
-        self.clamp()
     }
 }
 
@@ -234,7 +277,6 @@ impl Sub for Color {
             g: self.g - rhs.g,
             b: self.b - rhs.b,
         }
-        .clamped()
     }
 }
 
@@ -243,7 +285,6 @@ impl SubAssign for Color {
         self.r -= rhs.r;
         self.g -= rhs.g;
         self.b -= rhs.b;
-        self.clamp()
     }
 }
 
@@ -295,7 +336,6 @@ impl Mul<f32> for Color {
             g: self.g * scalar,
             b: self.b * scalar,
         }
-        .clamped()
     }
 }
 impl MulAssign<f32> for Color {
@@ -303,7 +343,6 @@ impl MulAssign<f32> for Color {
         self.r *= scalar;
         self.g *= scalar;
         self.b *= scalar;
-        self.clamp();
     }
 }
 impl PartialEq for Color {
@@ -323,7 +362,6 @@ impl Mul<&Color> for Color {
             g: self.g * rhs.g,
             b: self.b * rhs.b,
         }
-        .clamped() // Clamping to keep values within valid range
     }
 }
 
@@ -335,7 +373,6 @@ impl Mul<Color> for Color {
             g: self.g * rhs.g,
             b: self.b * rhs.b,
         }
-        .clamped()
     }
 }
 
@@ -466,6 +503,30 @@ mod tests {
     use super::*;
     use std::time::Instant;
 
+    #[test]
+    fn srgb_round_trip() {
+        // Endpoints stay put
+        assert_eq!(Color::BLACK.to_srgb(), Color::BLACK);
+        assert_eq!(Color::WHITE.to_linear(), Color::WHITE);
+        assert!((Color::WHITE.to_srgb().r - 1.0).abs() < 1e-6);
+
+        // Linear segment near black, and the curve meeting it without a jump
+        assert!((srgb_to_linear(0.02) - 0.02 / 12.92).abs() < 1e-7);
+        assert!((linear_to_srgb(0.001) - 0.001 * 12.92).abs() < 1e-7);
+        assert!((srgb_to_linear(0.04045) - srgb_to_linear(0.040_46)).abs() < 1e-5);
+        // Mid gray is a lot darker in linear space
+        assert!((srgb_to_linear(0.5) - 0.214).abs() < 1e-3);
+
+        for i in 0..=255 {
+            let c = i as f32 / 255.0;
+            let color = Color::new(c, c, c);
+            let back = color.to_linear().to_srgb();
+            assert!((back.r - c).abs() < 1e-5, "{c} came back as {}", back.r);
+            let back = color.to_srgb().to_linear();
+            assert!((back.g - c).abs() < 1e-5, "{c} came back as {}", back.g);
+        }
+    }
+
     #[test]
     fn benchmark_lerp() {
         let iterations = 10_000_000;
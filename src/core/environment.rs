@@ -0,0 +1,319 @@
+use glam::{Affine3A, Vec2, Vec3};
+
+use crate::core::geometry::{Material, Mesh, Tri, Vertex};
+use crate::core::{Color, Entity, EnvironmentLight, Fog, Sky};
+
+/// Static scenery that gives models some spatial context (floors, rooms, ...)
+///
+/// The environment generates its own entities, which are pushed through the exact same
+/// geometry -> raster -> lighting path as regular scene entities. They live outside of
+/// `Scene::entities` so that object selection/movement never picks them up.
+#[derive(Clone)]
+pub struct Environment {
+    background: Background,
+    /// World space height of the floor plane
+    floor_height: f32,
+    entities: Vec<Entity>,
+    /// Fills whatever the geometry doesn't cover
+    sky: Sky,
+    /// Ambient light and reflections taken from the sky, rebuilt whenever the sky changes
+    image_light: Option<EnvironmentLight>,
+    /// `Some(intensity)` when the sky should light the scene
+    image_light_intensity: Option<f32>,
+    fog: Fog,
+}
+
+#[derive(Clone)]
+pub enum Background {
+    Void, // Nothing
+    /// Checkered floor plane, `size` cells along each side
+    BlenderFloor {
+        size: i32,
+        cell_size: f32,
+
+        primary_color: Color,
+        secondary_color: Color,
+    },
+    /// Checkered floor with four walls facing inwards, walls are as tall as the room is wide
+    Room {
+        size: i32,
+        cell_size: f32,
+
+        wall_colors: [Color; 4],
+    },
+}
+
+impl Default for Environment {
+    fn default() -> Self {
+        Self::new(Background::Void)
+    }
+}
+
+impl Environment {
+    pub fn new(background: Background) -> Self {
+        Self::with_floor_height(background, 0.0)
+    }
+
+    pub fn with_floor_height(background: Background, floor_height: f32) -> Self {
+        let mut env = Self {
+            background,
+            floor_height,
+            entities: Vec::new(),
+            sky: Sky::None,
+            image_light: None,
+            image_light_intensity: None,
+            fog: Fog::default(),
+        };
+        env.rebuild();
+        env
+    }
+
+    /// Checkered "infinite-ish" floor, reaches roughly to the far plane of the default camera
+    pub fn blender_floor() -> Self {
+        Self::new(Background::BlenderFloor {
+            size: 40,
+            cell_size: 2.0,
+            primary_color: Color::GRAY,
+            secondary_color: Color::DARK_GRAY,
+        })
+    }
+
+    pub fn room() -> Self {
+        Self::new(Background::Room {
+            size: 10,
+            cell_size: 2.0,
+            wall_colors: [
+                Color::LIGHT_GRAY,
+                Color::GRAY,
+                Color::LIGHT_GRAY,
+                Color::GRAY,
+            ],
+        })
+    }
+
+    pub fn background(&self) -> &Background {
+        &self.background
+    }
+
+    pub fn set_background(&mut self, background: Background) {
+        self.background = background;
+        self.rebuild();
+    }
+
+    pub fn sky(&self) -> &Sky {
+        &self.sky
+    }
+
+    pub fn set_sky(&mut self, sky: Sky) {
+        self.sky = sky;
+        self.rebuild_image_light();
+    }
+
+    /// Turns image based lighting from the sky on (`Some(intensity)`) or off (`None`).
+    /// While it is on, the sky replaces the flat per-light ambient term and feeds reflections.
+    pub fn set_image_lighting(&mut self, intensity: Option<f32>) {
+        self.image_light_intensity = intensity;
+        self.rebuild_image_light();
+    }
+
+    pub fn image_light(&self) -> Option<&EnvironmentLight> {
+        self.image_light.as_ref()
+    }
+
+    pub fn fog(&self) -> &Fog {
+        &self.fog
+    }
+
+    pub fn fog_mut(&mut self) -> &mut Fog {
+        &mut self.fog
+    }
+
+    pub fn set_fog(&mut self, fog: Fog) {
+        self.fog = fog;
+    }
+
+    pub fn floor_height(&self) -> f32 {
+        self.floor_height
+    }
+
+    pub fn set_floor_height(&mut self, floor_height: f32) {
+        self.floor_height = floor_height;
+        self.rebuild();
+    }
+
+    /// Generated environment geometry, rendered after the scene entities
+    pub fn entities(&self) -> &[Entity] {
+        &self.entities
+    }
+
+    pub(crate) fn entities_mut(&mut self) -> &mut [Entity] {
+        &mut self.entities
+    }
+
+    /// Convolving the sky takes a moment, so only redo it when the sky or intensity changes
+    fn rebuild_image_light(&mut self) {
+        self.image_light = self
+            .image_light_intensity
+            .and_then(|intensity| EnvironmentLight::from_sky(&self.sky, intensity));
+    }
+
+    fn rebuild(&mut self) {
+        let transform = Affine3A::from_translation(Vec3::new(0.0, self.floor_height, 0.0));
+        self.entities = match self.background {
+            Background::Void => Vec::new(),
+            Background::BlenderFloor {
+                size,
+                cell_size,
+                primary_color,
+                secondary_color,
+            } => {
+                let mut mesh = Mesh::new();
+                mesh.name = "environment_floor".to_string();
+                mesh.set_material(flat_material("floor_primary", primary_color));
+                mesh.set_material(flat_material("floor_secondary", secondary_color));
+                push_checker_floor(&mut mesh, size, cell_size, 0, 1);
+                vec![Entity::new(
+                    mesh,
+                    transform,
+                    "Environment Floor".to_string(),
+                )]
+            }
+            Background::Room {
+                size,
+                cell_size,
+                wall_colors,
+            } => {
+                let mut mesh = Mesh::new();
+                mesh.name = "environment_room".to_string();
+                mesh.set_material(flat_material("floor_primary", Color::LIGHT_GRAY));
+                mesh.set_material(flat_material("floor_secondary", Color::GRAY));
+                for (i, color) in wall_colors.iter().enumerate() {
+                    mesh.set_material(flat_material(&format!("wall_{}", i), *color));
+                }
+                push_checker_floor(&mut mesh, size, cell_size, 0, 1);
+                push_room_walls(&mut mesh, size, cell_size, 2);
+                vec![Entity::new(mesh, transform, "Environment Room".to_string())]
+            }
+        };
+    }
+}
+
+/// `color` is taken as sRGB, same as colors coming from MTL files
+fn flat_material(name: &str, color: Color) -> Material {
+    let color = color.to_linear();
+    Material {
+        name: name.to_string(),
+        ambient: Some(color * 0.25),
+        diffuse: Some(color),
+        ..Default::default()
+    }
+}
+
+/// Pushes a quad as two triangles, corners are expected counter-clockwise when looking at the
+/// front face (same winding the clipper treats as front facing)
+fn push_quad(mesh: &mut Mesh, corners: [Vec3; 4], uvs: [Vec2; 4], material: usize) {
+    let base = mesh.vertices.len();
+    for (pos, uv) in corners.iter().zip(uvs.iter()) {
+        mesh.vertices.push(Vertex {
+            pos: *pos,
+            uv: Some(*uv),
+            ..Default::default()
+        });
+    }
+    mesh.tris.push(Tri {
+        vertices: [base, base + 1, base + 2],
+        material: Some(material),
+    });
+    mesh.tris.push(Tri {
+        vertices: [base, base + 2, base + 3],
+        material: Some(material),
+    });
+}
+
+/// Floor in the XZ plane centered on the origin, facing +Y, alternating between two materials
+fn push_checker_floor(
+    mesh: &mut Mesh,
+    size: i32,
+    cell_size: f32,
+    primary: usize,
+    secondary: usize,
+) {
+    let half = size as f32 * cell_size * 0.5;
+    for z in 0..size {
+        for x in 0..size {
+            let x0 = x as f32 * cell_size - half;
+            let z0 = z as f32 * cell_size - half;
+            let x1 = x0 + cell_size;
+            let z1 = z0 + cell_size;
+            let material = if (x + z) % 2 == 0 { primary } else { secondary };
+            push_quad(
+                mesh,
+                [
+                    Vec3::new(x0, 0.0, z0),
+                    Vec3::new(x0, 0.0, z1),
+                    Vec3::new(x1, 0.0, z1),
+                    Vec3::new(x1, 0.0, z0),
+                ],
+                [Vec2::ZERO, Vec2::Y, Vec2::ONE, Vec2::X],
+                material,
+            );
+        }
+    }
+}
+
+/// Four walls around the floor, all facing the center of the room.
+/// `first_material` is the material index of the first wall, the other three follow it.
+fn push_room_walls(mesh: &mut Mesh, size: i32, cell_size: f32, first_material: usize) {
+    let half = size as f32 * cell_size * 0.5;
+    let height = size as f32 * cell_size;
+    let uvs = [Vec2::ZERO, Vec2::X, Vec2::ONE, Vec2::Y];
+
+    // -Z wall, facing +Z
+    push_quad(
+        mesh,
+        [
+            Vec3::new(-half, 0.0, -half),
+            Vec3::new(half, 0.0, -half),
+            Vec3::new(half, height, -half),
+            Vec3::new(-half, height, -half),
+        ],
+        uvs,
+        first_material,
+    );
+    // +X wall, facing -X
+    push_quad(
+        mesh,
+        [
+            Vec3::new(half, 0.0, -half),
+            Vec3::new(half, 0.0, half),
+            Vec3::new(half, height, half),
+            Vec3::new(half, height, -half),
+        ],
+        uvs,
+        first_material + 1,
+    );
+    // +Z wall, facing -Z
+    push_quad(
+        mesh,
+        [
+            Vec3::new(half, 0.0, half),
+            Vec3::new(-half, 0.0, half),
+            Vec3::new(-half, height, half),
+            Vec3::new(half, height, half),
+        ],
+        uvs,
+        first_material + 2,
+    );
+    // -X wall, facing +X
+    push_quad(
+        mesh,
+        [
+            Vec3::new(-half, 0.0, half),
+            Vec3::new(-half, 0.0, -half),
+            Vec3::new(-half, height, -half),
+            Vec3::new(-half, height, half),
+        ],
+        uvs,
+        first_material + 3,
+    );
+}
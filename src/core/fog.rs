@@ -0,0 +1,140 @@
+use glam::Vec3;
+
+use crate::core::Color;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum FogMode {
+    #[default]
+    None,
+    /// Ramps from no fog at `start` to full fog at `end`
+    Linear,
+    /// `1 - e^(-density * distance)`
+    Exponential,
+    /// `1 - e^(-(density * distance)^2)`, clearer up close with a sharper falloff
+    ExponentialSquared,
+    /// Exponential fog that thins out with height above `base_height`
+    Height,
+}
+
+impl FogMode {
+    pub fn next(self) -> Self {
+        match self {
+            FogMode::None => FogMode::Linear,
+            FogMode::Linear => FogMode::Exponential,
+            FogMode::Exponential => FogMode::ExponentialSquared,
+            FogMode::ExponentialSquared => FogMode::Height,
+            FogMode::Height => FogMode::None,
+        }
+    }
+}
+
+/// Distance (and height) based fog, blended over lit geometry in the lighting pass.
+/// All parameters live side by side so switching modes at runtime keeps the other settings.
+#[derive(Clone, Copy, Debug)]
+pub struct Fog {
+    pub mode: FogMode,
+    /// Linear color, also used as the background when there is no sky
+    pub color: Color,
+    /// Used by the exponential and height modes
+    pub density: f32,
+    /// Linear mode range, in world units from the camera
+    pub start: f32,
+    pub end: f32,
+    /// Height mode, world space height at which the fog has `density`
+    pub base_height: f32,
+    /// Height mode, how quickly the fog thins out going up
+    pub falloff: f32,
+}
+
+impl Default for Fog {
+    fn default() -> Self {
+        Self {
+            mode: FogMode::None,
+            color: Color::new(0.5, 0.55, 0.6),
+            density: 0.05,
+            start: 5.0,
+            end: 40.0,
+            base_height: 0.0,
+            falloff: 0.5,
+        }
+    }
+}
+
+impl Fog {
+    pub fn linear(color: Color, start: f32, end: f32) -> Self {
+        Self {
+            mode: FogMode::Linear,
+            color,
+            start,
+            end,
+            ..Default::default()
+        }
+    }
+
+    pub fn exponential(color: Color, density: f32) -> Self {
+        Self {
+            mode: FogMode::Exponential,
+            color,
+            density,
+            ..Default::default()
+        }
+    }
+
+    pub fn exponential_squared(color: Color, density: f32) -> Self {
+        Self {
+            mode: FogMode::ExponentialSquared,
+            color,
+            density,
+            ..Default::default()
+        }
+    }
+
+    pub fn height(color: Color, density: f32, base_height: f32, falloff: f32) -> Self {
+        Self {
+            mode: FogMode::Height,
+            color,
+            density,
+            base_height,
+            falloff,
+            ..Default::default()
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.mode != FogMode::None
+    }
+
+    /// How much fog lies between `eye` and `point`, 0 is clear and 1 is fully fogged
+    pub fn factor(&self, eye: Vec3, point: Vec3) -> f32 {
+        let distance = eye.distance(point);
+        let factor = match self.mode {
+            FogMode::None => 0.0,
+            FogMode::Linear => (distance - self.start) / (self.end - self.start).max(f32::EPSILON),
+            FogMode::Exponential => 1.0 - (-self.density * distance).exp(),
+            FogMode::ExponentialSquared => {
+                let d = self.density * distance;
+                1.0 - (-d * d).exp()
+            }
+            FogMode::Height => {
+                // Density falls off as e^(-falloff * (y - base)), integrated along the view ray
+                let eye_density = (-self.falloff * (eye.y - self.base_height)).exp();
+                let rise = self.falloff * (point.y - eye.y);
+                let integral = if rise.abs() > 1e-4 {
+                    eye_density * (1.0 - (-rise).exp()) / rise
+                } else {
+                    eye_density
+                };
+                1.0 - (-self.density * distance * integral).exp()
+            }
+        };
+        factor.clamp(0.0, 1.0)
+    }
+
+    /// Blends `color` towards the fog color by the fog between `eye` and `point`
+    pub fn apply(&self, color: Color, eye: Vec3, point: Vec3) -> Color {
+        if !self.is_enabled() {
+            return color;
+        }
+        color.lerp(&self.color, self.factor(eye, point))
+    }
+}
@@ -11,6 +11,8 @@ pub struct Material {
     pub ambient: Option<Color>,
     pub diffuse: Option<Color>,
     pub specular: Option<Color>,
+    /// `Ke`, light the surface gives off by itself
+    pub emissive: Option<Color>,
 
     // Material properties
     pub shininess: Option<f32>,
@@ -42,10 +44,16 @@ impl Material {
         
         Self {
             name: mat.name,
-            // Convert [f32; 3] arrays to our Color type
-            ambient: mat.ambient.map(|a| Color::new(a[0], a[1], a[2])),
-            diffuse: mat.diffuse.map(|d| Color::new(d[0], d[1], d[2])),
-            specular: mat.specular.map(|s| Color::new(s[0], s[1], s[2])),
+            // Convert [f32; 3] arrays to our Color type, MTL colors are authored in sRGB
+            ambient: mat.ambient.map(|a| Color::new(a[0], a[1], a[2]).to_linear()),
+            diffuse: mat.diffuse.map(|d| Color::new(d[0], d[1], d[2]).to_linear()),
+            specular: mat.specular.map(|s| Color::new(s[0], s[1], s[2]).to_linear()),
+            // tobj doesn't know about Ke, so it ends up with the unknown parameters
+            emissive: mat
+                .unknown_param
+                .get("Ke")
+                .and_then(|ke| parse_color(ke))
+                .map(|c| c.to_linear()),
 
             shininess: mat.shininess,
             dissolve: mat.dissolve,
@@ -99,17 +107,17 @@ impl Material {
         
         // Load normal texture
         if let Some(ref path) = self.normal_texture {
-            self.normal_texture_data = Some(texture_manager.get_texture(path));
+            self.normal_texture_data = Some(texture_manager.get_linear_texture(path));
         }
         
         // Load shininess texture
         if let Some(ref path) = self.shininess_texture {
-            self.shininess_texture_data = Some(texture_manager.get_texture(path));
+            self.shininess_texture_data = Some(texture_manager.get_linear_texture(path));
         }
         
         // Load dissolve texture
         if let Some(ref path) = self.dissolve_texture {
-            self.dissolve_texture_data = Some(texture_manager.get_texture(path));
+            self.dissolve_texture_data = Some(texture_manager.get_linear_texture(path));
         }
     }
 
@@ -187,6 +195,18 @@ impl Material {
     }
 }
 
+/// Parses an MTL color value ("r g b"), returns None unless there are exactly three numbers
+fn parse_color(value: &str) -> Option<Color> {
+    let parts: Vec<f32> = value
+        .split_whitespace()
+        .map(|p| p.parse().ok())
+        .collect::<Option<_>>()?;
+    match parts[..] {
+        [r, g, b] => Some(Color::new(r, g, b)),
+        _ => None,
+    }
+}
+
 impl Display for Material {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         // TODO: Better pretty printing here
@@ -205,6 +225,7 @@ impl Default for Material {
             ambient: None,
             diffuse: None,
             specular: None,
+            emissive: None,
             shininess: None,
             dissolve: None,
             optical_density: None,
@@ -1,10 +1,10 @@
 use super::Material;
 use crate::core::color::Color;
 use crate::core::TextureManager;
+use crate::error::{check_mtl, Error, Result};
 use glam::{Affine3A, Vec2, Vec3};
 use std::io::{BufRead, BufReader};
 use std::path::{Path, PathBuf};
-use std::sync::{Arc, Mutex};
 
 use std::collections::HashMap;
 
@@ -45,14 +45,13 @@ pub struct Mesh {
     pub name: String,
     pub vertices: Vec<Vertex>,
     // Vertex buffer
-    pub normals: Arc<Mutex<Vec<Vec3>>>,
+    pub normals: Vec<Vec3>,
     // Normal buffer
     pub tris: Vec<Tri>,
     // Triangles
     pub materials: Vec<Material>,
     // Materials if available
-    normals_dirty: Arc<Mutex<bool>>,
-    welded: Arc<Mutex<bool>>,
+    normals_dirty: bool,
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, Hash)]
@@ -80,24 +79,23 @@ impl Mesh {
     pub fn new() -> Self {
         Self {
             vertices: Vec::new(),
-            normals: Arc::new(Mutex::new(Vec::new())),
+            normals: Vec::new(),
             tris: Vec::new(),
             materials: Vec::new(),
-            normals_dirty: Arc::new(Mutex::new(true)),
-            welded: Arc::new(Mutex::new(false)),
+            normals_dirty: true,
             name: String::from(""),
         }
     }
 
-    pub fn mark_normals_dirty(&self) {
-        *self.normals_dirty.lock().unwrap() = true;
+    pub fn mark_normals_dirty(&mut self) {
+        self.normals_dirty = true;
     }
 
-    pub fn update_normals(&self, transform: &Affine3A) {
-        let mut dirty = self.normals_dirty.lock().unwrap();
-        if *dirty {
+    /// Recalculates the world space normals for `transform` if they're out of date
+    pub fn update_normals(&mut self, transform: &Affine3A) {
+        if self.normals_dirty {
             self.fast_recalculate_normals(transform);
-            *dirty = false;
+            self.normals_dirty = false;
         }
     }
 
@@ -106,9 +104,9 @@ impl Mesh {
     }
 
     // TODO: Rename to something like fast_recalculate_normals
-    fn fast_recalculate_normals(&self, transform: &Affine3A) {
+    fn fast_recalculate_normals(&mut self, transform: &Affine3A) {
         // get the normal buffer
-        let mut normals = self.normals.lock().unwrap();
+        let normals = &mut self.normals;
         normals.resize(self.vertices.len(), Vec3::ZERO);
         // Calculate the normal transformation matrix
         let normal_matrix = transform.matrix3.inverse().transpose();
@@ -160,8 +158,8 @@ impl Mesh {
         n
      */
     // TODO: Rename to precise_recalculate_normals
-    fn better_recalculate_normals(&self, transform: &Affine3A) {
-        let mut normals = self.normals.lock().unwrap();
+    fn better_recalculate_normals(&mut self, transform: &Affine3A) {
+        let normals = &mut self.normals;
         normals.resize(self.vertices.len(), Vec3::ZERO);
         let normal_matrix = transform.matrix3.inverse().transpose();
 
@@ -231,11 +229,8 @@ impl Mesh {
     pub fn bake_normals_to_colors(&mut self) {
         self.update_normals(&Affine3A::IDENTITY);
 
-        // Get lock on normals
-        let normals = self.normals.lock().unwrap();
-
         for (i, vertex) in self.vertices.iter_mut().enumerate() {
-            let normal = normals[i];
+            let normal = self.normals[i];
             vertex.color = Some(Color::new(
                 (normal.x + 1.0) * 0.5,
                 (normal.y + 1.0) * 0.5,
@@ -244,43 +239,12 @@ impl Mesh {
         }
     }
 
-    pub fn from_obj(path: &str) -> Self {
-        let (models, materials_result) = tobj::load_obj(
-            path,
-            &tobj::LoadOptions {
-                triangulate: true,
-                single_index: false, // Keep separate indices so we can pair pos/uv correctly
-                ..Default::default()
-            },
-        )
-        .expect("Failed to load OBJ file");
+    /// Loads every model in an OBJ file into a single mesh. Only a missing or broken OBJ is an
+    /// error, material problems are logged and fall back to `Material::default()`.
+    pub fn from_obj(path: &str) -> Result<Self> {
+        let (models, materials) = load_obj(path)?;
 
         let mut outmesh = Mesh::new();
-
-        // Load materials
-        let materials = match materials_result {
-            Ok(mats) => {
-                about_mats(mats.clone(), models.clone());
-                mats.into_iter().map(Material::from_tobj).collect::<Vec<_>>()
-            }
-            Err(_) => {
-                let fallback = load_materials_fallback(path);
-                if fallback.is_empty() {
-                    vec![Material::default()]
-                } else {
-                    fallback
-                }
-            }
-        };
-        let mut materials = materials;
-
-        let base_dir = find_mtl_base_dir(path)
-            .or_else(|| Path::new(path).parent().map(|p| p.to_path_buf()))
-            .unwrap_or_else(|| Path::new(".").to_path_buf());
-        let mut texture_manager = TextureManager::with_base_path(&base_dir.to_string_lossy());
-        for material in materials.iter_mut() {
-            material.load_textures(&mut texture_manager);
-        }
         outmesh.materials = materials;
 
         // Process each model
@@ -288,7 +252,7 @@ impl Mesh {
             let mesh_data = model.mesh;
             outmesh.name = model.name;
 
-            println!(
+            log::debug!(
                 "OBJ Debug | model '{}': positions {} | texcoords {} | normals {} | indices {} (faces {}) | texcoord_indices_present: {}",
                 outmesh.name,
                 mesh_data.positions.len() / 3,
@@ -358,8 +322,7 @@ impl Mesh {
 
             // Load or compute normals
             if !mesh_data.normals.is_empty() {
-                let mut normals = outmesh.normals.lock().unwrap();
-                *normals = mesh_data
+                outmesh.normals = mesh_data
                     .normals
                     .chunks(3)
                     .map(|n| Vec3::new(n[0], n[1], n[2]).normalize())
@@ -369,14 +332,14 @@ impl Mesh {
             }
         }
         if outmesh.needs_weld(0.001) {
-            println!("Welding the Mesh!!! {:?}", outmesh.vertices.len());
+            log::debug!("Welding the Mesh!!! {:?}", outmesh.vertices.len());
             outmesh.weld_vertices(0.001);
-            println!("After Welding new len is {:?}", outmesh.vertices.len());
+            log::debug!("After Welding new len is {:?}", outmesh.vertices.len());
         }
         outmesh.fast_recalculate_normals(&Affine3A::IDENTITY);
         outmesh.print_shared_edges();
 
-        outmesh
+        Ok(outmesh)
     }
 
     fn build_edge_map(&self) -> HashMap<(usize, usize), Vec<usize>> {
@@ -397,17 +360,17 @@ impl Mesh {
         let edge_map = self.build_edge_map();
         for (edge, tri_indices) in edge_map {
             if tri_indices.len() > 1 {
-                println!("Edge {:?} is shared by triangles {:?}", edge, tri_indices);
+                log::trace!("Edge {:?} is shared by triangles {:?}", edge, tri_indices);
                 // Retrieve the normals at the vertices for this edge.
-                let n0 = self.normals.lock().unwrap()[edge.0];
-                let n1 = self.normals.lock().unwrap()[edge.1];
-                println!(
+                let n0 = self.normals[edge.0];
+                let n1 = self.normals[edge.1];
+                log::trace!(
                     "Vertex {} normal: {:?}, Vertex {} normal: {:?}",
                     edge.0, n0, edge.1, n1
                 );
                 // You could also compute the dot product between n0 and n1:
                 let similarity = n0.dot(n1);
-                println!("Similarity (dot product) of normals: {:.3}", similarity);
+                log::trace!("Similarity (dot product) of normals: {:.3}", similarity);
             }
         }
     }
@@ -448,55 +411,22 @@ impl Mesh {
             tri.vertices[2] = idx_map[tri.vertices[2]];
         }
         self.vertices = new_vertices;
-        self.normals.lock().unwrap().clear();
+        self.normals.clear();
         self.mark_normals_dirty();
         true
     }
 
-    pub fn from_obj_to_set(path: &str) -> HashMap<String, Mesh> {
-        let (models, materials_result) = tobj::load_obj(
-            path,
-            &tobj::LoadOptions {
-                triangulate: true,
-                single_index: false,
-                ..Default::default()
-            },
-        )
-        .expect("Failed to load OBJ file");
-
+    /// Like `from_obj`, but with a mesh per model of the file
+    pub fn from_obj_to_set(path: &str) -> Result<HashMap<String, Mesh>> {
+        let (models, materials) = load_obj(path)?;
         let mut meshes: HashMap<String, Mesh> = HashMap::new();
-        if let Ok(ref mats) = materials_result {
-            about_mats(mats.clone(), models.clone());
-        }
-
-        // Load materials
-        let materials = match materials_result {
-            Ok(mats) => mats.into_iter().map(Material::from_tobj).collect::<Vec<_>>(),
-            Err(_) => {
-                let fallback = load_materials_fallback(path);
-                if fallback.is_empty() {
-                    vec![Material::default()]
-                } else {
-                    fallback
-                }
-            }
-        };
-        let mut materials = materials;
-
-        let base_dir = find_mtl_base_dir(path)
-            .or_else(|| Path::new(path).parent().map(|p| p.to_path_buf()))
-            .unwrap_or_else(|| Path::new(".").to_path_buf());
-        let mut texture_manager = TextureManager::with_base_path(&base_dir.to_string_lossy());
-        for material in materials.iter_mut() {
-            material.load_textures(&mut texture_manager);
-        }
 
         // Process each model
         for model in models {
             let mesh_data = model.mesh;
             let mesh_name = model.name.clone();
 
-            println!(
+            log::debug!(
                 "OBJ Debug | model '{}': positions {} | texcoords {} | normals {} | indices {} (faces {}) | texcoord_indices_present: {}",
                 mesh_name,
                 mesh_data.positions.len() / 3,
@@ -516,8 +446,7 @@ impl Mesh {
 
             // Load or compute normals
             if !mesh_data.normals.is_empty() {
-                let mut normals = mesh.normals.lock().unwrap();
-                *normals = mesh_data
+                mesh.normals = mesh_data
                     .normals
                     .chunks(3)
                     .map(|n| Vec3::new(n[0], n[1], n[2]).normalize())
@@ -587,21 +516,21 @@ impl Mesh {
         // Weld vertices in each mesh
         for (name, mesh) in meshes.iter_mut() {
             if mesh.needs_weld(0.001) && !mesh.has_uvs(){
-                println!(
+                log::debug!(
                     "Welding Mesh '{}' with {} vertices",
                     name,
                     mesh.vertices.len()
                 );
                 mesh.weld_vertices(0.001);
-                println!("After welding, new vertex count: {}", mesh.vertices.len());
+                log::debug!("After welding, new vertex count: {}", mesh.vertices.len());
                 mesh.fast_recalculate_normals(&Affine3A::IDENTITY);
             } else {
                 mesh.fast_recalculate_normals(&Affine3A::IDENTITY);
-                println!("Skipped welding because either of these two variables where false: \n Needs Weld: {} \n Has UVs: {} ", mesh.needs_weld(0.001), mesh.has_uvs());
+                log::debug!("Skipped welding '{}' (needs weld: {}, has UVs: {})", name, mesh.needs_weld(0.001), mesh.has_uvs());
             }
         }
 
-        meshes
+        Ok(meshes)
     }
     pub fn needs_weld(&self, position_epsilon: f32) -> bool {
         if self.vertices.is_empty() {
@@ -616,6 +545,14 @@ impl Mesh {
         lookup.len() < self.vertices.len()
     }
 
+    /// Local space axis aligned bounding box (min, max), None for a mesh without vertices
+    pub fn bounds(&self) -> Option<(Vec3, Vec3)> {
+        let first = self.vertices.first()?.pos;
+        Some(self.vertices.iter().fold((first, first), |(min, max), v| {
+            (min.min(v.pos), max.max(v.pos))
+        }))
+    }
+
     pub fn has_uvs(&self) -> bool {
         self.vertices.iter().any(|v| {
             v.uv.is_some()
@@ -657,14 +594,57 @@ impl Mesh {
     }
 }
 
-fn load_materials_fallback(obj_path: &str) -> Vec<Material> {
-    let mut materials = Vec::new();
-    for mtl_path in read_mtllib_paths(obj_path) {
-        if let Ok((mats, _)) = tobj::load_mtl(&mtl_path) {
-            materials.extend(mats.into_iter().map(Material::from_tobj));
+/// Parses an OBJ file and its material libraries, with the materials' textures loaded. Any
+/// problem with the materials gets logged and leaves the default material in their place, so
+/// faces always have something to be shaded with.
+fn load_obj(path: &str) -> Result<(Vec<tobj::Model>, Vec<Material>)> {
+    let file = std::fs::File::open(path).map_err(|e| Error::io(path, e))?;
+    let obj_dir = Path::new(path).parent().unwrap_or_else(|| Path::new("."));
+    let (models, materials_result) = tobj::load_obj_buf(
+        &mut BufReader::new(file),
+        &tobj::LoadOptions {
+            triangulate: true,
+            single_index: false, // Keep separate indices so we can pair pos/uv correctly
+            ..Default::default()
+        },
+        |mtl_path| load_mtl(&obj_dir.join(mtl_path)),
+    )
+    .map_err(|e| Error::parse(path, e))?;
+
+    let mut materials = match materials_result {
+        Ok(mats) if !mats.is_empty() => {
+            about_mats(mats.clone(), models.clone());
+            mats.into_iter().map(Material::from_tobj).collect::<Vec<_>>()
         }
+        // Blender writes an empty material library for models without materials
+        Ok(_) => vec![Material::default()],
+        // Already logged by load_mtl
+        Err(_) => vec![Material::default()],
+    };
+
+    let base_dir = find_mtl_base_dir(path).unwrap_or_else(|| obj_dir.to_path_buf());
+    let mut texture_manager = TextureManager::with_base_path(&base_dir.to_string_lossy());
+    for material in materials.iter_mut() {
+        material.load_textures(&mut texture_manager);
     }
-    materials
+    Ok((models, materials))
+}
+
+/// Material loader handed to tobj, logs whatever goes wrong since tobj drops the path
+fn load_mtl(path: &Path) -> tobj::MTLLoadResult {
+    let result = std::fs::File::open(path)
+        .map_err(|e| Error::io(path, e))
+        .and_then(|file| {
+            check_mtl(path)?;
+            tobj::load_mtl_buf(&mut BufReader::new(file)).map_err(|e| Error::parse(path, e))
+        });
+    result.map_err(|e| {
+        log::warn!("{e}, using the default material instead");
+        match e {
+            Error::Parse { source, .. } => source,
+            _ => tobj::LoadError::OpenFileFailed,
+        }
+    })
 }
 
 fn find_mtl_base_dir(obj_path: &str) -> Option<PathBuf> {
@@ -703,16 +683,16 @@ fn about_mats(materials: Vec<tobj::Material>, models: Vec<tobj::Model>) {
     // Materials might report a separate loading error if the MTL file wasn't found.
     // If you don't need the materials, you can generate a default here and use that
     // instead.
-    println!("# of models: {}", models.len());
-    println!("# of materials: {}", materials.len());
+    log::debug!("# of models: {}", models.len());
+    log::debug!("# of materials: {}", materials.len());
 
     for (i, m) in models.iter().enumerate() {
         let mesh = &m.mesh;
 
-        println!("model[{}].name = \'{}\'", i, m.name);
-        println!("model[{}].mesh.material_id = {:?}", i, mesh.material_id);
+        log::debug!("model[{}].name = \'{}\'", i, m.name);
+        log::debug!("model[{}].mesh.material_id = {:?}", i, mesh.material_id);
 
-        println!(
+        log::debug!(
             "Size of model[{}].face_arities: {}",
             i,
             mesh.face_arities.len()
@@ -722,16 +702,16 @@ fn about_mats(materials: Vec<tobj::Material>, models: Vec<tobj::Model>) {
         for f in 0..mesh.face_arities.len() {
             let end = next_face + mesh.face_arities[f] as usize;
             let face_indices: Vec<_> = mesh.indices[next_face..end].iter().collect();
-            println!("    face[{}] = {:?}", f, face_indices);
+            log::debug!("    face[{}] = {:?}", f, face_indices);
             next_face = end;
         }
 
         // Normals and texture coordinates are also loaded, but not printed in this example
-        println!("model[{}].vertices: {}", i, mesh.positions.len() / 3);
+        log::debug!("model[{}].vertices: {}", i, mesh.positions.len() / 3);
 
         assert!(mesh.positions.len() % 3 == 0);
         for v in 0..mesh.positions.len() / 3 {
-            println!(
+            log::debug!(
                 "    v[{}] = ({}, {}, {})",
                 v,
                 mesh.positions[3 * v],
@@ -742,51 +722,51 @@ fn about_mats(materials: Vec<tobj::Material>, models: Vec<tobj::Model>) {
     }
 
     for (i, m) in materials.iter().enumerate() {
-        println!("material[{}].name = \'{}\'", i, m.name);
+        log::debug!("material[{}].name = \'{}\'", i, m.name);
         if let Some(ambient) = m.ambient {
-            println!(
+            log::debug!(
                 "    material.Ka = ({}, {}, {})",
                 ambient[0], ambient[1], ambient[2]
             );
         }
         if let Some(diffuse) = m.diffuse {
-            println!(
+            log::debug!(
                 "    material.Kd = ({}, {}, {})",
                 diffuse[0], diffuse[1], diffuse[2]
             );
         }
         if let Some(specular) = m.specular {
-            println!(
+            log::debug!(
                 "    material.Ks = ({}, {}, {})",
                 specular[0], specular[1], specular[2]
             );
         }
         if let Some(shininess) = m.shininess {
-            println!("    material.Ns = {}", shininess);
+            log::debug!("    material.Ns = {}", shininess);
         }
         if let Some(dissolve) = m.dissolve {
-            println!("    material.d = {}", dissolve);
+            log::debug!("    material.d = {}", dissolve);
         }
         if let Some(ambient_texture) = &m.ambient_texture {
-            println!("    material.map_Ka = {}", ambient_texture);
+            log::debug!("    material.map_Ka = {}", ambient_texture);
         }
         if let Some(diffuse_texture) = &m.diffuse_texture {
-            println!("    material.map_Kd = {}", diffuse_texture);
+            log::debug!("    material.map_Kd = {}", diffuse_texture);
         }
         if let Some(specular_texture) = &m.specular_texture {
-            println!("    material.map_Ks = {}", specular_texture);
+            log::debug!("    material.map_Ks = {}", specular_texture);
         }
         if let Some(shininess_texture) = &m.shininess_texture {
-            println!("    material.map_Ns = {}", shininess_texture);
+            log::debug!("    material.map_Ns = {}", shininess_texture);
         }
         if let Some(normal_texture) = &m.normal_texture {
-            println!("    material.map_Bump = {}", normal_texture);
+            log::debug!("    material.map_Bump = {}", normal_texture);
         }
         if let Some(dissolve_texture) = &m.dissolve_texture {
-            println!("    material.map_d = {}", dissolve_texture);
+            log::debug!("    material.map_d = {}", dissolve_texture);
         }
         for (k, v) in &m.unknown_param {
-            println!("    material.{} = {}", k, v);
+            log::debug!("    material.{} = {}", k, v);
         }
     }
 }
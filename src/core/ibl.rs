@@ -0,0 +1,228 @@
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+use glam::{Vec2, Vec3};
+use rayon::prelude::*;
+
+use crate::core::sky::{equirect_dir, equirect_uv};
+use crate::core::{Color, Sky, Texture};
+
+// Resolution the sky gets resampled at before projecting/convolving it
+const SOURCE_WIDTH: usize = 96;
+const SOURCE_HEIGHT: usize = 48;
+// Resolution of each prefiltered specular level
+const LEVEL_WIDTH: usize = 48;
+const LEVEL_HEIGHT: usize = 24;
+/// Phong lobe exponent of each prefiltered level, sharpest first
+const LEVEL_EXPONENTS: [f32; 5] = [512.0, 128.0, 32.0, 8.0, 2.0];
+
+/// Image based lighting derived from an environment map (`Sky`)
+///
+/// - Diffuse irradiance is stored as 9 spherical harmonics coefficients (bands 0-2), which is
+///   plenty for a lambertian lobe and costs next to nothing to evaluate per pixel.
+/// - Specular is a small stack of lat-long maps, each convolved with a Phong lobe of decreasing
+///   exponent. Lookups pick (and blend between) the levels closest to the material's shininess.
+#[derive(Clone, Debug)]
+pub struct EnvironmentLight {
+    sh: [Vec3; 9],
+    specular_levels: Vec<Texture>,
+    /// Scales both the diffuse and the specular contribution
+    pub intensity: f32,
+}
+
+struct SkySample {
+    dir: Vec3,
+    radiance: Vec3,
+    solid_angle: f32,
+}
+
+impl EnvironmentLight {
+    /// Returns `None` when there is no sky to take lighting from
+    pub fn from_sky(sky: &Sky, intensity: f32) -> Option<Self> {
+        if sky.is_none() {
+            return None;
+        }
+
+        let samples = resample_sky(sky);
+        let sh = project_sh9(&samples);
+        let specular_levels = LEVEL_EXPONENTS
+            .iter()
+            .map(|&exponent| prefilter_level(&samples, exponent))
+            .collect();
+
+        Some(Self {
+            sh,
+            specular_levels,
+            intensity,
+        })
+    }
+
+    /// Irradiance around `normal` divided by PI, i.e. what a white lambertian surface reflects
+    pub fn diffuse(&self, normal: Vec3) -> Color {
+        let n = normal;
+        let basis = sh9_basis(n);
+        // Convolution with the clamped cosine lobe (Ramamoorthi & Hanrahan)
+        const BAND_SCALE: [f32; 3] = [PI, 2.0 * PI / 3.0, PI / 4.0];
+        let mut irradiance = Vec3::ZERO;
+        for (i, coeff) in self.sh.iter().enumerate() {
+            let band = match i {
+                0 => 0,
+                1..=3 => 1,
+                _ => 2,
+            };
+            irradiance += *coeff * basis[i] * BAND_SCALE[band];
+        }
+        let radiance = (irradiance / PI).max(Vec3::ZERO) * self.intensity;
+        Color::new(radiance.x, radiance.y, radiance.z)
+    }
+
+    /// Prefiltered radiance arriving along `reflect_dir`, blurrier for lower `shininess`
+    pub fn specular(&self, reflect_dir: Vec3, shininess: f32) -> Color {
+        let last = (self.specular_levels.len() - 1) as f32;
+        // Every level is a factor of 4 apart in exponent
+        let level = ((LEVEL_EXPONENTS[0] / shininess.max(1.0)).log2() * 0.5).clamp(0.0, last);
+        let lower = level.floor() as usize;
+        let upper = level.ceil() as usize;
+        let uv = equirect_uv(reflect_dir);
+
+        let a = self.specular_levels[lower].sample(uv);
+        let b = self.specular_levels[upper].sample(uv);
+        a.lerp(&b, level - lower as f32) * self.intensity
+    }
+}
+
+/// Schlick's approximation, `f0` is the reflectance when looking straight at the surface
+pub fn fresnel_schlick(f0: Color, cos_theta: f32) -> Color {
+    let factor = (1.0 - cos_theta.clamp(0.0, 1.0)).powi(5);
+    Color::new(
+        f0.r + (1.0 - f0.r) * factor,
+        f0.g + (1.0 - f0.g) * factor,
+        f0.b + (1.0 - f0.b) * factor,
+    )
+}
+
+/// How much of the environment a material mirrors, MTL illumination models 3-7 all have
+/// reflections turned on, otherwise only fairly shiny surfaces get them
+pub fn reflectivity(shininess: f32, illumination_model: Option<u8>) -> f32 {
+    if matches!(illumination_model, Some(3..=7)) {
+        return 1.0;
+    }
+    ((shininess.max(1.0).log2() - 3.0) / 5.0).clamp(0.0, 1.0)
+}
+
+fn resample_sky(sky: &Sky) -> Vec<SkySample> {
+    let texel_area = (2.0 * PI / SOURCE_WIDTH as f32) * (PI / SOURCE_HEIGHT as f32);
+    (0..SOURCE_HEIGHT)
+        .flat_map(|y| (0..SOURCE_WIDTH).map(move |x| (x, y)))
+        .map(|(x, y)| {
+            let uv = texel_uv(x, y, SOURCE_WIDTH, SOURCE_HEIGHT);
+            let dir = equirect_dir(uv);
+            let color = sky.sample(dir).unwrap_or(Color::BLACK);
+            // Texels shrink towards the poles
+            let elevation = (uv.y - 0.5) * PI;
+            SkySample {
+                dir,
+                radiance: Vec3::new(color.r, color.g, color.b),
+                solid_angle: texel_area * elevation.cos(),
+            }
+        })
+        .collect()
+}
+
+/// Center of texel (x, y) in the UV convention used by `Texture::sample` (row 0 is v = 1)
+fn texel_uv(x: usize, y: usize, width: usize, height: usize) -> Vec2 {
+    Vec2::new(
+        (x as f32 + 0.5) / width as f32,
+        1.0 - (y as f32 + 0.5) / height as f32,
+    )
+}
+
+fn sh9_basis(d: Vec3) -> [f32; 9] {
+    [
+        0.282095,
+        0.488603 * d.y,
+        0.488603 * d.z,
+        0.488603 * d.x,
+        1.092548 * d.x * d.y,
+        1.092548 * d.y * d.z,
+        0.315392 * (3.0 * d.z * d.z - 1.0),
+        1.092548 * d.x * d.z,
+        0.546274 * (d.x * d.x - d.y * d.y),
+    ]
+}
+
+fn project_sh9(samples: &[SkySample]) -> [Vec3; 9] {
+    let mut sh = [Vec3::ZERO; 9];
+    for sample in samples {
+        let basis = sh9_basis(sample.dir);
+        for (coeff, y) in sh.iter_mut().zip(basis.iter()) {
+            *coeff += sample.radiance * *y * sample.solid_angle;
+        }
+    }
+    sh
+}
+
+fn prefilter_level(samples: &[SkySample], exponent: f32) -> Texture {
+    let data: Vec<Color> = (0..LEVEL_WIDTH * LEVEL_HEIGHT)
+        .into_par_iter()
+        .map(|i| {
+            let uv = texel_uv(i % LEVEL_WIDTH, i / LEVEL_WIDTH, LEVEL_WIDTH, LEVEL_HEIGHT);
+            let reflect_dir = equirect_dir(uv);
+            let mut sum = Vec3::ZERO;
+            let mut weight_sum = 0.0;
+            for sample in samples {
+                let cos = reflect_dir.dot(sample.dir);
+                if cos <= 0.0 {
+                    continue;
+                }
+                let weight = cos.powf(exponent) * sample.solid_angle;
+                sum += sample.radiance * weight;
+                weight_sum += weight;
+            }
+            let radiance = if weight_sum > 0.0 {
+                sum / weight_sum
+            } else {
+                Vec3::ZERO
+            };
+            Color::new(radiance.x, radiance.y, radiance.z)
+        })
+        .collect();
+
+    Texture {
+        width: LEVEL_WIDTH as u32,
+        height: LEVEL_HEIGHT as u32,
+        data: Arc::new(data),
+        path: format!("prefiltered_specular_{}", exponent),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uniform_white_sky_gives_unit_diffuse_everywhere() {
+        let sky = Sky::Gradient {
+            zenith: Color::WHITE,
+            horizon: Color::WHITE,
+            ground: Color::WHITE,
+        };
+        let light = EnvironmentLight::from_sky(&sky, 1.0).unwrap();
+        for normal in [
+            Vec3::X,
+            Vec3::Y,
+            -Vec3::Y,
+            Vec3::new(0.3, -0.5, 0.8).normalize(),
+        ] {
+            let diffuse = light.diffuse(normal);
+            assert!(
+                (diffuse.r - 1.0).abs() < 0.02,
+                "{:?} -> {:?}",
+                normal,
+                diffuse
+            );
+        }
+        let spec = light.specular(Vec3::Z, 64.0);
+        assert!((spec.g - 1.0).abs() < 0.02);
+    }
+}
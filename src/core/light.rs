@@ -1,4 +1,5 @@
 use crate::core::geometry::Material;
+use crate::core::ibl::{self, EnvironmentLight};
 use crate::core::Color;
 use glam::Vec3;
 
@@ -218,6 +219,61 @@ impl Default for Light {
 }
 
 impl Light {
+    /// Moves point and spot lights by `offset`. Directional lights have no position, their
+    /// direction gets tilted towards `offset` instead.
+    pub fn nudge(&mut self, offset: Vec3) {
+        match self.light_type {
+            LightType::Directional(ref mut direction) => {
+                *direction = (*direction + offset).normalize_or(*direction);
+            }
+            LightType::Point {
+                ref mut position, ..
+            }
+            | LightType::Spot {
+                ref mut position, ..
+            } => *position += offset,
+        }
+    }
+
+    /// Widens (positive) or narrows the cone of a spot light by `angle` radians, keeping the
+    /// width of its soft edge. Does nothing for other lights.
+    pub fn widen_cone(&mut self, angle: f32) {
+        if let LightType::Spot {
+            ref mut inner_cutoff,
+            ref mut outer_cutoff,
+            ..
+        } = self.light_type
+        {
+            let inner = inner_cutoff.clamp(-1.0, 1.0).acos();
+            let outer = outer_cutoff.clamp(-1.0, 1.0).acos();
+            let edge = (outer - inner).max(0.0);
+            let outer = (outer + angle).clamp(edge + 0.01, 89f32.to_radians());
+            *outer_cutoff = outer.cos();
+            *inner_cutoff = (outer - edge).cos();
+        }
+    }
+
+    /// Multiplies the linear and quadratic attenuation, above 1 the light reaches less far.
+    /// Does nothing for directional lights.
+    pub fn scale_attenuation(&mut self, factor: f32) {
+        match self.light_type {
+            LightType::Directional(_) => {}
+            LightType::Point {
+                ref mut linear,
+                ref mut quadratic,
+                ..
+            }
+            | LightType::Spot {
+                ref mut linear,
+                ref mut quadratic,
+                ..
+            } => {
+                *linear *= factor;
+                *quadratic *= factor;
+            }
+        }
+    }
+
     pub fn orbit(&mut self, center: Vec3, radius: f32, speed: f32, delta: f32) {
         if let LightType::Point {
             ref mut position, ..
@@ -231,27 +287,37 @@ impl Light {
     }
 }
 
+/// Everything `LightingModel::shade` needs to know about the fragment being shaded
+#[derive(Clone, Copy, Debug)]
+pub struct ShadeInput<'a> {
+    /// Albedo color from the GBuffer
+    pub albedo: Color,
+    /// World space normal from the GBuffer
+    pub normal: Vec3,
+    /// Specular color from the GBuffer
+    pub specular: Color,
+    pub shininess: f32,
+    /// World space position of the fragment
+    pub position: Vec3,
+    /// Normalized direction from the fragment to the camera
+    pub view_dir: Vec3,
+    /// Material properties, fetched from the GBuffer's material ids
+    pub material: Option<&'a Material>,
+    /// Ambient occlusion factor (1.0 = unoccluded), scales every ambient term
+    pub occlusion: f32,
+}
+
 pub trait LightingModel {
     /// Computes the final color for a pixel given the GBuffer data and scene lighting.
     ///
-    /// - `albedo`: The albedo color from the GBuffer.
-    /// - `normal`: The world-space normal from the GBuffer.
-    /// - `specular_color`: The specular color from the GBuffer.
-    /// - `shininess`: The shininess value from the GBuffer.
-    /// - `frag_pos`: the world-space position of the fragment.
-    /// - `view_dir`: the normalized direction from the fragment to the camera.
+    /// - `input`: the fragment, see `ShadeInput`.
     /// - `lights`: a slice of lights in the scene.
-    /// - `material`: material properties (can be defaulted or fetched from GBuffer indices if material IDs are stored).
+    /// - `environment`: image based lighting, replaces the per-light ambient term when present.
     fn shade(
         &self,
-        albedo: Color,
-        normal: Vec3,
-        specular_color: Color,
-        shininess: f32,
-        frag_pos: Vec3,
-        view_dir: Vec3,
+        input: ShadeInput,
         lights: &[Light],
-        material: Option<&Material>, // Still keeping material for potential complex materials
+        environment: Option<&EnvironmentLight>,
     ) -> Color;
 }
 
@@ -259,22 +325,37 @@ pub trait LightingModel {
 pub enum LightMode {
     Flat,
     BlinnPhong,
+    Toon,
     None,
 }
+
+impl LightMode {
+    /// Next shaded mode, `None` is left out of the cycle
+    pub fn next(&self) -> Self {
+        match self {
+            LightMode::Flat => LightMode::BlinnPhong,
+            LightMode::BlinnPhong => LightMode::Toon,
+            LightMode::Toon | LightMode::None => LightMode::Flat,
+        }
+    }
+}
 pub struct FlatShading;
 
 impl LightingModel for FlatShading {
     fn shade(
         &self,
-        albedo: Color,
-        normal: Vec3,
-        _specular_color: Color, // Not used in flat shading
-        _shininess: f32,        // Not used in flat shading
-        frag_pos: Vec3,
-        _view_dir: Vec3, // Unused in flat shading
+        input: ShadeInput,
         lights: &[Light],
-        material: Option<&Material>,
+        environment: Option<&EnvironmentLight>,
     ) -> Color {
+        let ShadeInput {
+            albedo,
+            normal,
+            position: frag_pos,
+            material,
+            occlusion,
+            ..
+        } = input;
         let mut final_color = Color::BLACK;
 
         // Prefer the albedo coming from the GBuffer (which already contains
@@ -294,9 +375,15 @@ impl LightingModel for FlatShading {
             })
             .unwrap_or(albedo);
 
+        if let Some(env) = environment {
+            final_color += diffuse * env.diffuse(normal) * occlusion;
+        }
+
         for light in lights {
             // Accumulate ambient component - it's constant for flat shading typically
-            final_color += ambient * light.color * light.intensity; // Ambient is affected by light color & intensity
+            if environment.is_none() {
+                final_color += ambient * light.color * light.intensity * occlusion; // Ambient is affected by light color & intensity
+            }
 
             match light.light_type {
                 LightType::Directional(direction) => {
@@ -360,7 +447,7 @@ impl LightingModel for FlatShading {
                 }
             }
         }
-        final_color.clamped()
+        final_color
     }
 }
 
@@ -368,15 +455,20 @@ pub struct BlinnPhongShading;
 impl LightingModel for BlinnPhongShading {
     fn shade(
         &self,
-        albedo: Color,
-        normal: Vec3,
-        specular_color: Color,
-        shininess: f32,
-        frag_pos: Vec3,
-        view_dir: Vec3,
+        input: ShadeInput,
         lights: &[Light],
-        material: Option<&Material>,
+        environment: Option<&EnvironmentLight>,
     ) -> Color {
+        let ShadeInput {
+            albedo,
+            normal,
+            specular: specular_color,
+            shininess,
+            position: frag_pos,
+            view_dir,
+            material,
+            occlusion,
+        } = input;
         let mut final_color: Color = (0.0, 0.0, 0.0).into();
         // Keep the albedo/specular coming from the rasterizer (includes textures).
         let ambient = material
@@ -404,9 +496,24 @@ impl LightingModel for BlinnPhongShading {
             .unwrap_or(specular_color);
         // Shininess is already from GBuffer input
 
+        if let Some(env) = environment {
+            final_color += diffuse * env.diffuse(normal) * occlusion;
+
+            let reflectivity =
+                ibl::reflectivity(shininess, material.and_then(|m| m.illumination_model));
+            if reflectivity > 0.0 {
+                let reflect_dir = (-view_dir).reflect(normal);
+                let fresnel = ibl::fresnel_schlick(specular, normal.dot(view_dir));
+                final_color +=
+                    env.specular(reflect_dir, shininess) * fresnel * (reflectivity * occlusion);
+            }
+        }
+
         for light in lights {
             // Accumulate ambient component
-            final_color += ambient * light.color * light.intensity; // Ambient unaffected by light type usually
+            if environment.is_none() {
+                final_color += ambient * light.color * light.intensity * occlusion; // Ambient unaffected by light type usually
+            }
 
             match light.light_type {
                 LightType::Directional(direction) => {
@@ -498,6 +605,148 @@ impl LightingModel for BlinnPhongShading {
             }
         }
 
-        final_color.clamped()
+        final_color
+    }
+}
+
+/// Cel shading, diffuse and specular are snapped to a few flat bands
+#[derive(Clone, Copy, Debug)]
+pub struct ToonShading {
+    /// Number of diffuse bands, surfaces facing away from every light still get the ambient term
+    pub diffuse_bands: u32,
+    /// Number of specular bands, 1 gives a single hard edged highlight
+    pub specular_bands: u32,
+}
+
+impl Default for ToonShading {
+    fn default() -> Self {
+        Self {
+            diffuse_bands: 3,
+            specular_bands: 1,
+        }
+    }
+}
+
+impl ToonShading {
+    fn quantize_diffuse(&self, value: f32) -> f32 {
+        let bands = self.diffuse_bands.max(1) as f32;
+        (value * bands).ceil() / bands
+    }
+
+    fn quantize_specular(&self, value: f32) -> f32 {
+        let bands = self.specular_bands.max(1) as f32;
+        ((value * bands + 0.5).floor() / bands).min(1.0)
+    }
+}
+
+/// Direction from `frag_pos` to the light and the light's attenuation/cone falloff there,
+/// None when the fragment is outside of a spot light's cone
+fn incidence(light: &Light, frag_pos: Vec3) -> Option<(Vec3, f32)> {
+    match light.light_type {
+        LightType::Directional(direction) => Some((-direction.normalize(), 1.0)),
+        LightType::Point {
+            position,
+            constant,
+            linear,
+            quadratic,
+        } => {
+            let light_vec = position - frag_pos;
+            let distance = light_vec.length();
+            let attenuation =
+                1.0 / (constant + linear * distance + quadratic * distance * distance).max(1.0);
+            Some((light_vec.normalize(), attenuation))
+        }
+        LightType::Spot {
+            position,
+            direction,
+            inner_cutoff,
+            outer_cutoff,
+            constant,
+            linear,
+            quadratic,
+        } => {
+            let light_vec = position - frag_pos;
+            let light_dir = light_vec.normalize();
+            let distance = light_vec.length();
+            let attenuation =
+                1.0 / (constant + linear * distance + quadratic * distance * distance).max(1.0);
+            let spot_factor = light_dir.dot((-direction).normalize());
+            if spot_factor <= outer_cutoff {
+                return None;
+            }
+            let cone = ((spot_factor - outer_cutoff) / (inner_cutoff - outer_cutoff)).clamp(0.0, 1.0);
+            Some((light_dir, attenuation * cone))
+        }
+    }
+}
+
+impl LightingModel for ToonShading {
+    fn shade(
+        &self,
+        input: ShadeInput,
+        lights: &[Light],
+        environment: Option<&EnvironmentLight>,
+    ) -> Color {
+        let ShadeInput {
+            albedo,
+            normal,
+            specular: specular_color,
+            shininess,
+            position: frag_pos,
+            view_dir,
+            material,
+            occlusion,
+        } = input;
+        let mut final_color = Color::BLACK;
+        let ambient = material
+            .and_then(|m| m.ambient)
+            .unwrap_or(Color::DARK_GRAY);
+        let diffuse = material
+            .map(|m| {
+                if m.diffuse_texture_data.is_some() {
+                    albedo
+                } else {
+                    m.diffuse.unwrap_or(albedo)
+                }
+            })
+            .unwrap_or(albedo);
+        let specular = material
+            .map(|m| {
+                if m.specular_texture_data.is_some() {
+                    specular_color
+                } else {
+                    m.specular.unwrap_or(specular_color)
+                }
+            })
+            .unwrap_or(specular_color);
+
+        if let Some(env) = environment {
+            final_color += diffuse * env.diffuse(normal) * occlusion;
+        }
+
+        for light in lights {
+            if environment.is_none() {
+                final_color += ambient * light.color * light.intensity * occlusion;
+            }
+
+            let Some((light_dir, falloff)) = incidence(light, frag_pos) else {
+                continue;
+            };
+            // Quantize the final light amount rather than the cosine, so distance falloff
+            // also steps instead of smearing the bands
+            let diff_factor = self.quantize_diffuse(normal.dot(light_dir).max(0.0) * falloff);
+            let halfway_dir = (light_dir + view_dir).normalize();
+            let spec_factor = if diff_factor > 0.0 {
+                self.quantize_specular(normal.dot(halfway_dir).max(0.0).powf(shininess) * falloff)
+            } else {
+                0.0
+            };
+
+            final_color += (diffuse * diff_factor + specular * spec_factor)
+                * light.color
+                * light.intensity;
+        }
+
+        final_color
     }
 }
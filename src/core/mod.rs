@@ -2,11 +2,19 @@ use std::f32::consts::PI;
 
 mod camera;
 mod color;
+mod environment;
+mod fog;
+mod ibl;
 mod scene;
+mod sky;
 mod texture;
 mod input;
 pub use scene::Entity;
-pub use scene::{Background, Environment, RenderMode, Scene};
+pub use environment::{Background, Environment};
+pub use fog::{Fog, FogMode};
+pub use ibl::EnvironmentLight;
+pub use scene::{RenderMode, Scene};
+pub use sky::Sky;
 
 pub use color::Color;
 
@@ -33,5 +41,7 @@ pub use light::Light;
 pub use light::LightMode;
 pub use light::LightingModel;
 pub use light::LightType;
+pub use light::ShadeInput;
+pub use light::ToonShading;
 
 pub use texture::{Texture, TextureFilter, TextureManager};
@@ -2,23 +2,37 @@ use crate::core::light::Light;
 use std::{
     fmt::{self, Display, Formatter},
     path::PathBuf,
-    sync::{Arc, Mutex},
 };
 
 use crate::core::camera::Camera;
 
 use glam::{Affine3A, Vec3};
 
+use crate::core::Environment;
+use crate::error::Result;
 use crate::geometry::Mesh;
-use crate::core::TextureManager;
-
-use super::{geometry::Material, Color};
 
 #[derive(Clone, Debug, Copy)]
 pub enum RenderMode {
     Solid,
+    /// Every edge, including the ones behind the mesh
     Wireframe,
     FixedPoint,
+    /// Only the edges that aren't hidden behind the mesh's (invisible) surfaces
+    HiddenLine,
+    /// Solid shading with the edges drawn on top
+    SolidWireframe,
+}
+
+impl RenderMode {
+    pub fn next(&self) -> Self {
+        match self {
+            RenderMode::Solid => RenderMode::Wireframe,
+            RenderMode::Wireframe => RenderMode::HiddenLine,
+            RenderMode::HiddenLine => RenderMode::SolidWireframe,
+            RenderMode::SolidWireframe | RenderMode::FixedPoint => RenderMode::Solid,
+        }
+    }
 }
 
 impl Display for RenderMode {
@@ -27,6 +41,8 @@ impl Display for RenderMode {
             RenderMode::Wireframe => write!(f, "Wireframe"),
             RenderMode::FixedPoint => write!(f, "Fixed Point"),
             RenderMode::Solid => write!(f, "Standard"),
+            RenderMode::HiddenLine => write!(f, "Hidden Line"),
+            RenderMode::SolidWireframe => write!(f, "Solid + Wire"),
         }
     }
 }
@@ -44,32 +60,7 @@ pub struct Entity {
     pub name: String,
     pub mesh: Mesh,
     transform: glam::Affine3A,
-    render_mode: Arc<Mutex<RenderMode>>,
-}
-
-#[derive(Clone)]
-// FIX: Doesnt work !!!!!!!!!!
-pub struct Environment {
-    pub background: Background,
-    // TODO: lighting
-}
-
-#[derive(Clone)]
-pub enum Background {
-    Void, // Nothing
-    BlenderFloor {
-        size: i32,
-        cell_size: f32,
-
-        primary_color: Color,
-        secondary_color: Color,
-    },
-    Room {
-        size: i32,
-        cell_size: f32,
-
-        wall_colors: [Color; 4],
-    },
+    render_mode: RenderMode,
 }
 
 impl Display for Entity {
@@ -83,26 +74,27 @@ impl Entity {
         Self {
             mesh,
             transform,
-            render_mode: Arc::new(Mutex::new(RenderMode::Solid)),
+            render_mode: RenderMode::Solid,
             name,
         }
     }
 
-    pub fn from_obj(path: &str) -> Self {
-        let mesh: Mesh = Mesh::from_obj(path);
+    pub fn from_obj(path: &str) -> Result<Self> {
+        let mesh: Mesh = Mesh::from_obj(path)?;
         // make name be last part of path
         let name = path.split("/").last().unwrap().to_string();
 
-        Self {
+        Ok(Self {
             mesh,
             transform: Affine3A::IDENTITY,
-            render_mode: Arc::new(Mutex::new(RenderMode::Solid)),
+            render_mode: RenderMode::Solid,
             name,
-        }
+        })
     }
 
-    pub fn from_obj_set(path: &str) -> Vec<Self> {
-        let meshes = Mesh::from_obj_to_set(path);
+    /// An entity per model in the OBJ file
+    pub fn from_obj_set(path: &str) -> Result<Vec<Self>> {
+        let meshes = Mesh::from_obj_to_set(path)?;
         let mut out = Vec::new();
 
         for (_, mesh) in meshes {
@@ -110,10 +102,10 @@ impl Entity {
                 name: mesh.name.clone(),
                 mesh,
                 transform: Affine3A::IDENTITY,
-                render_mode: Arc::new(Mutex::new(RenderMode::Solid)),
+                render_mode: RenderMode::Solid,
             })
         }
-        out
+        Ok(out)
     }
 
     pub fn transform(&self) -> &Affine3A {
@@ -125,41 +117,57 @@ impl Entity {
         self.mesh.mark_normals_dirty();
     }
 
-    pub fn update(&self) {
+    /// Brings the mesh's world space normals up to date with the transform
+    pub fn update(&mut self) {
         self.mesh.update_normals(&self.transform);
     }
 
-    pub fn from_obj_with_transform(path: &str, transform: Affine3A) -> Self {
-        let mesh: Mesh = Mesh::from_obj(path);
+    /// World space axis aligned box around the transformed mesh bounds
+    pub fn world_bounds(&self) -> Option<(Vec3, Vec3)> {
+        let (min, max) = self.mesh.bounds()?;
+        let corners = (0..8).map(|i| {
+            let corner = Vec3::new(
+                if i & 1 == 0 { min.x } else { max.x },
+                if i & 2 == 0 { min.y } else { max.y },
+                if i & 4 == 0 { min.z } else { max.z },
+            );
+            self.transform.transform_point3(corner)
+        });
+        corners.fold(None, |bounds, p| match bounds {
+            None => Some((p, p)),
+            Some((min, max)) => Some((min.min(p), max.max(p))),
+        })
+    }
+
+    pub fn from_obj_with_transform(path: &str, transform: Affine3A) -> Result<Self> {
+        let mesh: Mesh = Mesh::from_obj(path)?;
         let name = path.split("/").last().unwrap().to_string();
-        Self {
+        Ok(Self {
             mesh,
             transform,
-            render_mode: Arc::new(Mutex::new(RenderMode::Solid)),
+            render_mode: RenderMode::Solid,
             name,
-        }
+        })
     }
 
-    pub fn from_obj_with_scale(path: &str, scale: f32) -> Self {
-        let mesh: Mesh = Mesh::from_obj(path);
+    pub fn from_obj_with_scale(path: &str, scale: f32) -> Result<Self> {
+        let mesh: Mesh = Mesh::from_obj(path)?;
         let transform: Affine3A = Affine3A::from_scale(glam::Vec3::splat(scale));
         let name = path.split("/").last().unwrap().to_string();
-        Self {
+        Ok(Self {
             mesh,
             transform,
-            render_mode: Arc::new(Mutex::new(RenderMode::Solid)),
+            render_mode: RenderMode::Solid,
             name,
-        }
+        })
     }
 
-    pub fn set_render_mode(&self, mode: RenderMode) {
-        if let Ok(mut current_mode) = self.render_mode.lock() {
-            *current_mode = mode;
-        }
+    pub fn set_render_mode(&mut self, mode: RenderMode) {
+        self.render_mode = mode;
     }
 
-    pub fn render_mode(&self) -> Arc<Mutex<RenderMode>> {
-        Arc::clone(&self.render_mode)
+    pub fn render_mode(&self) -> RenderMode {
+        self.render_mode
     }
 }
 
@@ -168,6 +176,7 @@ pub struct Scene {
     pub camera: Camera,
     pub entities: Vec<Entity>,
     pub lights: Vec<Light>,
+    pub environment: Environment,
 }
 
 impl Scene {
@@ -176,6 +185,7 @@ impl Scene {
             camera,
             entities: Vec::new(),
             lights: Vec::new(),
+            environment: Environment::default(),
         }
     }
 
@@ -183,6 +193,38 @@ impl Scene {
         self.entities.push(entity);
     }
 
+    pub fn set_environment(&mut self, environment: Environment) {
+        self.environment = environment;
+    }
+
+    /// Looks up an entity by the id used in `ProcessedGeometry`/the GBuffer.
+    /// Ids past the end of `entities` refer to the environment's generated entities.
+    pub fn entity(&self, id: usize) -> &Entity {
+        if id < self.entities.len() {
+            &self.entities[id]
+        } else {
+            &self.environment.entities()[id - self.entities.len()]
+        }
+    }
+
+    /// Scene entities followed by environment entities, indexed the same way as `entity`
+    pub fn renderable_entities(&self) -> impl Iterator<Item = &Entity> {
+        self.entities
+            .iter()
+            .chain(self.environment.entities().iter())
+    }
+
+    /// Updates every renderable entity's normals, the pipeline calls this before processing
+    /// geometry so the rest of the frame only needs to read the scene
+    pub fn update(&mut self) {
+        for entity in &mut self.entities {
+            entity.update();
+        }
+        for entity in self.environment.entities_mut() {
+            entity.update();
+        }
+    }
+
     pub fn spin(&mut self, entity: usize) {
         self.entities[entity].transform *= glam::Affine3A::from_rotation_y(0.03);
         self.entities[entity].transform *= glam::Affine3A::from_rotation_x(0.01);
@@ -204,6 +246,7 @@ impl Default for Scene {
             camera: cam,
             entities: Vec::new(),
             lights: Vec::new(),
+            environment: Environment::default(),
         }
     }
 }
@@ -212,7 +255,7 @@ impl Entity {
     // Adding default intity constructors for some of the files located within the assets folder,
     // will make testing / debugging a bit easier
     //
-    pub fn new_icosphere() -> Vec<Self> {
+    pub fn new_icosphere() -> Result<Vec<Self>> {
         let icos = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
             .join("assets")
             .join("models")
@@ -220,7 +263,7 @@ impl Entity {
         Self::from_obj_set(icos.to_str().unwrap())
     }
 
-    pub fn new_suzanne() -> Vec<Self> {
+    pub fn new_suzanne() -> Result<Vec<Self>> {
         let monkey_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
             .join("assets")
             .join("models")
@@ -228,7 +271,7 @@ impl Entity {
         Self::from_obj_set(monkey_path.to_str().unwrap())
     }
 
-    pub fn new_penguin() -> Vec<Self> {
+    pub fn new_penguin() -> Result<Vec<Self>> {
         let penguin_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
             .join("assets")
             .join("models")
@@ -240,7 +283,7 @@ impl Entity {
         entities
     }
 
-    pub fn new_thwomp() -> Vec<Self> {
+    pub fn new_thwomp() -> Result<Vec<Self>> {
         let thwomp_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
             .join("assets")
             .join("models")
@@ -248,7 +291,7 @@ impl Entity {
             .join("Thwomp-Classic [Sm64].obj");
         Self::from_obj_set(thwomp_path.to_str().unwrap())
     }
-    pub fn new_teapot() -> Vec<Self> {
+    pub fn new_teapot() -> Result<Vec<Self>> {
         let teapot = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
             .join("assets")
             .join("models")
@@ -257,7 +300,7 @@ impl Entity {
         Self::from_obj_set(teapot.to_str().unwrap())
     }
 
-    pub fn new_textured_teapot() -> Vec<Self> {
+    pub fn new_textured_teapot() -> Result<Vec<Self>> {
         let teapot_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
             .join("assets")
             .join("models")
@@ -267,21 +310,21 @@ impl Entity {
         Self::from_obj_set(teapot_path.to_str().unwrap())
     }
 
-    pub fn new_skull() -> Vec<Self> {
+    pub fn new_skull() -> Result<Vec<Self>> {
         let skull = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
             .join("assets")
             .join("models")
             .join("skull.obj");
         Self::from_obj_set(skull.to_str().unwrap())
     }
-    pub fn new_ferris() -> Vec<Self> {
+    pub fn new_ferris() -> Result<Vec<Self>> {
         let ferris = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
             .join("assets")
             .join("models")
             .join("ferris.obj");
         Self::from_obj_set(ferris.to_str().unwrap())
     }
-    pub fn new_sphere() -> Vec<Self> {
+    pub fn new_sphere() -> Result<Vec<Self>> {
         let sphere = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
             .join("assets")
             .join("models")
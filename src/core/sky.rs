@@ -0,0 +1,128 @@
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+use glam::{Vec2, Vec3};
+
+use crate::core::{Color, Texture};
+use crate::error::Result;
+
+/// What gets drawn in pixels that no geometry covered
+#[derive(Clone, Debug, Default)]
+pub enum Sky {
+    /// Leave uncovered pixels at the buffer's clear color
+    #[default]
+    None,
+    /// Simple vertical gradient, blends from `horizon` up to `zenith` and down to `ground`
+    Gradient {
+        zenith: Color,
+        horizon: Color,
+        ground: Color,
+    },
+    /// Single lat-long panorama (2:1 aspect ratio)
+    Equirectangular(Arc<Texture>),
+    /// Six faces in the order +X, -X, +Y, -Y, +Z, -Z
+    Cubemap(Arc<[Texture; 6]>),
+}
+
+impl Sky {
+    pub fn default_gradient() -> Self {
+        // Picked as sRGB, sky colors are linear like everything else in the lighting pass
+        Sky::Gradient {
+            zenith: Color::new(0.18, 0.36, 0.68).to_linear(),
+            horizon: Color::new(0.75, 0.82, 0.9).to_linear(),
+            ground: Color::new(0.22, 0.2, 0.18).to_linear(),
+        }
+    }
+
+    pub fn equirectangular_from_file(path: &str) -> Result<Self> {
+        let texture = Texture::load_from_file(path)?;
+        Ok(Sky::Equirectangular(Arc::new(texture)))
+    }
+
+    /// Loads a skybox, paths are expected in the order +X, -X, +Y, -Y, +Z, -Z
+    pub fn cubemap_from_files(paths: [&str; 6]) -> Result<Self> {
+        let [px, nx, py, ny, pz, nz] = paths;
+        Ok(Sky::Cubemap(Arc::new([
+            Texture::load_from_file(px)?,
+            Texture::load_from_file(nx)?,
+            Texture::load_from_file(py)?,
+            Texture::load_from_file(ny)?,
+            Texture::load_from_file(pz)?,
+            Texture::load_from_file(nz)?,
+        ])))
+    }
+
+    pub fn is_none(&self) -> bool {
+        matches!(self, Sky::None)
+    }
+
+    /// Color seen when looking along the (normalized) world space direction `dir`
+    pub fn sample(&self, dir: Vec3) -> Option<Color> {
+        match self {
+            Sky::None => None,
+            Sky::Gradient {
+                zenith,
+                horizon,
+                ground,
+            } => {
+                // sqrt keeps the horizon band fairly thin
+                if dir.y >= 0.0 {
+                    Some(horizon.lerp(zenith, dir.y.sqrt()))
+                } else {
+                    Some(horizon.lerp(ground, (-dir.y).sqrt()))
+                }
+            }
+            Sky::Equirectangular(texture) => Some(texture.sample(equirect_uv(dir))),
+            Sky::Cubemap(faces) => {
+                let (face, uv) = cubemap_face_uv(dir);
+                Some(faces[face].sample(uv))
+            }
+        }
+    }
+}
+
+/// Lat-long lookup, -Z (the default camera forward) lands in the middle of the image
+pub(crate) fn equirect_uv(dir: Vec3) -> Vec2 {
+    let u = 0.5 + dir.x.atan2(-dir.z) / (2.0 * PI);
+    let v = 0.5 + dir.y.clamp(-1.0, 1.0).asin() / PI;
+    Vec2::new(u, v)
+}
+
+/// Inverse of `equirect_uv`
+pub(crate) fn equirect_dir(uv: Vec2) -> Vec3 {
+    let phi = (uv.x - 0.5) * 2.0 * PI;
+    let elevation = (uv.y - 0.5) * PI;
+    Vec3::new(
+        elevation.cos() * phi.sin(),
+        elevation.sin(),
+        -elevation.cos() * phi.cos(),
+    )
+}
+
+/// Picks the cube face by the major axis of `dir` and returns the face index and its UV.
+/// Follows the usual OpenGL cubemap layout.
+fn cubemap_face_uv(dir: Vec3) -> (usize, Vec2) {
+    let abs = dir.abs();
+    let (face, sc, tc, ma) = if abs.x >= abs.y && abs.x >= abs.z {
+        if dir.x > 0.0 {
+            (0, -dir.z, -dir.y, abs.x)
+        } else {
+            (1, dir.z, -dir.y, abs.x)
+        }
+    } else if abs.y >= abs.z {
+        if dir.y > 0.0 {
+            (2, dir.x, dir.z, abs.y)
+        } else {
+            (3, dir.x, -dir.z, abs.y)
+        }
+    } else if dir.z > 0.0 {
+        (4, dir.x, -dir.y, abs.z)
+    } else {
+        (5, -dir.x, -dir.y, abs.z)
+    };
+
+    let u = (sc / ma + 1.0) * 0.5;
+    let v = (tc / ma + 1.0) * 0.5;
+    // Texture::sample treats v = 1 as the top row, the cubemap convention has t growing downwards
+    (face, Vec2::new(u, 1.0 - v))
+}
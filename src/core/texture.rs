@@ -1,4 +1,5 @@
 use crate::core::Color;
+use crate::error::{Error, Result};
 use glam::Vec2;
 use image::{ImageBuffer, Rgb, RgbImage};
 use std::collections::HashMap;
@@ -15,11 +16,25 @@ pub struct Texture {
 }
 
 impl Texture {
-    /// Load a texture from a file path
-    pub fn load_from_file(path: &str) -> Result<Self, String> {
+    /// Load a color texture from a file path, texels are decoded from sRGB into linear space
+    pub fn load_from_file(path: &str) -> Result<Self> {
+        Self::load(path, true)
+    }
+
+    /// Load a texture holding non-color data (normal maps, shininess, dissolve) as-is
+    pub fn load_linear_from_file(path: &str) -> Result<Self> {
+        Self::load(path, false)
+    }
+
+    fn load(path: &str, srgb: bool) -> Result<Self> {
         // Try to load the image
-        let img = image::open(path)
-            .map_err(|e| format!("Failed to load texture '{}': {}", path, e))?;
+        let img = image::open(path).map_err(|e| match e {
+            image::ImageError::IoError(source) => Error::io(path, source),
+            source => Error::Image {
+                path: path.into(),
+                source,
+            },
+        })?;
         
         // Convert to RGB8 format
         let rgb_img = img.to_rgb8();
@@ -31,7 +46,8 @@ impl Texture {
             let r = pixel[0] as f32 / 255.0;
             let g = pixel[1] as f32 / 255.0;
             let b = pixel[2] as f32 / 255.0;
-            color_data.push(Color::new(r, g, b));
+            let color = Color::new(r, g, b);
+            color_data.push(if srgb { color.to_linear() } else { color });
         }
         
         Ok(Texture {
@@ -149,39 +165,64 @@ impl TextureManager {
         }
     }
     
-    /// Load a texture and cache it
-    pub fn load_texture(&mut self, path: &str) -> Result<Arc<Texture>, String> {
+    /// Load a color (sRGB) texture and cache it
+    pub fn load_texture(&mut self, path: &str) -> Result<Arc<Texture>> {
+        self.load_texture_as(path, true)
+    }
+
+    /// Load a data (linear) texture and cache it
+    pub fn load_linear_texture(&mut self, path: &str) -> Result<Arc<Texture>> {
+        self.load_texture_as(path, false)
+    }
+
+    fn load_texture_as(&mut self, path: &str, srgb: bool) -> Result<Arc<Texture>> {
         let normalized_path = normalize_texture_path(path);
+        // The same image decoded both ways are two different textures
+        let cache_key = if srgb {
+            normalized_path.clone()
+        } else {
+            format!("{} (linear)", normalized_path)
+        };
 
         // Check if already loaded
-        if let Some(texture) = self.textures.get(&normalized_path) {
+        if let Some(texture) = self.textures.get(&cache_key) {
             return Ok(texture.clone());
         }
 
         let full_path = self
             .resolve_texture_path(&normalized_path)
-            .ok_or_else(|| {
-                format!(
-                    "Texture '{}' not found (base path: '{}')",
-                    normalized_path, self.base_path
-                )
+            .ok_or_else(|| Error::TextureNotFound {
+                path: normalized_path.clone().into(),
+                base: self.base_path.clone().into(),
             })?;
 
-        let texture = Texture::load_from_file(full_path.to_string_lossy().as_ref())?;
+        let texture = Texture::load(full_path.to_string_lossy().as_ref(), srgb)?;
         let arc_texture = Arc::new(texture);
         
         // Cache the loaded texture
         self.textures
-            .insert(normalized_path, arc_texture.clone());
+            .insert(cache_key, arc_texture.clone());
         Ok(arc_texture)
     }
     
-    /// Get a texture from cache, loading it if necessary
+    /// Get a texture from cache, loading it if necessary. A texture that fails to load is
+    /// logged and replaced by plain white.
     pub fn get_texture(&mut self, path: &str) -> Arc<Texture> {
         match self.load_texture(path) {
             Ok(texture) => texture,
             Err(e) => {
-                println!("Warning: Failed to load texture '{}': {}", path, e);
+                log::warn!("{e}, using plain white instead");
+                Arc::new(Texture::default_white())
+            }
+        }
+    }
+
+    /// Same as `get_texture`, but for textures that hold data rather than color
+    pub fn get_linear_texture(&mut self, path: &str) -> Arc<Texture> {
+        match self.load_linear_texture(path) {
+            Ok(texture) => texture,
+            Err(e) => {
+                log::warn!("{e}, using plain white instead");
                 Arc::new(Texture::default_white())
             }
         }
@@ -198,11 +239,11 @@ impl TextureManager {
     }
 
     pub fn print_about(&self) {
-        println!("TextureManager: Loaded {} textures.", self.textures.len());
-        println!("Base path: {}", self.base_path);
-        println!("Textures:");
+        log::info!("TextureManager: Loaded {} textures.", self.textures.len());
+        log::info!("Base path: {}", self.base_path);
+        log::info!("Textures:");
         for (path, texture) in &self.textures {
-            println!(" - {} ({}x{}), colorlen: {}", path, texture.width, texture.height, texture.data.len());
+            log::info!(" - {} ({}x{}), colorlen: {}", path, texture.width, texture.height, texture.data.len());
         }
 
     }
@@ -0,0 +1,226 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+use tobj::LoadError;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Everything that can go wrong loading models, materials and textures
+#[derive(Debug)]
+pub enum Error {
+    /// A file that couldn't be opened or read
+    Io { path: PathBuf, source: io::Error },
+    /// An OBJ or MTL file tobj rejected. `line` (1 based) is where the problem is, when it could
+    /// be tracked down.
+    Parse {
+        path: PathBuf,
+        line: Option<usize>,
+        source: LoadError,
+    },
+    /// An image that couldn't be decoded
+    Image {
+        path: PathBuf,
+        source: image::ImageError,
+    },
+    /// A texture a material refers to that isn't under the directory textures are looked up in
+    TextureNotFound { path: PathBuf, base: PathBuf },
+}
+
+impl Error {
+    pub fn io(path: impl AsRef<Path>, source: io::Error) -> Self {
+        Error::Io {
+            path: path.as_ref().to_path_buf(),
+            source,
+        }
+    }
+
+    /// Wraps a tobj error, re-reading the file to find the line that caused it
+    pub fn parse(path: impl AsRef<Path>, source: LoadError) -> Self {
+        let path = path.as_ref();
+        Error::Parse {
+            path: path.to_path_buf(),
+            line: find_error_line(path, &source),
+            source,
+        }
+    }
+
+    /// The file the error is about
+    pub fn path(&self) -> &Path {
+        match self {
+            Error::Io { path, .. }
+            | Error::Parse { path, .. }
+            | Error::Image { path, .. }
+            | Error::TextureNotFound { path, .. } => path,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io { path, source } => write!(f, "{}: {source}", path.display()),
+            Error::Parse {
+                path,
+                line: Some(line),
+                source,
+            } => write!(f, "{}:{line}: {source}", path.display()),
+            Error::Parse {
+                path,
+                line: None,
+                source,
+            } => write!(f, "{}: {source}", path.display()),
+            Error::Image { path, source } => {
+                write!(f, "{}: failed to decode texture: {source}", path.display())
+            }
+            Error::TextureNotFound { path, base } => write!(
+                f,
+                "{}: texture not found (looked in '{}')",
+                path.display(),
+                base.display()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            Error::Parse { source, .. } => Some(source),
+            Error::Image { source, .. } => Some(source),
+            Error::TextureNotFound { .. } => None,
+        }
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(error: Error) -> Self {
+        let kind = match &error {
+            Error::Io { source, .. } => source.kind(),
+            Error::TextureNotFound { .. } => io::ErrorKind::NotFound,
+            Error::Parse { .. } | Error::Image { .. } => io::ErrorKind::InvalidData,
+        };
+        io::Error::new(kind, error)
+    }
+}
+
+/// tobj only says what kind of problem it ran into, not where. Goes over the file again looking
+/// for the first line that would cause `error`.
+fn find_error_line(path: &Path, error: &LoadError) -> Option<usize> {
+    let lines: Vec<String> = BufReader::new(File::open(path).ok()?)
+        .lines()
+        .collect::<io::Result<_>>()
+        .ok()?;
+    let words = |line: &str| {
+        line.split_whitespace()
+            .map(str::to_string)
+            .collect::<Vec<_>>()
+    };
+    let floats = |words: &[String]| words.iter().all(|w| w.parse::<f32>().is_ok());
+
+    // Totals for the out of bounds checks, tobj checks positive indices once a model is done
+    let count = |tag: &str| {
+        lines
+            .iter()
+            .filter(|l| l.split_whitespace().next() == Some(tag))
+            .count()
+    };
+    let totals = [count("v"), count("vt"), count("vn")];
+    let mut seen = [0; 3];
+
+    for (i, line) in lines.iter().enumerate() {
+        let words = words(line);
+        let Some((tag, args)) = words.split_first() else {
+            continue;
+        };
+        let bad = match (tag.as_str(), error) {
+            ("v", LoadError::PositionParseError) => args.len() < 3 || !floats(&args[..3]),
+            ("v", LoadError::ScalingByZeroError) => {
+                args.len() == 4 && args[3].parse::<f32>() == Ok(0.0)
+            }
+            ("v", LoadError::ColorParseError) => args.len() == 5 || args.len() > 6,
+            ("vt", LoadError::TexcoordParseError) => args.len() < 2 || !floats(&args[..2]),
+            ("vn", LoadError::NormalParseError) => args.len() < 3 || !floats(&args[..3]),
+            ("f" | "l", LoadError::FaceParseError) => args.iter().any(|vertex| {
+                vertex.split('/').count() > 3
+                    || vertex
+                        .split('/')
+                        .any(|index| !index.is_empty() && index.parse::<isize>().is_err())
+            }),
+            ("f", LoadError::InvalidPolygon) => args.len() < 3,
+            (
+                "f" | "l",
+                LoadError::FaceVertexOutOfBounds
+                | LoadError::FaceTexCoordOutOfBounds
+                | LoadError::FaceNormalOutOfBounds,
+            ) => {
+                let slot = match error {
+                    LoadError::FaceVertexOutOfBounds => 0,
+                    LoadError::FaceTexCoordOutOfBounds => 1,
+                    _ => 2,
+                };
+                args.iter().any(|vertex| {
+                    let index = vertex
+                        .split('/')
+                        .nth(slot)
+                        .and_then(|i| i.parse::<isize>().ok());
+                    match index {
+                        Some(index) if index > 0 => index as usize > totals[slot],
+                        Some(index) => index == 0 || index.unsigned_abs() > seen[slot],
+                        None => false,
+                    }
+                })
+            }
+            ("usemtl", LoadError::MaterialParseError) => args.is_empty(),
+            ("Ka" | "Kd" | "Ks" | "Ke", LoadError::MaterialParseError) => {
+                args.len() < 3 || !floats(&args[..3])
+            }
+            ("Ns" | "Ni" | "d", LoadError::MaterialParseError) => {
+                args.is_empty() || !floats(&args[..1])
+            }
+            _ => false,
+        };
+        if bad {
+            return Some(i + 1);
+        }
+        match tag.as_str() {
+            "v" => seen[0] += 1,
+            "vt" => seen[1] += 1,
+            "vn" => seen[2] += 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Checks an MTL file for the problems tobj doesn't handle gracefully (a color with fewer than
+/// three components makes it panic), returning the error it should be rejected with.
+pub(crate) fn check_mtl(path: &Path) -> Result<()> {
+    match find_error_line(path, &LoadError::MaterialParseError) {
+        Some(line) => Err(Error::Parse {
+            path: path.to_path_buf(),
+            line: Some(line),
+            source: LoadError::MaterialParseError,
+        }),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_errors_name_the_line() {
+        let path = std::env::temp_dir().join("terminal_renderer_bad_face.obj");
+        std::fs::write(&path, "v 0 0 0\nv 1 0 0\nv 0 1 0\n# a comment\nf 1 2 4\n").unwrap();
+        let error = Error::parse(&path, LoadError::FaceVertexOutOfBounds);
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(error, Error::Parse { line: Some(5), .. }));
+        assert!(error
+            .to_string()
+            .ends_with(":5: face vertex index out of bounds"));
+    }
+}
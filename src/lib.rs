@@ -8,14 +8,21 @@ pub const TINY_DIMENSIONS: (usize, usize) = (320, 256);
 use clap::{Arg, Command};
 
 pub mod core;
+pub mod error;
+pub mod logging;
 pub mod pipeline;
 pub mod util;
 
+// Lets the exported macros below log without the caller depending on `log`
+#[doc(hidden)]
+pub use log;
+
 pub use core::geometry;
 pub use core::Camera;
 pub use core::Color;
 pub use core::Entity;
 pub use core::Scene;
+pub use error::Error;
 pub use util::format_mat4;
 
 #[derive(Debug, Clone, Copy)]
@@ -33,6 +40,10 @@ pub struct Metrics {
     pub fps_update_timer: Instant,
     pub current_fps: f32,
     pub frame_times: Vec<f32>,
+    /// Triangles left after clipping in the last frame
+    pub triangles: usize,
+    /// Fragments the rasterizer produced in the last frame
+    pub fragments: usize,
 }
 
 impl Default for Metrics {
@@ -49,6 +60,8 @@ impl Metrics {
             fps_update_timer: Instant::now(),
             current_fps: 0.0,
             frame_times: Vec::with_capacity(120),
+            triangles: 0,
+            fragments: 0,
         }
     }
 
@@ -76,45 +89,51 @@ impl Metrics {
             self.fps_update_timer = Instant::now();
         }
     }
+
+    /// Shortest frame of the recorded window in milliseconds
+    pub fn min_frame_ms(&self) -> f32 {
+        self.frame_times.iter().copied().reduce(f32::min).unwrap_or(0.0)
+    }
+
+    /// Longest frame of the recorded window in milliseconds
+    pub fn max_frame_ms(&self) -> f32 {
+        self.frame_times.iter().copied().reduce(f32::max).unwrap_or(0.0)
+    }
 }
 impl Display for Metrics {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "FPS: {:.2} | Avg: {:.2}ms | Min: {:.2}ms | Max: {:.2}ms",
+            "FPS: {:.2} | Avg: {:.2}ms | Min: {:.2}ms | Max: {:.2}ms | Tris: {} | Frags: {}",
             self.current_fps,
             self.frame_time.as_secs_f32() * 1000.0,
-            self.frame_times
-                .iter()
-                .copied()
-                .reduce(f32::min)
-                .unwrap_or(0.0),
-            self.frame_times
-                .iter()
-                .copied()
-                .reduce(f32::max)
-                .unwrap_or(0.0)
+            self.min_frame_ms(),
+            self.max_frame_ms(),
+            self.triangles,
+            self.fragments
         )
     }
 }
 
-pub static DEBUG_PIPELINE: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(true);
-
-// Helper macro for debug printing
-#[macro_export]
-macro_rules! debug_print {
-    ($($arg:tt)*) => {
-        if $crate::DEBUG_PIPELINE.load(std::sync::atomic::Ordering::Relaxed) {
-            println!($($arg)*);
-        }
-    };
-}
-
 pub fn create_clap_command() -> Command {
     Command::new("terminal_renderer")
         .about("3D Software Renderer")
         .version("0.1")
         .author("Liam Wirth")
+        .arg(
+            Arg::new("log")
+                .long("log")
+                .value_name("SPEC")
+                .global(true)
+                .help("Log levels, e.g. 'info,terminal_renderer::pipeline=debug'. Overrides the TERMINAL_RENDERER_LOG env var."),
+        )
+        .arg(
+            Arg::new("log-file")
+                .long("log-file")
+                .value_name("FILE")
+                .global(true)
+                .help("File the log is written to, terminal_renderer.log by default"),
+        )
         .subcommand(
             Command::new("render")
                 .about("Render a 3D model in the terminal or a window (using minifb)")
@@ -138,7 +157,9 @@ pub fn create_clap_command() -> Command {
         )
 }
 
-pub fn handle_clap_matches(matches: &clap::ArgMatches) -> (DisplayTarget, Option<PathBuf>) {
+pub fn handle_clap_matches(
+    matches: &clap::ArgMatches,
+) -> error::Result<(DisplayTarget, Option<PathBuf>)> {
     if let Some(("render", sub_matches)) = matches.subcommand() {
         let mode = sub_matches
             .get_one::<String>("mode")
@@ -153,16 +174,18 @@ pub fn handle_clap_matches(matches: &clap::ArgMatches) -> (DisplayTarget, Option
             "terminal" | "t" => DisplayTarget::Terminal,
             "video" | "v" => DisplayTarget::Window,
             _ => {
-                eprintln!("Invalid mode: {}. Defaulting to terminal.", mode);
+                log::warn!("Invalid mode: {}. Defaulting to terminal.", mode);
                 DisplayTarget::Terminal
             }
         };
-        let model = Path::new(model).to_owned().canonicalize().unwrap();
-        return (target, Some(model));
+        let model = Path::new(model)
+            .canonicalize()
+            .map_err(|e| Error::io(model, e))?;
+        return Ok((target, Some(model)));
     }
 
     // Default behavior when no subcommand is provided
-    (DisplayTarget::Terminal, None)
+    Ok((DisplayTarget::Terminal, None))
 }
 
 /// TODO: un-macro this
@@ -173,44 +196,44 @@ macro_rules! handle_crossterm_keys {
         match $key_code {
             // Toggle wireframe
             KeyCode::Char('p') => {
-                let current = $states.borrow().draw_wireframe;
-                $states.borrow_mut().draw_wireframe = !current;
+                let current = $states.draw_wireframe;
+                $states.draw_wireframe = !current;
             }
             // Toggle bake_normals
             KeyCode::Char('b') => {
-                let current = $states.borrow().bake_normals;
-                $states.borrow_mut().bake_normals = !current;
+                let current = $states.bake_normals;
+                $states.bake_normals = !current;
             }
             // Toggle move_obj
             KeyCode::Char('j') => {
-                let current = $states.borrow().move_obj;
-                $states.borrow_mut().move_obj = !current;
-                println!("Move obj: {}", !current);
+                let current = $states.move_obj;
+                $states.move_obj = !current;
+                $crate::log::debug!("Move obj: {}", !current);
             }
             // Decrement current_obj
             KeyCode::Char('[') => {
-                let mut current = $states.borrow().current_obj;
+                let mut current = $states.current_obj;
                 current = current.saturating_sub(1);
                 if current > $scene.entities.len() - 1 {
                     current = $scene.entities.len() - 1;
                 }
-                $states.borrow_mut().current_obj = current;
-                println!("Current object: {}", current);
+                $states.current_obj = current;
+                $crate::log::debug!("Current object: {}", current);
             }
             // Increment current_obj
             KeyCode::Char(']') => {
-                let mut current = $states.borrow().current_obj;
+                let mut current = $states.current_obj;
                 current += 1;
                 if current > $scene.entities.len() - 1 {
                     current %= $scene.entities.len();
                 }
-                $states.borrow_mut().current_obj = current;
-                println!("Current object: {}", current);
+                $states.current_obj = current;
+                $crate::log::debug!("Current object: {}", current);
             }
             // Move forward/back or entity
             KeyCode::Char('w') => {
-                let move_obj = $states.borrow().move_obj;
-                let current_obj = $states.borrow().current_obj;
+                let move_obj = $states.move_obj;
+                let current_obj = $states.current_obj;
                 if move_obj {
                     let ent = &$scene.entities[current_obj];
                     let mut t = *ent.transform();
@@ -221,8 +244,8 @@ macro_rules! handle_crossterm_keys {
                 }
             }
             KeyCode::Char('s') => {
-                let move_obj = $states.borrow().move_obj;
-                let current_obj = $states.borrow().current_obj;
+                let move_obj = $states.move_obj;
+                let current_obj = $states.current_obj;
                 if move_obj {
                     let ent = &$scene.entities[current_obj];
                     let mut t = *ent.transform();
@@ -234,8 +257,8 @@ macro_rules! handle_crossterm_keys {
             }
             // Move left/right or entity
             KeyCode::Char('a') => {
-                let move_obj = $states.borrow().move_obj;
-                let current_obj = $states.borrow().current_obj;
+                let move_obj = $states.move_obj;
+                let current_obj = $states.current_obj;
                 if move_obj {
                     let ent = &$scene.entities[current_obj];
                     let mut t = *ent.transform();
@@ -246,8 +269,8 @@ macro_rules! handle_crossterm_keys {
                 }
             }
             KeyCode::Char('d') => {
-                let move_obj = $states.borrow().move_obj;
-                let current_obj = $states.borrow().current_obj;
+                let move_obj = $states.move_obj;
+                let current_obj = $states.current_obj;
                 if move_obj {
                     let ent = &$scene.entities[current_obj];
                     let mut t = *ent.transform();
@@ -259,15 +282,15 @@ macro_rules! handle_crossterm_keys {
             }
             // Reset entity or camera
             KeyCode::Char('u') => {
-                if $states.borrow().move_obj {
+                if $states.move_obj {
                 } else {
                     $scene.camera.reset();
                 }
             }
             // Move up/down (space/shift in minifb example)
             KeyCode::Char(' ') => {
-                let move_obj = $states.borrow().move_obj;
-                let current_obj = $states.borrow().current_obj;
+                let move_obj = $states.move_obj;
+                let current_obj = $states.current_obj;
                 if move_obj {
                     let ent = &$scene.entities[current_obj];
                     let mut t = *ent.transform();
@@ -297,23 +320,23 @@ macro_rules! handle_crossterm_mouse {
             MouseEventKind::Down(btn) => {
                 match btn {
                     MouseButton::Left => {
-                        println!(
+                        $crate::log::debug!(
                             "Left click at ({}, {})",
                             $mouse_event.column, $mouse_event.row
                         );
                         // Add your "on left click" logic here.
                         // E.g. maybe toggle wireframe, or select an entity, etc.
-                        // $states.borrow_mut().some_flag = true;
+                        // $states.some_flag = true;
                     }
                     MouseButton::Right => {
-                        println!(
+                        $crate::log::debug!(
                             "Right click at ({}, {})",
                             $mouse_event.column, $mouse_event.row
                         );
                         // Some other logic for right click
                     }
                     MouseButton::Middle => {
-                        println!(
+                        $crate::log::debug!(
                             "Middle click at ({}, {})",
                             $mouse_event.column, $mouse_event.row
                         );
@@ -324,20 +347,20 @@ macro_rules! handle_crossterm_mouse {
             MouseEventKind::Up(btn) => {
                 match btn {
                     MouseButton::Left => {
-                        println!("Left button released");
+                        $crate::log::debug!("Left button released");
                         // Add custom logic for left release
                     }
                     MouseButton::Right => {
-                        println!("Right button released");
+                        $crate::log::debug!("Right button released");
                     }
                     MouseButton::Middle => {
-                        println!("Middle button released");
+                        $crate::log::debug!("Middle button released");
                     }
                 }
             }
             // When mouse is moved while button is held (drag)
             MouseEventKind::Drag(btn) => {
-                println!(
+                $crate::log::debug!(
                     "Dragging with {:?} button at ({}, {})",
                     btn, $mouse_event.column, $mouse_event.row
                 );
@@ -346,21 +369,21 @@ macro_rules! handle_crossterm_mouse {
             // When mouse simply moves (no buttons held)
             MouseEventKind::Moved => {
                 // This fires frequently; you may or may not want to do something
-                println!(
+                $crate::log::trace!(
                     "Mouse moved to ({}, {})",
                     $mouse_event.column, $mouse_event.row
                 );
             }
             // Scroll wheel
             MouseEventKind::ScrollDown => {
-                println!(
+                $crate::log::debug!(
                     "Scrolled down at ({}, {})",
                     $mouse_event.column, $mouse_event.row
                 );
                 // Maybe zoom the camera out or do something
             }
             MouseEventKind::ScrollUp => {
-                println!(
+                $crate::log::debug!(
                     "Scrolled up at ({}, {})",
                     $mouse_event.column, $mouse_event.row
                 );
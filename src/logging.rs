@@ -0,0 +1,199 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use simplelog::{Config, WriteLogger};
+
+/// Env var holding a level spec, see `LevelSpec::parse`. `--log` takes precedence over it.
+pub const LOG_ENV: &str = "TERMINAL_RENDERER_LOG";
+/// Where the log file goes when `--log-file` isn't given
+pub const DEFAULT_LOG_FILE: &str = "terminal_renderer.log";
+/// Lines kept for the in-app log panel
+const SCROLLBACK_LINES: usize = 200;
+
+/// Log levels per module, e.g. `info,terminal_renderer::pipeline=debug,minifb=off`.
+/// A bare level sets the default, `module=level` applies to that module and everything in it,
+/// the longest matching module wins.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LevelSpec {
+    default: LevelFilter,
+    modules: Vec<(String, LevelFilter)>,
+}
+
+impl Default for LevelSpec {
+    /// Info for this crate, only warnings and errors from dependencies
+    fn default() -> Self {
+        Self {
+            default: LevelFilter::Warn,
+            modules: vec![(env!("CARGO_CRATE_NAME").to_string(), LevelFilter::Info)],
+        }
+    }
+}
+
+impl LevelSpec {
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut default = None;
+        let mut modules: Vec<(String, LevelFilter)> = Vec::new();
+        for directive in spec.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+            match directive.split_once('=') {
+                Some((module, level)) => {
+                    let module = module.trim();
+                    let level = parse_level(level)?;
+                    modules.retain(|(m, _)| m != module);
+                    modules.push((module.to_string(), level));
+                }
+                None => default = Some(parse_level(directive)?),
+            }
+        }
+        // A bare level applies to everything, this crate included, without one the crate keeps
+        // its default unless it was given explicitly
+        match default {
+            Some(default) => Ok(Self { default, modules }),
+            None => {
+                let mut parsed = Self::default();
+                parsed.modules.retain(|(m, _)| !modules.iter().any(|(n, _)| n == m));
+                parsed.modules.extend(modules);
+                Ok(parsed)
+            }
+        }
+    }
+
+    pub fn level_for(&self, target: &str) -> LevelFilter {
+        self.modules
+            .iter()
+            .filter(|(module, _)| {
+                target == module
+                    || target
+                        .strip_prefix(module.as_str())
+                        .is_some_and(|rest| rest.starts_with("::"))
+            })
+            .max_by_key(|(module, _)| module.len())
+            .map_or(self.default, |(_, level)| *level)
+    }
+
+    /// The most verbose level anything is enabled at
+    pub fn max_level(&self) -> LevelFilter {
+        self.modules
+            .iter()
+            .map(|(_, level)| *level)
+            .fold(self.default, Ord::max)
+    }
+}
+
+fn parse_level(level: &str) -> Result<LevelFilter, String> {
+    level
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid log level '{}'", level.trim()))
+}
+
+/// One line of the scrollback shown by the log panel
+#[derive(Clone, Debug)]
+pub struct LogLine {
+    pub level: Level,
+    pub message: String,
+}
+
+fn scrollback() -> &'static Mutex<VecDeque<LogLine>> {
+    static SCROLLBACK: OnceLock<Mutex<VecDeque<LogLine>>> = OnceLock::new();
+    SCROLLBACK.get_or_init(|| Mutex::new(VecDeque::with_capacity(SCROLLBACK_LINES)))
+}
+
+/// The last `count` logged lines, oldest first
+pub fn recent(count: usize) -> Vec<LogLine> {
+    let scrollback = scrollback().lock().unwrap();
+    let skip = scrollback.len().saturating_sub(count);
+    scrollback.iter().skip(skip).cloned().collect()
+}
+
+/// Filters by `LevelSpec`, then writes to the log file and the scrollback
+struct Logger {
+    spec: LevelSpec,
+    file: Option<Box<WriteLogger<File>>>,
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.spec.level_for(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        if let Some(file) = &self.file {
+            file.log(record);
+        }
+        let mut scrollback = scrollback().lock().unwrap();
+        if scrollback.len() == SCROLLBACK_LINES {
+            scrollback.pop_front();
+        }
+        scrollback.push_back(LogLine {
+            level: record.level(),
+            message: record.args().to_string(),
+        });
+    }
+
+    fn flush(&self) {
+        if let Some(file) = &self.file {
+            file.flush();
+        }
+    }
+}
+
+/// Installs the global logger. Nothing gets printed to stdout, which belongs to the terminal
+/// renderer, messages go to `file` (if given) and the in-app log panel.
+pub fn init(spec: LevelSpec, file: Option<&Path>) -> std::io::Result<()> {
+    let file = match file {
+        Some(path) => Some(WriteLogger::new(
+            LevelFilter::Trace,
+            Config::default(),
+            File::create(path)?,
+        )),
+        None => None,
+    };
+    log::set_max_level(spec.max_level());
+    log::set_boxed_logger(Box::new(Logger { spec, file }))
+        .map_err(|e| std::io::Error::other(e.to_string()))
+}
+
+/// Sets up logging from `--log`/`--log-file`, falling back to `LOG_ENV` and `DEFAULT_LOG_FILE`
+pub fn init_from_matches(matches: &clap::ArgMatches) -> std::io::Result<()> {
+    let spec = matches
+        .get_one::<String>("log")
+        .cloned()
+        .or_else(|| std::env::var(LOG_ENV).ok());
+    let spec = match spec {
+        Some(spec) => LevelSpec::parse(&spec)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?,
+        None => LevelSpec::default(),
+    };
+    let file = matches
+        .get_one::<String>("log-file")
+        .map_or(DEFAULT_LOG_FILE, String::as_str);
+    init(spec, Some(Path::new(file)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn longest_module_prefix_wins() {
+        let spec =
+            LevelSpec::parse("error,terminal_renderer=warn,terminal_renderer::pipeline=trace")
+                .unwrap();
+        assert_eq!(spec.level_for("minifb"), LevelFilter::Error);
+        assert_eq!(spec.level_for("terminal_renderer::core"), LevelFilter::Warn);
+        assert_eq!(
+            spec.level_for("terminal_renderer::pipeline::rasterizer"),
+            LevelFilter::Trace
+        );
+        // Only whole path segments match
+        assert_eq!(spec.level_for("terminal_renderer_extra"), LevelFilter::Error);
+        assert_eq!(spec.max_level(), LevelFilter::Trace);
+        assert!(LevelSpec::parse("loud").is_err());
+    }
+}
@@ -17,9 +17,6 @@
 // TODO: Egui for debug console?
 // TODO: Live debug log with egui?
 //
-// FIX: Pipeline freaks out if a model is loaded and has no materials, will panic in multiple places instead of applying default material values. More specifically this actually happens when a material file exists, but is empty. (Blender will auto generate one)
-//
-//
 // FIX: Need to re-implement movement for camera, as the camera crosses the origin, culling needs
 // to be flipped, among other things, as well as movement values (from positive to negative)
 use crossterm::{
@@ -31,18 +28,37 @@ use crossterm::{
 use glam::{Affine3A, Vec3};
 use minifb::{Key, Scale, Window, WindowOptions};
 use std::io::{self};
+use std::path::PathBuf;
 use std::time::{Duration, Instant};
 use terminal_renderer::{
-    core::{Camera, Entity, Light, Scene},
-    pipeline::{pipeline::Pipeline, FrameBuffer, TermBuffer},
-    Color, DEBUG_PIPELINE, TINY_DIMENSIONS,
+    core::{Camera, Entity, Environment, Fog, Light, Scene, Sky},
+    pipeline::{
+        pipeline::Pipeline, Buffer, DropPolicy, FrameBuffer, Presenter, TermBuffer,
+        TerminalCapabilities, ThreadedPresenter, WindowPresenter,
+    },
+    Color, TINY_DIMENSIONS, create_clap_command, logging,
 };
 
 const WIDTH: usize = 1920;
 const HEIGHT: usize = 1080;
+/// Path (.csv, .json or .trace.json) the per frame profile gets written to on exit, nothing is
+/// recorded when unset
+const PROFILE_ENV: &str = "TERMINAL_RENDERER_PROFILE";
+/// Number of frame buffers (2 or 3) for writing to the terminal from its own thread, frames the
+/// terminal can't keep up with get dropped. Unset presents on the render thread.
+const PRESENT_THREAD_ENV: &str = "TERMINAL_RENDERER_PRESENT_THREAD";
+/// Height over width of a terminal cell, overrides what the terminal reports (and the default of
+/// 2 when it reports nothing)
+const CELL_ASPECT_ENV: &str = "TERMINAL_RENDERER_CELL_ASPECT";
+/// Milliseconds to wait for the terminal to answer the capability queries, 0 skips them and goes
+/// by environment variables only. Slow links (ssh) may need more than the default.
+const CAPABILITY_TIMEOUT_ENV: &str = "TERMINAL_RENDERER_CAPABILITY_TIMEOUT";
+const DEFAULT_CAPABILITY_TIMEOUT: Duration = Duration::from_millis(200);
 
 fn main() -> io::Result<()> {
-    DEBUG_PIPELINE.store(false, std::sync::atomic::Ordering::Relaxed);
+    // Nothing may print to stdout while the terminal renderer owns it, everything goes through
+    // the log (file and the in-app panel)
+    logging::init_from_matches(&create_clap_command().get_matches())?;
     let camera = Camera::new(
         Vec3::new(0.1, 2.0, 8.), // Position camera back a bit
         Vec3::new(0.0, 2.0, 0.),
@@ -50,7 +66,13 @@ fn main() -> io::Result<()> {
     );
 
     let mut scene = Scene::new(camera);
-    // scene.add_entity(floor);
+    let mut environment = Environment::blender_floor();
+    environment.set_floor_height(-3.8); // Just below the penguin's feet
+    environment.set_sky(Sky::default_gradient());
+    environment.set_image_lighting(Some(1.0));
+    // Fades the far end of the floor into the sky's horizon color
+    environment.set_fog(Fog::exponential(Color::new(0.75, 0.82, 0.9).to_linear(), 0.02));
+    scene.set_environment(environment);
     let point = Light::easy_point(Vec3::new(3., 3., 3.)); // FIX: All lighting calculations are backwards
     let mut point2 = Light::easy_point(Vec3::new(5., -5. ,5.));
     let mut point3 = Light::easy_point(Vec3::new(-5., -5., 5.));
@@ -67,7 +89,7 @@ fn main() -> io::Result<()> {
     scene.add_light(point4);
 
     // let mut ent = Entity::new_teapot();
-    let mut ent = Entity::new_penguin();
+    let mut ent = Entity::new_penguin()?;
     // let mut ent = Entity::new_thwomp();
 
     ent[0].set_transform(Affine3A::from_rotation_x(0.4));
@@ -83,19 +105,30 @@ fn main() -> io::Result<()> {
 }
 
 fn run_term(scene: Scene) -> io::Result<()> {
+    // Settings are read first, a bad value must not leave the terminal in raw mode
+    let present_buffers = present_thread_buffers()?;
+    let capability_timeout = capability_timeout()?;
+    let cell_aspect = cell_aspect_override()?;
+
     // 1) Setup crossterm
     enable_raw_mode()?;
     let mut stdout = io::stdout();
-    execute!(
-        stdout,
-        terminal::EnterAlternateScreen,
-        Hide,
-        Clear(ClearType::All)
-    )?;
+    execute!(stdout, terminal::EnterAlternateScreen, Hide)?;
+    // Terminals that don't understand a query may print some of it, cleared right after
+    let capabilities = TerminalCapabilities::detect(capability_timeout);
+    log::info!("Terminal capabilities: {capabilities:?}");
+    execute!(stdout, Clear(ClearType::All))?;
 
     // 2) Create pipeline
     let (tw, th) = crossterm::terminal::size()?;
     let mut pipeline = Pipeline::<TermBuffer>::new(tw as usize, th as usize, scene);
+    pipeline.set_pixel_aspect(
+        cell_aspect
+            .or(capabilities.cell_aspect())
+            .unwrap_or(<TermBuffer as Buffer>::PIXEL_ASPECT),
+    );
+    let profile = start_profiling(&mut pipeline);
+    let mut terminal = terminal_presenter(&capabilities, present_buffers);
 
     // 3) For timing/Metrics
     let mut last_frame = Instant::now();
@@ -118,20 +151,104 @@ fn run_term(scene: Scene) -> io::Result<()> {
         let frame_delta = now - last_frame;
         if frame_delta >= frame_duration {
             let (nw, nh) = crossterm::terminal::size()?;
-            if nw as usize != pipeline.width || nh as usize != pipeline.height {
-                pipeline =
-                    Pipeline::<TermBuffer>::new(nw as usize, nh as usize, pipeline.scene.clone());
-            }
+            pipeline.resize(nw as usize, nh as usize);
 
-            pipeline.render_frame(None, frame_delta)?;
+            pipeline.render_frame(&mut [terminal.as_mut()], frame_delta)?;
 
             last_frame = now;
         }
     }
 
 
-    // 5) Cleanup
+    // 5) Cleanup, the present thread has to be done with the terminal before it's restored
+    drop(terminal);
     cleanup_terminal()?;
+    save_profile(&pipeline, profile)
+}
+
+/// Cell height over width from `CELL_ASPECT_ENV`, None when unset
+fn cell_aspect_override() -> io::Result<Option<f32>> {
+    let Some(aspect) = std::env::var_os(CELL_ASPECT_ENV) else {
+        return Ok(None);
+    };
+    aspect
+        .to_str()
+        .and_then(|a| a.parse().ok())
+        .filter(|a: &f32| a.is_finite() && *a > 0.0)
+        .map(Some)
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{CELL_ASPECT_ENV} must be a positive number"),
+            )
+        })
+}
+
+/// How long `TerminalCapabilities::detect` waits, from `CAPABILITY_TIMEOUT_ENV`
+fn capability_timeout() -> io::Result<Duration> {
+    let Some(millis) = std::env::var_os(CAPABILITY_TIMEOUT_ENV) else {
+        return Ok(DEFAULT_CAPABILITY_TIMEOUT);
+    };
+    millis
+        .to_str()
+        .and_then(|m| m.parse().ok())
+        .map(Duration::from_millis)
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{CAPABILITY_TIMEOUT_ENV} must be a number of milliseconds"),
+            )
+        })
+}
+
+/// Frame buffers for the present thread from `PRESENT_THREAD_ENV`, None when unset. Read before
+/// the terminal is set up so a bad value doesn't leave it in raw mode.
+fn present_thread_buffers() -> io::Result<Option<usize>> {
+    let Some(buffers) = std::env::var_os(PRESENT_THREAD_ENV) else {
+        return Ok(None);
+    };
+    buffers
+        .to_str()
+        .and_then(|b| b.parse().ok())
+        .filter(|b| (2..=3).contains(b))
+        .map(Some)
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{PRESENT_THREAD_ENV} must be 2 or 3"),
+            )
+        })
+}
+
+/// Writes to stdout from the render thread, or from its own thread with `buffers` frame buffers,
+/// using the best output `capabilities` allow
+fn terminal_presenter(
+    capabilities: &TerminalCapabilities,
+    buffers: Option<usize>,
+) -> Box<dyn Presenter> {
+    let Some(buffers) = buffers else {
+        return Box::new(capabilities.presenter());
+    };
+    log::info!("Presenting from a separate thread with {buffers} buffers");
+    Box::new(ThreadedPresenter::new(
+        capabilities.presenter(),
+        buffers,
+        DropPolicy::DropOldest,
+    ))
+}
+
+/// Starts recording per frame stats when `PROFILE_ENV` is set, returns where to save them
+fn start_profiling<B: Buffer>(pipeline: &mut Pipeline<B>) -> Option<PathBuf> {
+    let path = PathBuf::from(std::env::var_os(PROFILE_ENV)?);
+    pipeline.profiler_mut().set_recording(true);
+    Some(path)
+}
+
+fn save_profile<B: Buffer>(pipeline: &Pipeline<B>, path: Option<PathBuf>) -> io::Result<()> {
+    if let Some(path) = path {
+        pipeline.profiler().save(&path)?;
+        log::info!("Wrote profile to {}", path.display());
+    }
     Ok(())
 }
 
@@ -143,7 +260,7 @@ fn cleanup_terminal() -> io::Result<()> {
 }
 
 pub fn run_win(scene: Scene) -> io::Result<()> {
-    let mut window = Window::new(
+    let window = Window::new(
         "Terminal Renderer - Window Mode",
         WIDTH,
         HEIGHT,
@@ -153,18 +270,24 @@ pub fn run_win(scene: Scene) -> io::Result<()> {
             ..WindowOptions::default()
         },
     )
-    .expect("Unable to open window");
+    .map_err(|e| io::Error::other(e.to_string()))?;
+    let mut window = WindowPresenter::new(window);
     let mut pipeline = Pipeline::<FrameBuffer>::new(WIDTH, HEIGHT, scene);
+    let profile = start_profiling(&mut pipeline);
     let mut last_frame = Instant::now();
-    while window.is_open() {
-        if window.is_key_down(Key::Escape) || window.is_key_down(Key::Q) {
+    while window.window().is_open() {
+        if window.window().is_key_down(Key::Escape) || window.window().is_key_down(Key::Q) {
             break;
         }
+        let (width, height) = window.window().get_size();
+        if width > 0 && height > 0 {
+            pipeline.resize(width, height);
+        }
         let now = Instant::now();
         let frame_delta = now - last_frame;
-        pipeline.render_frame(Some(&mut window), frame_delta)?;
-        pipeline.window_handle_input(&window, now);
+        pipeline.render_frame(&mut [&mut window], frame_delta)?;
+        pipeline.window_handle_input(window.window(), now);
         last_frame = now;
     }
-    Ok(())
+    save_profile(&pipeline, profile)
 }
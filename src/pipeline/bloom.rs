@@ -0,0 +1,215 @@
+use rayon::prelude::*;
+
+use crate::core::Color;
+
+#[derive(Clone, Copy, Debug)]
+pub struct BloomSettings {
+    pub enabled: bool,
+    /// Luminance (after exposure) above which pixels start to glow, 1.0 is "brighter than white"
+    pub threshold: f32,
+    /// How much of the blurred highlights gets added back on top of the image
+    pub intensity: f32,
+    /// Number of downsample steps, each one doubles the glow's reach
+    pub levels: usize,
+}
+
+impl Default for BloomSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            threshold: 1.0,
+            intensity: 0.5,
+            levels: 5,
+        }
+    }
+}
+
+struct Level {
+    width: usize,
+    height: usize,
+    data: Vec<Color>,
+}
+
+impl Level {
+    fn resize(&mut self, width: usize, height: usize) {
+        self.width = width;
+        self.height = height;
+        self.data.resize(width * height, Color::BLACK);
+    }
+
+    fn get(&self, x: isize, y: isize) -> Color {
+        let x = x.clamp(0, self.width as isize - 1) as usize;
+        let y = y.clamp(0, self.height as isize - 1) as usize;
+        self.data[y * self.width + x]
+    }
+
+    /// Bilinear lookup, `x`/`y` are in pixel units of this level (pixel centers at +0.5)
+    fn sample(&self, x: f32, y: f32) -> Color {
+        let x = x - 0.5;
+        let y = y - 0.5;
+        let x0 = x.floor();
+        let y0 = y.floor();
+        let tx = x - x0;
+        let ty = y - y0;
+        let (x0, y0) = (x0 as isize, y0 as isize);
+        let top = self.get(x0, y0).lerp(&self.get(x0 + 1, y0), tx);
+        let bottom = self.get(x0, y0 + 1).lerp(&self.get(x0 + 1, y0 + 1), tx);
+        top.lerp(&bottom, ty)
+    }
+}
+
+/// Downsample/upsample bloom chain, keeps its intermediate buffers around between frames
+#[derive(Default)]
+pub struct Bloom {
+    levels: Vec<Level>,
+}
+
+impl Bloom {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the glow of everything brighter than the threshold back into `hdr`.
+    /// `hdr` has to hold the linear, not yet tone mapped lighting result, `exposure` is the one
+    /// tone mapping will use so the threshold follows what ends up looking bright on screen.
+    pub fn apply(
+        &mut self,
+        hdr: &mut [Color],
+        width: usize,
+        height: usize,
+        exposure: f32,
+        settings: &BloomSettings,
+    ) {
+        if !settings.enabled || settings.levels == 0 || width == 0 || height == 0 {
+            return;
+        }
+        self.allocate(width, height, settings.levels);
+        if self.levels.is_empty() {
+            return;
+        }
+
+        // Bright pass straight into the first (half resolution) level
+        let threshold = settings.threshold;
+        let inv_exposure = 1.0 / exposure.max(f32::EPSILON);
+        let first = &mut self.levels[0];
+        let first_width = first.width;
+        first
+            .data
+            .par_chunks_mut(first_width)
+            .enumerate()
+            .for_each(|(y, row)| {
+                for (x, out) in row.iter_mut().enumerate() {
+                    let mut sum = Color::BLACK;
+                    for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                        let sx = (x * 2 + dx).min(width - 1);
+                        let sy = (y * 2 + dy).min(height - 1);
+                        sum += bright_pass(hdr[sy * width + sx] * exposure, threshold);
+                    }
+                    *out = sum * (0.25 * inv_exposure);
+                }
+            });
+
+        // Downsample chain
+        for i in 1..self.levels.len() {
+            let (larger, smaller) = self.levels.split_at_mut(i);
+            downsample(&larger[i - 1], &mut smaller[0]);
+        }
+
+        // Upsample back, accumulating every level into the one above it
+        for i in (1..self.levels.len()).rev() {
+            let (larger, smaller) = self.levels.split_at_mut(i);
+            upsample_add(&smaller[0], &mut larger[i - 1]);
+        }
+
+        // Composite onto the full resolution image, normalized so more levels don't mean brighter
+        let scale = settings.intensity / self.levels.len() as f32;
+        let glow = &self.levels[0];
+        hdr.par_chunks_mut(width).enumerate().for_each(|(y, row)| {
+            for (x, out) in row.iter_mut().enumerate() {
+                let gx = (x as f32 + 0.5) * glow.width as f32 / width as f32;
+                let gy = (y as f32 + 0.5) * glow.height as f32 / height as f32;
+                *out += tent(glow, gx, gy) * scale;
+            }
+        });
+    }
+
+    fn allocate(&mut self, width: usize, height: usize, max_levels: usize) {
+        let mut sizes = Vec::with_capacity(max_levels);
+        let (mut w, mut h) = (width, height);
+        while sizes.len() < max_levels && w > 1 && h > 1 {
+            w = w.div_ceil(2);
+            h = h.div_ceil(2);
+            sizes.push((w, h));
+        }
+
+        self.levels.truncate(sizes.len());
+        while self.levels.len() < sizes.len() {
+            self.levels.push(Level {
+                width: 0,
+                height: 0,
+                data: Vec::new(),
+            });
+        }
+        for (level, (w, h)) in self.levels.iter_mut().zip(sizes) {
+            level.resize(w, h);
+        }
+    }
+}
+
+/// Keeps only the part of the color above the threshold, with a short ramp so highlights don't
+/// pop in and out
+fn bright_pass(color: Color, threshold: f32) -> Color {
+    let luminance = color.luminance();
+    if luminance <= 0.0 {
+        return Color::BLACK;
+    }
+    let knee = threshold * 0.5;
+    let soft = (luminance - threshold + knee).clamp(0.0, 2.0 * knee);
+    let soft = soft * soft / (4.0 * knee + 1e-5);
+    let contribution = soft.max(luminance - threshold) / luminance;
+    color * contribution.max(0.0)
+}
+
+fn downsample(src: &Level, dst: &mut Level) {
+    let dst_width = dst.width;
+    dst.data
+        .par_chunks_mut(dst_width)
+        .enumerate()
+        .for_each(|(y, row)| {
+            for (x, out) in row.iter_mut().enumerate() {
+                let (sx, sy) = (x as isize * 2, y as isize * 2);
+                *out = (src.get(sx, sy)
+                    + src.get(sx + 1, sy)
+                    + src.get(sx, sy + 1)
+                    + src.get(sx + 1, sy + 1))
+                    * 0.25;
+            }
+        });
+}
+
+fn upsample_add(src: &Level, dst: &mut Level) {
+    let (dst_width, dst_height) = (dst.width, dst.height);
+    dst.data
+        .par_chunks_mut(dst_width)
+        .enumerate()
+        .for_each(|(y, row)| {
+            for (x, out) in row.iter_mut().enumerate() {
+                let sx = (x as f32 + 0.5) * src.width as f32 / dst_width as f32;
+                let sy = (y as f32 + 0.5) * src.height as f32 / dst_height as f32;
+                *out += tent(src, sx, sy);
+            }
+        });
+}
+
+/// 3x3 tent filter built from bilinear taps, smooths out the blockiness of the box downsample
+fn tent(level: &Level, x: f32, y: f32) -> Color {
+    const WEIGHTS: [f32; 3] = [0.25, 0.5, 0.25];
+    let mut sum = Color::BLACK;
+    for (j, wy) in WEIGHTS.iter().enumerate() {
+        for (i, wx) in WEIGHTS.iter().enumerate() {
+            let sample = level.sample(x + i as f32 - 1.0, y + j as f32 - 1.0);
+            sum += sample * (wx * wy);
+        }
+    }
+    sum
+}
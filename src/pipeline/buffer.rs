@@ -1,30 +1,79 @@
 use crate::core::{Color, Pixel};
-use glam::UVec2;
-use minifb::Window;
+use crate::pipeline::line::{self, LineStyle};
+use crate::pipeline::{Frame, GlyphCache};
+use glam::{Mat4, UVec2, Vec3, Vec4};
 use rayon::iter::ParallelIterator;
 use rayon::slice::ParallelSliceMut;
-use std::cell::RefCell;
-use std::io::{self, stdout, Write};
 pub const MAX_DIMS: UVec2 = UVec2::new(1920, 1080);
 
 // Note might be worth going back to refcells for interior mutability
 
 pub trait Buffer {
     type Pixel: Clone + Send + Sync;
+    /// Height of a pixel over its width, what `Pipeline` assumes until told otherwise with
+    /// `Pipeline::set_pixel_aspect`
+    const PIXEL_ASPECT: f32 = 1.0;
 
     fn new(width: usize, height: usize) -> Self
     where
         Self: Sized;
     fn clear(&mut self);
+    /// `color` is linear and already tone mapped, implementations encode it for display (sRGB)
     fn create_pixel(color: Color) -> Self::Pixel;
     fn set_pixel(&mut self, pos: (usize, usize), depth: &f32, pixel: Self::Pixel);
-    fn draw_line(&mut self, start: UVec2, end: UVec2, pixel: Self::Pixel);
-    fn present(&self) -> io::Result<()> {
-        Ok(()) // Default does nothin
+    /// Width and height in pixels
+    fn size(&self) -> (usize, usize);
+    /// Linear color currently at `pos`, None outside of the buffer
+    fn pixel_color(&self, pos: (usize, usize)) -> Option<Color>;
+
+    /// Mixes `color` into what's already at `pos` by `coverage` (0-1), depth tested like
+    /// `set_pixel`. Used for anti aliased drawing.
+    fn blend_pixel(&mut self, pos: (usize, usize), depth: &f32, color: Color, coverage: f32) {
+        if let Some(existing) = self.pixel_color(pos) {
+            let color = existing.lerp(&color, coverage.clamp(0.0, 1.0));
+            self.set_pixel(pos, depth, Self::create_pixel(color));
+        }
+    }
+
+    /// Screen space line drawn on top of everything (depth 0), clipped to the buffer.
+    /// Use `draw_line_clip`/`draw_line_world` for lines that are part of the scene.
+    fn draw_line(&mut self, start: UVec2, end: UVec2, pixel: Self::Pixel) {
+        let (width, height) = self.size();
+        let (start, end) = (start.as_vec2(), end.as_vec2());
+        let Some((t0, t1)) = line::clip_to_screen(start, end, width, height) else {
+            return;
+        };
+        line::bresenham(start.lerp(end, t0), start.lerp(end, t1), |pos, _| {
+            self.set_pixel((pos.x as usize, pos.y as usize), &0.0, pixel.clone());
+        });
     }
-    fn present_window(&self, _window: &mut Window) -> io::Result<()> {
-        Ok(()) // Default implementation does nothing
+
+    /// Draws `text` on top of everything with its top left corner at `pos`, '\n' starts a new
+    /// line. Terminals put one character per cell, windows rasterize the embedded font.
+    /// Not depth tested, later text covers earlier text.
+    fn draw_text(&mut self, pos: (usize, usize), text: &str, color: Color);
+    /// Width and height in pixels of one character drawn by `draw_text`
+    fn char_size(&self) -> (usize, usize);
+
+    /// Line between two clip space points, clipped to the frustum and depth tested
+    fn draw_line_clip(&mut self, start: Vec4, end: Vec4, color: Color, style: LineStyle) {
+        line::draw_clip_line(self, start, end, color, style);
     }
+
+    /// Line between two world space points, `view_proj` takes them to clip space
+    fn draw_line_world(
+        &mut self,
+        view_proj: &Mat4,
+        start: Vec3,
+        end: Vec3,
+        color: Color,
+        style: LineStyle,
+    ) {
+        line::draw_world_line(self, view_proj, start, end, color, style);
+    }
+
+    /// The finished image, for handing to `Presenter`s
+    fn frame(&self) -> Frame<'_>;
 }
 
 pub struct TermBuffer {
@@ -32,11 +81,12 @@ pub struct TermBuffer {
     pub height: usize,
     pub data: Vec<Pixel>,
     pub depth: Vec<f32>,
-    cached_lines: RefCell<Vec<String>>,
 }
 
 impl Buffer for TermBuffer {
     type Pixel = Pixel;
+    /// Terminal fonts are usually about twice as tall as wide
+    const PIXEL_ASPECT: f32 = 2.0;
 
     fn new(width: usize, height: usize) -> Self {
         let _max = MAX_DIMS.x as usize * MAX_DIMS.y as usize;
@@ -45,7 +95,6 @@ impl Buffer for TermBuffer {
             height,
             data: vec![Pixel::default_term(); width * height], // Initialize with default pixels
             depth: vec![f32::INFINITY; width * height],        // Initialize depth buffer
-            cached_lines: RefCell::new(vec![String::new(); height]),
         }
     }
 
@@ -79,115 +128,50 @@ impl Buffer for TermBuffer {
     }
 
     fn create_pixel(color: Color) -> Self::Pixel {
-        Pixel::new_terminal('█', color)
+        Pixel::new_terminal('█', color.to_srgb())
     }
 
-    fn draw_line(&mut self, start: UVec2, end: UVec2, pixel: Self::Pixel) {
-        let start = (start.x as f32, start.y as f32);
-        let end = (end.x as f32, end.y as f32);
-
-        crate::pipeline::rasterizer::bresenham(
-            start.into(),
-            end.into(),
-            pixel,
-            |pos, depth, pixel| {
-                self.set_pixel((pos.x as usize, pos.y as usize), &depth, pixel);
-            },
-        );
+    fn size(&self) -> (usize, usize) {
+        (self.width, self.height)
     }
 
-    // TODO: Profile old present code against new/current present code
-
-    /*
-        fn present(&self) -> io::Result<()> {
-            let mut stdout = io::stdout();
-
-            // Use Crossterm's queueing system to minimize syscalls
-            stdout.queue(MoveTo(0, 0))?;
-            stdout.queue(Hide)?;
+    fn char_size(&self) -> (usize, usize) {
+        (1, 1)
+    }
 
-            for y in 0..self.height {
-                for x in 0..self.width {
+    fn draw_text(&mut self, pos: (usize, usize), text: &str, color: Color) {
+        let color = color.to_srgb();
+        for (row, line) in text.lines().enumerate() {
+            let y = pos.1 + row;
+            for (column, ch) in line.chars().enumerate() {
+                let x = pos.0 + column;
+                if x < self.width && y < self.height {
                     let index = x + y * self.width;
-                    if let Pixel::Terminal { ch, color } = self.data[index] {
-                        stdout.queue(SetForegroundColor(color.to_crossterm_color()))?;
-                        stdout.queue(Print(ch))?;
-                    }
-                }
-                // Print a newline but avoid scrolling on last line:
-                if y < self.height - 1 {
-                    stdout.queue(Print("\r\n"))?;
+                    self.data[index] = Pixel::new_terminal(ch, color);
+                    self.depth[index] = f32::NEG_INFINITY;
                 }
             }
-
-            stdout.queue(Show)?;
-            stdout.flush()?;
-            Ok(())
         }
     }
-        */
-    fn present(&self) -> std::io::Result<()> {
-        let mut stdout = stdout();
-        let mut output = String::new();
-        let mut cached_lines = self.cached_lines.borrow_mut();
-        if cached_lines.len() != self.height {
-            cached_lines.clear();
-            cached_lines.resize_with(self.height, String::new);
-        }
-        let mut has_updates = false;
-
-        // Render each line
-        for y in 0..self.height {
-            let mut x = 0;
-            // NOTE: Considering we are rendering line by line, we could possibly multithread this
-            // operation too, but I think I might need to be more sparse with rayon multithreading
-            // because that adds overhead and might not be worth it for small operations like this,
-            // buuuut this does operate over millions of pixels so it might be worth it
-            let mut rendered_line = String::new();
-            let mut last_color: Option<String> = None;
 
-            // Move cursor to the beginning of the line once
-            rendered_line.push_str(&format!("\x1B[{};{}H", y + 1, 1));
-
-            while x < self.width {
-                let index = x + y * self.width;
-                let pixel: &Pixel = &self.data[index];
-                let current_color = pixel.color().to_ansii_escape(); // returns the ANSI
-                                                                     // escape code string
-
-                // Accumulate characters with the same color
-                let mut pixel_chars = String::new();
-                while x < self.width && self.data[x + y * self.width].color() == pixel.color() {
-                    pixel_chars.push(self.data[x + y * self.width].ch());
-                    x += 1;
-                }
-                // Change color if necessary
-                if last_color.as_ref() != Some(&current_color) {
-                    rendered_line.push_str(&current_color);
-                    last_color = Some(current_color);
-                }
-
-                // Append the accumulated characters
-                rendered_line.push_str(&pixel_chars);
-            }
-
-            if cached_lines[y] != rendered_line {
-                if !has_updates {
-                    // Hide cursor once per frame that has updates
-                    output.push_str("\x1B[?25l");
-                    has_updates = true;
-                }
-                output.push_str(&rendered_line);
-                cached_lines[y] = rendered_line;
-            }
+    fn pixel_color(&self, pos: (usize, usize)) -> Option<Color> {
+        if pos.0 >= self.width || pos.1 >= self.height {
+            return None;
         }
-
-        if !has_updates {
-            return Ok(());
+        let pixel = &self.data[pos.0 + pos.1 * self.width];
+        // Cleared cells are blank, which shows the terminal's (presumably dark) background
+        if pixel.ch() == ' ' {
+            return Some(Color::BLACK);
         }
+        Some(pixel.color().to_linear())
+    }
 
-        stdout.write_all(output.as_bytes())?;
-        stdout.flush()
+    fn frame(&self) -> Frame<'_> {
+        Frame::Cells {
+            width: self.width,
+            height: self.height,
+            data: &self.data,
+        }
     }
 }
 
@@ -196,7 +180,7 @@ pub struct FrameBuffer {
     pub height: usize,
     pub data: Vec<u32>,
     pub depth: Vec<f32>,
-    pub window: *mut Window, // Babies first Unsafe code
+    glyphs: GlyphCache,
 }
 
 impl Buffer for FrameBuffer {
@@ -209,7 +193,7 @@ impl Buffer for FrameBuffer {
             height,
             data: vec![0; buf_size], // White background initially
             depth: vec![f32::INFINITY; buf_size],
-            window: std::ptr::null_mut(), // Will be set externally
+            glyphs: GlyphCache::new(),
         }
     }
 
@@ -248,33 +232,42 @@ impl Buffer for FrameBuffer {
     }
 
     fn create_pixel(color: Color) -> Self::Pixel {
-        Pixel::new_framebuffer(color)
+        Pixel::new_framebuffer(color.to_srgb())
     }
 
-    fn draw_line(&mut self, start: glam::UVec2, end: glam::UVec2, pixel: Self::Pixel) {
-        let start = (start.x as f32, start.y as f32);
-        let end = (end.x as f32, end.y as f32);
+    fn size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
 
-        crate::pipeline::rasterizer::bresenham(
-            start.into(),
-            end.into(),
-            pixel,
-            |pos, depth, pixel| {
-                self.set_pixel((pos.x as usize, pos.y as usize), &depth, pixel);
-            },
-        );
+    fn pixel_color(&self, pos: (usize, usize)) -> Option<Color> {
+        if pos.0 >= self.width || pos.1 >= self.height {
+            return None;
+        }
+        Some(Pixel::Framebuffer(self.data[pos.0 + pos.1 * self.width]).color().to_linear())
     }
 
-    fn present_window(&self, window: &mut Window) -> io::Result<()> {
-        window
-            .update_with_buffer(&self.data, self.width, self.height)
-            .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
-        Ok(())
+    fn char_size(&self) -> (usize, usize) {
+        GlyphCache::cell_size()
     }
-}
 
-impl FrameBuffer {
-    pub fn attach_window(&mut self, window: &mut Window) {
-        self.window = window as *mut Window;
+    fn draw_text(&mut self, pos: (usize, usize), text: &str, color: Color) {
+        // Taken out for the duration so the closure can borrow the rest of the buffer
+        let mut glyphs = std::mem::take(&mut self.glyphs);
+        glyphs.draw(pos, text, |pos, coverage| {
+            if let Some(existing) = self.pixel_color(pos) {
+                let index = pos.0 + pos.1 * self.width;
+                self.data[index] = existing.lerp(&color, coverage).to_srgb().to_u32();
+                self.depth[index] = f32::NEG_INFINITY;
+            }
+        });
+        self.glyphs = glyphs;
+    }
+
+    fn frame(&self) -> Frame<'_> {
+        Frame::Rgb {
+            width: self.width,
+            height: self.height,
+            data: &self.data,
+        }
     }
 }
@@ -0,0 +1,302 @@
+use std::io::{self, IsTerminal, Write};
+use std::time::{Duration, Instant};
+
+use super::{ColorMode, TerminalPresenter};
+
+/// Queries sent by `TerminalCapabilities::detect`, every reply is optional except the last one.
+///
+/// - `CSI ? 2026 $ p`: DECRQM, is synchronized output (mode 2026) known
+/// - set a 24 bit foreground and ask for it back with DECRQSS, truecolor terminals keep it
+/// - `CSI 16 t`, `CSI 14 t`, `CSI 18 t`: cell size and window size in pixels, size in cells
+/// - `CSI c`: primary device attributes, every terminal answers it, so its reply marks the end of
+///   the others
+const QUERIES: &[u8] = b"\x1B[?2026$p\
+\x1B[38;2;1;2;3m\x1BP$qm\x1B\\\x1B[m\
+\x1B[16t\x1B[14t\x1B[18t\
+\x1B[c";
+
+/// What the terminal we're running in can do, see `TerminalCapabilities::detect`. Covers what
+/// `TerminalPresenter` can make use of: the color depth and synchronized output, plus the cell
+/// size for the pixel aspect.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TerminalCapabilities {
+    /// Synchronized updates (DEC mode 2026), frames show up whole instead of tearing
+    pub synchronized_output: bool,
+    /// 24 bit color escapes
+    pub truecolor: bool,
+    /// Size of one cell in pixels (width, height)
+    pub cell_size: Option<(u16, u16)>,
+    /// Whether the terminal answered the queries, when it didn't everything else comes from
+    /// environment variables
+    pub responded: bool,
+}
+
+impl TerminalCapabilities {
+    /// Asks the terminal on stdin/stdout what it supports, waiting at most `timeout` for the
+    /// replies. A zero `timeout` skips the queries and goes by environment variables only.
+    ///
+    /// Needs raw mode, replies would wait for a newline otherwise. Call it before reading any
+    /// input, keys pressed while it waits are read along with the replies and dropped.
+    pub fn detect(timeout: Duration) -> Self {
+        let env = |name: &str| std::env::var(name).ok();
+        Self::from_replies(&query(timeout), env)
+    }
+
+    /// Capabilities from the raw `replies` to `QUERIES`, with environment variables from `env`
+    /// filling in what the replies don't say
+    pub fn from_replies(replies: &[u8], env: impl Fn(&str) -> Option<String>) -> Self {
+        let mut caps = Self::default();
+        let mut truecolor_reply = None;
+        let mut window_pixels = None;
+        let mut window_cells = None;
+
+        for reply in Reply::parse(replies) {
+            caps.responded = true;
+            match reply {
+                Reply::Csi {
+                    params,
+                    intermediates: "$",
+                    last: 'y',
+                } => {
+                    let mut params = params.trim_start_matches('?').split(';');
+                    if params.next() == Some("2026") {
+                        // 1 set, 2 reset, 3 permanently set, 0 and 4 mean it can't be used
+                        caps.synchronized_output = matches!(params.next(), Some("1" | "2" | "3"));
+                    }
+                }
+                Reply::Csi {
+                    params,
+                    intermediates: "",
+                    last: 't',
+                } => {
+                    let numbers: Vec<u16> =
+                        params.split(';').filter_map(|n| n.parse().ok()).collect();
+                    match numbers[..] {
+                        [6, height, width] => caps.cell_size = Some((width, height)),
+                        [4, height, width] => window_pixels = Some((width, height)),
+                        [8, rows, columns] => window_cells = Some((columns, rows)),
+                        _ => {}
+                    }
+                }
+                // DECRQSS reply, `1$r` when valid followed by the current SGR, `:` or `;` separated
+                Reply::Dcs(data) => {
+                    if let Some(sgr) = data.strip_prefix("1$r") {
+                        truecolor_reply = Some(sgr.contains("2;1;2;3") || sgr.contains(":1:2:3"));
+                    }
+                }
+                Reply::Csi { .. } => {}
+            }
+        }
+
+        // Pixels per cell from the window sizes when the terminal doesn't tell directly
+        if let (None, Some((pw, ph)), Some((cw, ch))) =
+            (caps.cell_size, window_pixels, window_cells)
+            && cw > 0
+            && ch > 0
+        {
+            caps.cell_size = Some((pw / cw, ph / ch));
+        }
+        caps.cell_size = caps.cell_size.filter(|&(w, h)| w > 0 && h > 0);
+
+        let env_truecolor = env("COLORTERM").is_some_and(|c| c == "truecolor" || c == "24bit");
+        let term = env("TERM").unwrap_or_default();
+        // Without COLORTERM (ssh doesn't pass it on) or a DECRQSS reply, only assume truecolor
+        // is missing on the consoles known to lack it
+        let basic_term = matches!(term.as_str(), "" | "dumb" | "linux" | "vt100" | "vt220");
+        caps.truecolor = env_truecolor || truecolor_reply.unwrap_or(!basic_term);
+        caps
+    }
+
+    pub fn color_mode(&self) -> ColorMode {
+        if self.truecolor {
+            ColorMode::TrueColor
+        } else {
+            ColorMode::Ansi256
+        }
+    }
+
+    /// Height of a cell over its width, None when the terminal didn't report its cell size
+    pub fn cell_aspect(&self) -> Option<f32> {
+        self.cell_size.map(|(w, h)| h as f32 / w as f32)
+    }
+
+    /// Sets up `presenter` for the color depth and synchronized output this terminal supports
+    pub fn configure<W: Write>(&self, presenter: &mut TerminalPresenter<W>) {
+        presenter.set_color_mode(self.color_mode());
+        presenter.set_synchronized(self.synchronized_output);
+    }
+
+    /// A stdout presenter set up with `configure`
+    pub fn presenter(&self) -> TerminalPresenter {
+        let mut presenter = TerminalPresenter::new();
+        self.configure(&mut presenter);
+        presenter
+    }
+}
+
+/// Sends `QUERIES` and collects the replies until the device attributes come back or `timeout`
+/// runs out. Nothing gets sent when stdin or stdout isn't a terminal, or on platforms without
+/// `poll`.
+fn query(timeout: Duration) -> Vec<u8> {
+    let mut stdout = io::stdout();
+    if !cfg!(unix) || timeout.is_zero() || !io::stdin().is_terminal() || !stdout.is_terminal() {
+        return Vec::new();
+    }
+    if stdout
+        .write_all(QUERIES)
+        .and_then(|()| stdout.flush())
+        .is_err()
+    {
+        return Vec::new();
+    }
+    read_replies(timeout)
+}
+
+/// Reads stdin until the device attributes reply is in or `timeout` runs out. Reads only happen
+/// once `poll` says there is data, so nothing is left waiting on stdin for the input handling
+/// afterwards.
+#[cfg(unix)]
+fn read_replies(timeout: Duration) -> Vec<u8> {
+    use std::os::fd::AsRawFd;
+
+    // Straight from the file descriptor, `Stdin`'s buffer would hide bytes from `poll`
+    let fd = io::stdin().as_raw_fd();
+    let deadline = Instant::now() + timeout;
+    let mut replies = Vec::new();
+    let mut chunk = [0u8; 256];
+    while !Reply::parse(&replies).any(|reply| reply.is_device_attributes()) {
+        let left = deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            break;
+        }
+        let mut pollfd = libc::pollfd {
+            fd,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let millis = left.as_micros().div_ceil(1000).min(i32::MAX as u128) as i32;
+        // SAFETY: `pollfd` is a single valid entry that outlives the call
+        match unsafe { libc::poll(&mut pollfd, 1, millis) } {
+            0 => break,
+            n if n < 0 => {
+                if io::Error::last_os_error().kind() == io::ErrorKind::Interrupted {
+                    continue;
+                }
+                break;
+            }
+            _ => {}
+        }
+        // SAFETY: reads at most `chunk.len()` bytes into `chunk`
+        let read = unsafe { libc::read(fd, chunk.as_mut_ptr().cast(), chunk.len()) };
+        if read <= 0 {
+            break;
+        }
+        replies.extend_from_slice(&chunk[..read as usize]);
+    }
+    replies
+}
+
+#[cfg(not(unix))]
+fn read_replies(_timeout: Duration) -> Vec<u8> {
+    Vec::new()
+}
+
+/// Control sequence in a terminal's replies
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Reply<'a> {
+    /// `ESC [ params intermediates last`
+    Csi {
+        params: &'a str,
+        intermediates: &'a str,
+        last: char,
+    },
+    /// `ESC P data ESC \`
+    Dcs(&'a str),
+}
+
+impl<'a> Reply<'a> {
+    /// Complete sequences in `bytes`, skipping anything else and stopping at an unfinished one
+    fn parse(bytes: &'a [u8]) -> impl Iterator<Item = Reply<'a>> {
+        let mut rest = bytes;
+        std::iter::from_fn(move || {
+            loop {
+                let start = rest.iter().position(|&b| b == 0x1B)?;
+                rest = &rest[start..];
+                let (reply, len) = match *rest.get(1)? {
+                    b'[' => {
+                        let body = &rest[2..];
+                        let params = body
+                            .iter()
+                            .take_while(|b| (0x30..=0x3F).contains(*b))
+                            .count();
+                        let intermediates = body[params..]
+                            .iter()
+                            .take_while(|b| (0x20..=0x2F).contains(*b))
+                            .count();
+                        let last = *body.get(params + intermediates)?;
+                        let reply = (0x40..=0x7E).contains(&last).then(|| Reply::Csi {
+                            params: std::str::from_utf8(&body[..params]).unwrap_or(""),
+                            intermediates: std::str::from_utf8(
+                                &body[params..params + intermediates],
+                            )
+                            .unwrap_or(""),
+                            last: last as char,
+                        });
+                        (reply, 2 + params + intermediates + 1)
+                    }
+                    b'P' => {
+                        let body = &rest[2..];
+                        let end = body.windows(2).position(|w| w == b"\x1B\\")?;
+                        let data = std::str::from_utf8(&body[..end]).unwrap_or("");
+                        (Some(Reply::Dcs(data)), 2 + end + 2)
+                    }
+                    _ => (None, 1),
+                };
+                rest = &rest[len..];
+                if reply.is_some() {
+                    return reply;
+                }
+            }
+        })
+    }
+
+    fn is_device_attributes(&self) -> bool {
+        matches!(self, Reply::Csi { params, intermediates: "", last: 'c' } if params.starts_with('?'))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capabilities_from_replies() {
+        // A kitty-like terminal: synchronized output, truecolor SGR echoed back, 10x20 cells
+        let replies = b"\x1B[?2026;2$y\x1BP1$r0;38:2::1:2:3m\x1B\\\
+\x1B[6;20;10t\x1B[4;1000;800t\x1B[8;50;80t\x1B[?62;22c";
+        let caps = TerminalCapabilities::from_replies(replies, |_| None);
+        assert_eq!(
+            caps,
+            TerminalCapabilities {
+                synchronized_output: true,
+                truecolor: true,
+                cell_size: Some((10, 20)),
+                responded: true,
+            }
+        );
+        assert_eq!(caps.cell_aspect(), Some(2.0));
+
+        // Only device attributes and the window sizes, on the linux console
+        let replies = b"\x1B[4;800;1600t\x1B[8;50;200t\x1B[?63;4c";
+        let env = |name: &str| (name == "TERM").then(|| "linux".to_string());
+        let caps = TerminalCapabilities::from_replies(replies, env);
+        assert!(!caps.synchronized_output);
+        assert_eq!(caps.color_mode(), ColorMode::Ansi256);
+        assert_eq!(caps.cell_size, Some((8, 16)));
+
+        // No answer at all, going by the environment
+        let env = |name: &str| (name == "COLORTERM").then(|| "truecolor".to_string());
+        let caps = TerminalCapabilities::from_replies(b"", env);
+        assert!(!caps.responded && caps.truecolor);
+    }
+}
@@ -25,16 +25,40 @@ pub struct ClipTriangle {
     pub vertices: [ClipVertex; 3],
 }
 
+/// What `Clipper::clip` did with a triangle
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClipOutcome {
+    /// Back facing or degenerate, dropped before clipping
+    Culled,
+    /// Entirely inside the frustum, passed through as is
+    Inside,
+    /// Crossed at least one frustum plane and got cut down to the part inside
+    Clipped,
+    /// Entirely outside the frustum
+    Outside,
+}
+
 pub struct Clipper {
     backface_culling: bool,
     frustum_planes: [Vec4; 6],
 }
 
+/// The view frustum expressed in clip space, where it no longer depends on the camera.
+/// (glam's `perspective_rh` maps the near plane to z = 0 and the far plane to z = w)
+pub const CLIP_SPACE_PLANES: [Vec4; 6] = [
+    Vec4::new(1.0, 0.0, 0.0, 1.0),  // Left:   x + w >= 0
+    Vec4::new(-1.0, 0.0, 0.0, 1.0), // Right:  w - x >= 0
+    Vec4::new(0.0, 1.0, 0.0, 1.0),  // Bottom: y + w >= 0
+    Vec4::new(0.0, -1.0, 0.0, 1.0), // Top:    w - y >= 0
+    Vec4::new(0.0, 0.0, 1.0, 0.0),  // Near:   z >= 0
+    Vec4::new(0.0, 0.0, -1.0, 1.0), // Far:    w - z >= 0
+];
+
 impl Clipper {
     pub fn new() -> Self {
         Self {
             backface_culling: true,
-            frustum_planes: [Vec4::ZERO; 6],
+            frustum_planes: CLIP_SPACE_PLANES,
         }
     }
 
@@ -47,8 +71,23 @@ impl Clipper {
     }
 
     pub fn clip_triangle(&self, triangle: &ClipTriangle) -> Vec<ClipTriangle> {
+        self.clip(triangle).1
+    }
+
+    /// `clip_triangle`, also reporting what happened to the triangle
+    pub fn clip(&self, triangle: &ClipTriangle) -> (ClipOutcome, Vec<ClipTriangle>) {
         if !self.should_process_triangle(triangle) {
-            return Vec::new();
+            return (ClipOutcome::Culled, Vec::new());
+        }
+
+        let crosses_plane = self.frustum_planes.iter().any(|plane| {
+            triangle
+                .vertices
+                .iter()
+                .any(|vertex| self.distance_to_plane(vertex, *plane) < 0.0)
+        });
+        if !crosses_plane {
+            return (ClipOutcome::Inside, vec![triangle.clone()]);
         }
 
         let mut triangles = vec![triangle.clone()];
@@ -58,7 +97,11 @@ impl Clipper {
             triangles = self.clip_against_plane(triangles, *plane);
         }
 
-        triangles
+        if triangles.is_empty() {
+            (ClipOutcome::Outside, triangles)
+        } else {
+            (ClipOutcome::Clipped, triangles)
+        }
     }
 
     fn should_process_triangle(&self, triangle: &ClipTriangle) -> bool {
@@ -0,0 +1,186 @@
+use glam::{Affine3A, Mat4, Vec3, Vec4};
+
+use super::{Buffer, LineStyle};
+use crate::core::Color;
+
+/// Segments used for each circle of `DebugDraw::sphere` and `DebugDraw::circle`
+const CIRCLE_SEGMENTS: usize = 32;
+/// Length of an arrow's head relative to the whole arrow
+const ARROW_HEAD: f32 = 0.2;
+
+struct DebugLine {
+    start: Vec3,
+    end: Vec3,
+    color: Color,
+    style: LineStyle,
+}
+
+struct DebugLabel {
+    pos: Vec3,
+    text: String,
+    color: Color,
+}
+
+/// Immediate mode list of world space shapes drawn on top of the scene.
+///
+/// Anything pushed during a frame gets drawn by `Pipeline::render_frame` after the scene (depth
+/// tested against it, except for labels which always end up on top) and is cleared afterwards,
+/// so code that wants a shape to stay on screen pushes it again every frame.
+#[derive(Default)]
+pub struct DebugDraw {
+    lines: Vec<DebugLine>,
+    labels: Vec<DebugLabel>,
+    style: LineStyle,
+}
+
+impl DebugDraw {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Style of the lines pushed from now on
+    pub fn set_style(&mut self, style: LineStyle) {
+        self.style = style;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty() && self.labels.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.lines.clear();
+        self.labels.clear();
+    }
+
+    pub fn line(&mut self, start: Vec3, end: Vec3, color: Color) {
+        self.lines.push(DebugLine {
+            start,
+            end,
+            color,
+            style: self.style,
+        });
+    }
+
+    /// Line from `start` to `end` with a four finned head at `end`
+    pub fn arrow(&mut self, start: Vec3, end: Vec3, color: Color) {
+        self.line(start, end, color);
+        let shaft = end - start;
+        let Some(dir) = shaft.try_normalize() else {
+            return;
+        };
+        let length = shaft.length() * ARROW_HEAD;
+        let side = dir.any_orthonormal_vector() * length * 0.5;
+        let up = dir.cross(side);
+        let base = end - dir * length;
+        for fin in [side, -side, up, -up] {
+            self.line(end, base + fin, color);
+        }
+    }
+
+    /// The x, y and z axes of `transform` as red, green and blue lines of length `size`
+    pub fn axes(&mut self, transform: &Affine3A, size: f32) {
+        let origin = transform.transform_point3(Vec3::ZERO);
+        for (axis, color) in [
+            (Vec3::X, Color::RED),
+            (Vec3::Y, Color::GREEN),
+            (Vec3::Z, Color::BLUE),
+        ] {
+            let dir = transform.transform_vector3(axis).normalize_or_zero();
+            self.line(origin, origin + dir * size, color);
+        }
+    }
+
+    /// The 12 edges of an axis aligned box
+    pub fn aabb(&mut self, min: Vec3, max: Vec3, color: Color) {
+        let corner = |i: usize| {
+            Vec3::new(
+                if i & 1 == 0 { min.x } else { max.x },
+                if i & 2 == 0 { min.y } else { max.y },
+                if i & 4 == 0 { min.z } else { max.z },
+            )
+        };
+        self.box_edges(corner, color);
+    }
+
+    /// Circle around `center` in the plane perpendicular to `normal`
+    pub fn circle(&mut self, center: Vec3, normal: Vec3, radius: f32, color: Color) {
+        let normal = normal.normalize_or_zero();
+        if normal == Vec3::ZERO {
+            return;
+        }
+        let u = normal.any_orthonormal_vector() * radius;
+        let v = normal.cross(u);
+        let point = |i: usize| {
+            let angle = i as f32 / CIRCLE_SEGMENTS as f32 * std::f32::consts::TAU;
+            center + u * angle.cos() + v * angle.sin()
+        };
+        for i in 0..CIRCLE_SEGMENTS {
+            self.line(point(i), point(i + 1), color);
+        }
+    }
+
+    /// Three circles, one around each axis
+    pub fn sphere(&mut self, center: Vec3, radius: f32, color: Color) {
+        for normal in [Vec3::X, Vec3::Y, Vec3::Z] {
+            self.circle(center, normal, radius, color);
+        }
+    }
+
+    /// Outline of the volume `view_proj` maps to clip space, e.g. a camera's view frustum
+    pub fn frustum(&mut self, view_proj: &Mat4, color: Color) {
+        let inverse = view_proj.inverse();
+        let corner = |i: usize| {
+            let ndc = Vec4::new(
+                if i & 1 == 0 { -1.0 } else { 1.0 },
+                if i & 2 == 0 { -1.0 } else { 1.0 },
+                if i & 4 == 0 { 0.0 } else { 1.0 },
+                1.0,
+            );
+            let world = inverse * ndc;
+            world.truncate() / world.w
+        };
+        self.box_edges(corner, color);
+    }
+
+    /// Text anchored at a world space position, drawn on top of everything
+    pub fn text(&mut self, pos: Vec3, text: impl Into<String>, color: Color) {
+        self.labels.push(DebugLabel {
+            pos,
+            text: text.into(),
+            color,
+        });
+    }
+
+    /// Draws everything in the list into `buffer`, `view_proj` takes world space to clip space
+    pub fn render<B: Buffer + ?Sized>(&self, buffer: &mut B, view_proj: &Mat4) {
+        for line in &self.lines {
+            buffer.draw_line_world(view_proj, line.start, line.end, line.color, line.style);
+        }
+
+        let (width, height) = buffer.size();
+        for label in &self.labels {
+            let clip = *view_proj * label.pos.extend(1.0);
+            if clip.w <= 0.0 {
+                continue;
+            }
+            let ndc = clip.truncate() / clip.w;
+            let x = (ndc.x + 1.0) * 0.5 * width as f32;
+            let y = (1.0 - ndc.y) * 0.5 * height as f32;
+            if x < 0.0 || y < 0.0 || x >= width as f32 || y >= height as f32 {
+                continue;
+            }
+            buffer.draw_text((x as usize, y as usize), &label.text, label.color);
+        }
+    }
+
+    /// The 12 edges between 8 corners indexed by bits (x, y, z)
+    fn box_edges(&mut self, corner: impl Fn(usize) -> Vec3, color: Color) {
+        for i in 0..8 {
+            for bit in [1, 2, 4] {
+                if i & bit == 0 {
+                    self.line(corner(i), corner(i | bit), color);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,145 @@
+use glam::{Mat4, Vec4};
+
+use super::GBuffer;
+use crate::core::Color;
+
+/// What the pipeline presents, either the lit image or a single GBuffer channel
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum DebugView {
+    /// Normal rendering, lighting and post processing
+    #[default]
+    Lit,
+    Albedo,
+    /// World space normals remapped from -1..1 to 0..1 as RGB
+    Normal,
+    /// View depth as grayscale, white is closest, rescaled to the depth range on screen
+    Depth,
+    Specular,
+    /// Log scale grayscale, white is a shininess of 1024 or more
+    Shininess,
+    Dissolve,
+    /// A random looking but stable color per (entity, material) pair
+    MaterialId,
+    /// A random looking but stable color per entity
+    EntityId,
+    /// Texture coordinates as RG, wrapped to 0..1
+    Uv,
+}
+
+impl DebugView {
+    pub fn next(self) -> Self {
+        match self {
+            DebugView::Lit => DebugView::Albedo,
+            DebugView::Albedo => DebugView::Normal,
+            DebugView::Normal => DebugView::Depth,
+            DebugView::Depth => DebugView::Specular,
+            DebugView::Specular => DebugView::Shininess,
+            DebugView::Shininess => DebugView::Dissolve,
+            DebugView::Dissolve => DebugView::MaterialId,
+            DebugView::MaterialId => DebugView::EntityId,
+            DebugView::EntityId => DebugView::Uv,
+            DebugView::Uv => DebugView::Lit,
+        }
+    }
+
+    /// Color of GBuffer pixel `idx` under this view, None where there is no geometry.
+    ///
+    /// The result goes through `Buffer::create_pixel` like any other linear color, so views that
+    /// show data rather than colors (normals, depth, ids, ...) are decoded from sRGB first to end
+    /// up on screen with exactly their raw value.
+    pub fn color(&self, gbuffer: &GBuffer, idx: usize, depth: &DepthRange) -> Option<Color> {
+        if gbuffer.depth[idx] == f32::INFINITY {
+            return None;
+        }
+        let color = match self {
+            DebugView::Lit => return None,
+            DebugView::Albedo => return Some(gbuffer.albedo[idx]),
+            DebugView::Specular => return Some(gbuffer.specular[idx]),
+            DebugView::Normal => {
+                let n = gbuffer.normal[idx].normalize_or_zero() * 0.5 + 0.5;
+                Color::new(n.x, n.y, n.z)
+            }
+            DebugView::Depth => {
+                let v = 1.0 - depth.normalize(gbuffer.depth[idx]);
+                Color::new(v, v, v)
+            }
+            DebugView::Shininess => {
+                let v = (gbuffer.shininess[idx].max(1.0).log2() / 10.0).clamp(0.0, 1.0);
+                Color::new(v, v, v)
+            }
+            DebugView::Dissolve => {
+                let v = gbuffer.dissolve[idx].clamp(0.0, 1.0);
+                Color::new(v, v, v)
+            }
+            DebugView::MaterialId => match gbuffer.matid[idx] {
+                Some((entity, material)) => hash_color((entity << 16) ^ material),
+                None => Color::BLACK,
+            },
+            DebugView::EntityId => match gbuffer.matid[idx] {
+                Some((entity, _)) => hash_color(entity),
+                None => Color::BLACK,
+            },
+            DebugView::Uv => {
+                let uv = gbuffer.uv[idx];
+                Color::new(uv.x.rem_euclid(1.0), uv.y.rem_euclid(1.0), 0.0)
+            }
+        };
+        Some(color.to_linear())
+    }
+}
+
+/// Closest and furthest linear depth of everything on screen, used to spread the depth view
+/// over the full gray range instead of the camera's (mostly empty) near/far range
+pub struct DepthRange {
+    inv_projection: Mat4,
+    min: f32,
+    max: f32,
+}
+
+impl DepthRange {
+    pub fn new(gbuffer: &GBuffer, projection: &Mat4) -> Self {
+        let inv_projection = projection.inverse();
+        let (min, max) = gbuffer
+            .depth
+            .iter()
+            .filter(|depth| **depth != f32::INFINITY)
+            .map(|depth| linear_depth(*depth, &inv_projection))
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), d| {
+                (min.min(d), max.max(d))
+            });
+        Self {
+            inv_projection,
+            min,
+            max,
+        }
+    }
+
+    /// Maps a stored (non linear) depth to 0 at the closest and 1 at the furthest pixel.
+    /// Spaced logarithmically, a floor running off to the horizon would otherwise squash
+    /// everything up close into the same shade.
+    pub fn normalize(&self, depth: f32) -> f32 {
+        let min = self.min.max(f32::EPSILON);
+        let range = (self.max.max(min) / min).ln().max(f32::EPSILON);
+        ((linear_depth(depth, &self.inv_projection).max(min) / min).ln() / range).clamp(0.0, 1.0)
+    }
+}
+
+/// View space distance along the view direction for a stored depth. For a perspective projection
+/// it doesn't depend on the pixel, so the center of the screen is as good as any.
+fn linear_depth(depth: f32, inv_projection: &Mat4) -> f32 {
+    let view_pos = *inv_projection * Vec4::new(0.0, 0.0, depth * 2.0 - 1.0, 1.0);
+    -view_pos.z / view_pos.w
+}
+
+/// Integer hash (the murmur3 finalizer) spread over RGB, neighbouring ids get unrelated colors
+fn hash_color(id: usize) -> Color {
+    let mut h = id as u32 ^ 0x9E37_79B9;
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x85EB_CA6B);
+    h ^= h >> 13;
+    h = h.wrapping_mul(0xC2B2_AE35);
+    h ^= h >> 16;
+    // Keep every channel away from black so ids stay distinguishable from the background
+    let channel = |shift: u32| 0.2 + 0.8 * ((h >> shift) & 0xFF) as f32 / 255.0;
+    Color::new(channel(0), channel(8), channel(16))
+}
@@ -0,0 +1,300 @@
+use glam::{Mat4, Vec2, Vec3, Vec4};
+
+use super::{Buffer, CLIP_SPACE_PLANES};
+use crate::core::Color;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum LineStyle {
+    /// Plain Bresenham, one pixel wide
+    #[default]
+    Aliased,
+    /// Xiaolin Wu's algorithm, pixels get blended with what's already in the buffer by coverage
+    AntiAliased,
+    /// Bresenham with a span of the given number of pixels across the line
+    Thick(u32),
+}
+
+/// One end of a line after projection, with what's needed for perspective correct depth
+#[derive(Clone, Copy, Debug)]
+struct Endpoint {
+    screen: Vec2,
+    inv_w: f32,
+    /// NDC z
+    z: f32,
+}
+
+impl Endpoint {
+    fn new(clip: Vec4, width: usize, height: usize) -> Self {
+        let inv_w = 1.0 / clip.w;
+        let ndc = clip.truncate() * inv_w;
+        Self {
+            screen: Vec2::new(
+                (ndc.x + 1.0) * 0.5 * width as f32,
+                (1.0 - ndc.y) * 0.5 * height as f32,
+            ),
+            inv_w,
+            z: ndc.z,
+        }
+    }
+}
+
+/// Depth at `t` along the line (in screen space), interpolated the same perspective correct way
+/// the triangle rasterizer does it so lines drawn on a surface end up at the surface's depth
+fn depth_at(a: &Endpoint, b: &Endpoint, t: f32) -> f32 {
+    let inv_w = a.inv_w + (b.inv_w - a.inv_w) * t;
+    let z_over_w = a.z * a.inv_w + (b.z * b.inv_w - a.z * a.inv_w) * t;
+    let z = z_over_w / inv_w;
+    ((z + 1.0) * 0.5).clamp(0.0, 1.0)
+}
+
+/// Clips a clip space line against the view frustum, None when none of it is visible
+pub fn clip_line(start: Vec4, end: Vec4) -> Option<(Vec4, Vec4)> {
+    let (mut t0, mut t1) = (0.0f32, 1.0f32);
+    for plane in CLIP_SPACE_PLANES {
+        let d0 = plane.dot(start);
+        let d1 = plane.dot(end);
+        if d0 < 0.0 && d1 < 0.0 {
+            return None;
+        }
+        if d0 < 0.0 {
+            t0 = t0.max(d0 / (d0 - d1));
+        } else if d1 < 0.0 {
+            t1 = t1.min(d0 / (d0 - d1));
+        }
+    }
+    (t0 <= t1).then(|| (start.lerp(end, t0), start.lerp(end, t1)))
+}
+
+/// Clips a screen space line to the pixels of a `width` x `height` buffer (Liang-Barsky).
+/// Returns the visible range as parameters along the line, 0 is `start` and 1 is `end`.
+pub fn clip_to_screen(start: Vec2, end: Vec2, width: usize, height: usize) -> Option<(f32, f32)> {
+    if width == 0 || height == 0 {
+        return None;
+    }
+    let delta = end - start;
+    let max = Vec2::new(width as f32 - 1.0, height as f32 - 1.0);
+    let (mut t0, mut t1) = (0.0f32, 1.0f32);
+    // (p, q) pairs for the left, right, top and bottom edges
+    for (p, q) in [
+        (-delta.x, start.x),
+        (delta.x, max.x - start.x),
+        (-delta.y, start.y),
+        (delta.y, max.y - start.y),
+    ] {
+        if p == 0.0 {
+            if q < 0.0 {
+                return None;
+            }
+            continue;
+        }
+        let t = q / p;
+        if p < 0.0 {
+            t0 = t0.max(t);
+        } else {
+            t1 = t1.min(t);
+        }
+    }
+    (t0 <= t1).then_some((t0, t1))
+}
+
+/// Draws a line between two clip space points, clipped to the frustum and depth tested
+pub fn draw_clip_line<B: Buffer + ?Sized>(
+    buffer: &mut B,
+    start: Vec4,
+    end: Vec4,
+    color: Color,
+    style: LineStyle,
+) {
+    let Some((start, end)) = clip_line(start, end) else {
+        return;
+    };
+    let (width, height) = buffer.size();
+    let a = Endpoint::new(start, width, height);
+    let b = Endpoint::new(end, width, height);
+    let Some((t0, t1)) = clip_to_screen(a.screen, b.screen, width, height) else {
+        return;
+    };
+    let from = a.screen.lerp(b.screen, t0);
+    let to = a.screen.lerp(b.screen, t1);
+    let depth = |t: f32| depth_at(&a, &b, t0 + (t1 - t0) * t);
+
+    match style {
+        LineStyle::Aliased => {
+            let pixel = B::create_pixel(color);
+            bresenham(from, to, |pos, t| {
+                plot(buffer, pos, depth(t), pixel.clone());
+            });
+        }
+        LineStyle::Thick(thickness) => {
+            let pixel = B::create_pixel(color);
+            let thickness = thickness.max(1) as i32;
+            // Spans run across the major axis, which keeps the width constant at any angle
+            let delta = to - from;
+            let across = if delta.x.abs() >= delta.y.abs() {
+                Vec2::Y
+            } else {
+                Vec2::X
+            };
+            let first = -(thickness - 1) / 2;
+            bresenham(from, to, |pos, t| {
+                let depth = depth(t);
+                for offset in first..first + thickness {
+                    plot(buffer, pos + across * offset as f32, depth, pixel.clone());
+                }
+            });
+        }
+        LineStyle::AntiAliased => {
+            wu(from, to, |pos, t, coverage| {
+                if pos.x >= 0.0 && pos.y >= 0.0 {
+                    let pos = (pos.x as usize, pos.y as usize);
+                    buffer.blend_pixel(pos, &depth(t), color, coverage);
+                }
+            });
+        }
+    }
+}
+
+/// Draws a line between two world space points, `view_proj` takes them to clip space
+pub fn draw_world_line<B: Buffer + ?Sized>(
+    buffer: &mut B,
+    view_proj: &Mat4,
+    start: Vec3,
+    end: Vec3,
+    color: Color,
+    style: LineStyle,
+) {
+    draw_clip_line(
+        buffer,
+        *view_proj * start.extend(1.0),
+        *view_proj * end.extend(1.0),
+        color,
+        style,
+    );
+}
+
+/// `set_pixel` that skips positions left of or above the buffer instead of clamping them to 0
+fn plot<B: Buffer + ?Sized>(buffer: &mut B, pos: Vec2, depth: f32, pixel: B::Pixel) {
+    if pos.x >= 0.0 && pos.y >= 0.0 {
+        buffer.set_pixel((pos.x as usize, pos.y as usize), &depth, pixel);
+    }
+}
+
+/// Bresenham's line algorithm
+/// https://en.wikipedia.org/wiki/Bresenham%27s_line_algorithm
+///
+/// Calls `plot` with every pixel and how far along the line it is (0 at `start`, 1 at `end`)
+/// for interpolating depth or other attributes
+pub fn bresenham<F>(start: Vec2, end: Vec2, mut plot: F)
+where
+    F: FnMut(Vec2, f32),
+{
+    let mut x0 = start.x as i32;
+    let mut y0 = start.y as i32;
+    let x1 = end.x as i32;
+    let y1 = end.y as i32;
+
+    let dx = (x1 - x0).abs();
+    let sx = if x0 < x1 { 1 } else { -1 };
+    let dy = -(y1 - y0).abs();
+    let sy = if y0 < y1 { 1 } else { -1 };
+    let mut err = dx + dy;
+    let steps = dx.max(-dy).max(1) as f32;
+    let mut step = 0;
+
+    loop {
+        plot(Vec2::new(x0 as f32, y0 as f32), step as f32 / steps);
+
+        if x0 == x1 && y0 == y1 {
+            break;
+        }
+        let e2 = 2 * err;
+        if e2 >= dy {
+            err += dy;
+            x0 += sx;
+        }
+        if e2 <= dx {
+            err += dx;
+            y0 += sy;
+        }
+        step += 1;
+    }
+}
+
+/// Xiaolin Wu's line algorithm, calls `plot` with every pixel, how far along the line it is and
+/// how much of it the line covers (0-1)
+pub fn wu<F>(start: Vec2, end: Vec2, mut plot: F)
+where
+    F: FnMut(Vec2, f32, f32),
+{
+    // Walk along the major axis, treating steep lines as flat ones with x and y swapped
+    let steep = (end.y - start.y).abs() > (end.x - start.x).abs();
+    let (mut a, mut b) = if steep {
+        (Vec2::new(start.y, start.x), Vec2::new(end.y, end.x))
+    } else {
+        (start, end)
+    };
+    let reversed = a.x > b.x;
+    if reversed {
+        std::mem::swap(&mut a, &mut b);
+    }
+
+    let dx = b.x - a.x;
+    let gradient = if dx > f32::EPSILON {
+        (b.y - a.y) / dx
+    } else {
+        0.0
+    };
+
+    for x in a.x.round() as i32..=b.x.round() as i32 {
+        let x = x as f32;
+        let y = a.y + gradient * (x - a.x);
+        let t = if dx > f32::EPSILON {
+            ((x - a.x) / dx).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        let t = if reversed { 1.0 - t } else { t };
+
+        let y0 = y.floor();
+        let frac = y - y0;
+        for (py, coverage) in [(y0, 1.0 - frac), (y0 + 1.0, frac)] {
+            if coverage <= 0.0 {
+                continue;
+            }
+            let pos = if steep {
+                Vec2::new(py, x)
+            } else {
+                Vec2::new(x, py)
+            };
+            plot(pos, t, coverage);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn screen_clipping_keeps_lines_inside_the_buffer() {
+        let (t0, t1) = clip_to_screen(Vec2::new(-10.0, 5.0), Vec2::new(30.0, 5.0), 20, 10).unwrap();
+        let start = Vec2::new(-10.0, 5.0).lerp(Vec2::new(30.0, 5.0), t0);
+        let end = Vec2::new(-10.0, 5.0).lerp(Vec2::new(30.0, 5.0), t1);
+        assert_eq!(start, Vec2::new(0.0, 5.0));
+        assert_eq!(end, Vec2::new(19.0, 5.0));
+        assert!(clip_to_screen(Vec2::new(-5.0, -1.0), Vec2::new(25.0, -3.0), 20, 10).is_none());
+    }
+
+    #[test]
+    fn frustum_clipping_cuts_at_the_near_plane() {
+        let projection = Mat4::perspective_rh(1.0, 1.0, 0.1, 10.0);
+        // From behind the camera to in front of it, straight down the view axis
+        let start = projection * Vec4::new(0.0, 0.0, 1.0, 1.0);
+        let end = projection * Vec4::new(0.0, 0.0, -5.0, 1.0);
+        let (near, far) = clip_line(start, end).unwrap();
+        assert!(near.z.abs() < 1e-4 && near.w > 0.0);
+        assert_eq!(far, end);
+        let behind = projection * Vec4::new(0.0, 0.0, 2.0, 1.0);
+        assert!(clip_line(start, behind).is_none());
+    }
+}
@@ -3,16 +3,45 @@ use glam::{Mat4, Vec2};
 
 pub mod rasterizer;
 
+mod bloom;
+pub use bloom::{Bloom, BloomSettings};
 mod buffer;
 pub use buffer::Buffer;
 pub use buffer::FrameBuffer;
 pub use buffer::TermBuffer;
 pub use buffer::MAX_DIMS;
+#[cfg(feature = "terminal")]
+mod capabilities;
+#[cfg(feature = "terminal")]
+pub use capabilities::TerminalCapabilities;
 mod clipper;
+mod debug_draw;
+pub use debug_draw::DebugDraw;
+mod debug_view;
+pub use debug_view::{DebugView, DepthRange};
+pub mod line;
+pub use line::LineStyle;
 pub mod pipeline;
+mod outline;
+pub use outline::{Outline, OutlineSettings};
+mod post;
+mod present;
+#[cfg(feature = "window")]
+pub use present::WindowPresenter;
+pub use present::{ColorMode, FilePresenter, Frame, Presenter, TerminalPresenter};
+mod present_thread;
+pub use present_thread::{DropPolicy, PresentStats, ThreadedPresenter};
+pub mod profiler;
+pub use post::ToneMapping;
+mod ssao;
+mod text;
+pub use text::GlyphCache;
+pub use ssao::{Ssao, SsaoSettings};
+pub use clipper::ClipOutcome;
 pub use clipper::ClipTriangle;
 pub use clipper::ClipVertex;
 pub use clipper::Clipper;
+pub use clipper::CLIP_SPACE_PLANES;
 use glam::Vec3;
 
 /// Represents geometry that has been processed through the transformation pipeline
@@ -37,6 +66,8 @@ pub struct GBuffer {
     pub specular: Vec<Color>, // Specular color
     pub shininess: Vec<f32>,  // Shininess (for reflections)
     pub dissolve: Vec<f32>,   // Dissolve value
+    pub uv: Vec<Vec2>,        // Texture coordinates
+    pub depth_only: Vec<bool>, // Occluder from a hidden-line entity, has depth but isn't shaded
     pub matid: Vec<Option<(usize, usize)>>, // First is entity id, second is material id
                               //pub fragid: Vec<Option<usize>>, // mat id // HACK: // TODO: I will eventually figure out what place I passed the material and actually needed it, until then, fuck it lol
 }
@@ -50,6 +81,8 @@ impl GBuffer {
             specular: vec![Color::BLACK; size],
             shininess: vec![0.0; size],
             dissolve: vec![0.0; size],
+            uv: vec![Vec2::ZERO; size],
+            depth_only: vec![false; size],
             matid: vec![None; size],
         }
     }
@@ -61,6 +94,8 @@ impl GBuffer {
         self.specular.fill(Color::BLACK);
         self.shininess.fill(f32::NEG_INFINITY);
         self.dissolve.fill(f32::NEG_INFINITY);
+        self.uv.fill(Vec2::ZERO);
+        self.depth_only.fill(false);
         self.matid.fill(None);
     }
 }
@@ -76,6 +111,18 @@ pub struct ProjectedVertex {
     pub color: Color,
 }
 
+/// What a fragment contributes to the frame
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum FragmentKind {
+    /// Regular surface, goes through the GBuffer and gets shaded
+    #[default]
+    Surface,
+    /// Only occludes, written to the GBuffer's depth but shown as background (hidden-line fill)
+    DepthOnly,
+    /// Edge drawn on top of the finished image, depth tested against the GBuffer
+    Overlay,
+}
+
 /// ***A Pixel To Be***
 ///
 /// Represents a potential pixel in the graphics pipeline before final rasterization
@@ -103,6 +150,8 @@ pub struct Fragment {
     pub mat_id: Option<(usize, usize)>, // first is entity id, second is mat id
 
                                         // what about emissives tho
+    /// Whether this is shaded, only occludes, or is an overlay edge
+    pub kind: FragmentKind,
 }
 
 impl Default for Fragment {
@@ -121,6 +170,7 @@ impl Default for Fragment {
             dissolve: 0.,
             uv: Vec2::ZERO,
             mat_id: None,
+            kind: FragmentKind::Surface,
         }
     }
 }
@@ -0,0 +1,132 @@
+use glam::{Mat4, Vec4};
+use rayon::prelude::*;
+
+use super::GBuffer;
+use crate::core::Color;
+
+#[derive(Clone, Copy, Debug)]
+pub struct OutlineSettings {
+    pub enabled: bool,
+    /// Linear color of the lines, written as-is (no exposure or tone mapping)
+    pub color: Color,
+    /// Distance in pixels to the neighbours that get compared, effectively the line width
+    pub thickness: usize,
+    /// Relative jump in view distance that counts as a silhouette (0.1 = 10% further away)
+    pub depth_threshold: f32,
+    /// Cosine of the angle between neighbouring normals below which there is a crease
+    pub normal_threshold: f32,
+}
+
+impl Default for OutlineSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            color: Color::BLACK,
+            thickness: 1,
+            depth_threshold: 0.1,
+            normal_threshold: 0.6,
+        }
+    }
+}
+
+/// Edge detection on the GBuffer, finds silhouettes (depth), creases (normal) and
+/// object/material boundaries (matid)
+#[derive(Default)]
+pub struct Outline {
+    /// View distance per pixel, INFINITY where there is no geometry
+    distance: Vec<f32>,
+    /// Depth the outline should be written at, None where there is no edge
+    edges: Vec<Option<f32>>,
+}
+
+impl Outline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Edge depth per pixel from the last `detect` call
+    pub fn edges(&self) -> &[Option<f32>] {
+        &self.edges
+    }
+
+    pub fn detect(
+        &mut self,
+        gbuffer: &GBuffer,
+        width: usize,
+        height: usize,
+        projection: &Mat4,
+        settings: &OutlineSettings,
+    ) {
+        let size = width * height;
+        self.distance.resize(size, f32::INFINITY);
+        self.edges.resize(size, None);
+        if !settings.enabled {
+            self.edges.fill(None);
+            return;
+        }
+
+        // The stored depth is non linear, compare actual distances so the threshold means the
+        // same thing up close and far away
+        let inv_projection = projection.inverse();
+        self.distance
+            .par_chunks_mut(width)
+            .enumerate()
+            .for_each(|(y, row)| {
+                for (x, out) in row.iter_mut().enumerate() {
+                    let depth = gbuffer.depth[y * width + x];
+                    if depth == f32::INFINITY {
+                        *out = f32::INFINITY;
+                        continue;
+                    }
+                    let ndc = Vec4::new(
+                        (x as f32 / width as f32) * 2.0 - 1.0,
+                        1.0 - (y as f32 / height as f32) * 2.0,
+                        depth * 2.0 - 1.0,
+                        1.0,
+                    );
+                    let view_pos = inv_projection * ndc;
+                    *out = (view_pos.truncate() / view_pos.w).length();
+                }
+            });
+
+        let step = settings.thickness.max(1) as isize;
+        let distance = &self.distance;
+        self.edges
+            .par_chunks_mut(width)
+            .enumerate()
+            .for_each(|(y, row)| {
+                for (x, out) in row.iter_mut().enumerate() {
+                    let idx = y * width + x;
+                    let here = distance[idx];
+                    let mut edge: Option<f32> = None;
+
+                    for (dx, dy) in [(-step, 0), (step, 0), (0, -step), (0, step)] {
+                        let nx = x as isize + dx;
+                        let ny = y as isize + dy;
+                        if nx < 0 || ny < 0 || nx >= width as isize || ny >= height as isize {
+                            continue;
+                        }
+                        let n = ny as usize * width + nx as usize;
+                        let there = distance[n];
+                        // Only the pixel further away gets the line, this keeps lines one
+                        // `thickness` wide and puts silhouettes just outside the object
+                        if there == f32::INFINITY || there > here {
+                            continue;
+                        }
+
+                        let silhouette = here == f32::INFINITY
+                            || (here - there) > settings.depth_threshold * there;
+                        let crease = here != f32::INFINITY
+                            && (gbuffer.matid[idx] != gbuffer.matid[n]
+                                || gbuffer.normal[idx].dot(gbuffer.normal[n])
+                                    < settings.normal_threshold);
+                        if silhouette || crease {
+                            let depth = gbuffer.depth[n];
+                            edge = Some(edge.map_or(depth, |d| d.min(depth)));
+                        }
+                    }
+                    *out = edge;
+                }
+            });
+    }
+}
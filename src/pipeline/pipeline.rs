@@ -1,28 +1,78 @@
 use std::time::Instant;
-pub(crate) use std::{cell::RefCell, io};
+pub(crate) use std::io;
 
-use crossterm::event;
-use crossterm::event::Event;
-use crossterm::event::KeyCode;
-use crossterm::event::KeyEvent;
-use crossterm::event::MouseEventKind;
+#[cfg(feature = "terminal")]
+use crossterm::event::{self, Event, KeyCode, KeyEvent, MouseEventKind};
 use glam::Vec3;
-use glam::{Affine3A, Mat4, Vec2, Vec4};
-use minifb::{Key, KeyRepeat, MouseButton, MouseMode, Window};
+use glam::{Mat4, Vec2, Vec4};
+// Only the input handlers use these
+#[cfg(any(feature = "window", feature = "terminal"))]
+use {crate::util::format_mat4, glam::Affine3A};
+#[cfg(feature = "window")]
+use minifb::{Key, KeyRepeat, MouseButton, MouseMode};
 
 use super::{
-    buffer::Buffer, rasterizer::Rasterizer, Clipper, Fragment, GBuffer, ProcessedGeometry,
+    buffer::Buffer, rasterizer::Rasterizer, Bloom, BloomSettings, ClipOutcome, Clipper, DebugDraw,
+    DebugView, DepthRange, Presenter,
+    Fragment, FragmentKind, GBuffer, Outline, OutlineSettings, ProcessedGeometry, Ssao,
+    SsaoSettings, ToneMapping,
+    profiler::{Profiler, Stage},
+};
+use crate::core::{Light, LightType};
+use crate::core::Sky;
+use crate::core::{
+    BlinnPhongShading, FlatShading, LightMode, LightingModel, ShadeInput, ToonShading,
 };
-use crate::core::LightType;
-use crate::core::{BlinnPhongShading, FlatShading, LightMode, LightingModel};
 use crate::{
-    core::{Color, RenderMode, Scene},
-    debug_print,
+    core::{Color, Scene},
     pipeline::{ClipTriangle, ClipVertex},
-    util::format_mat4,
     Metrics,
 };
 
+/// Depth the sky gets written at, anything rasterized is always in front of it
+const SKY_DEPTH: f32 = f32::MAX;
+
+/// Exposure changes by a factor of 2^(1/4) per key press, four presses is one stop. Public, like
+/// the light edit steps below, so frontends with their own input handling can use the same steps.
+pub const EXPOSURE_STEP: f32 = 1.189_207_1;
+pub const MIN_EXPOSURE: f32 = 1.0 / 64.0;
+pub const MAX_EXPOSURE: f32 = 64.0;
+/// Empty pixels only get written once bloom spills more than this much light into them
+const GLOW_CUTOFF: f32 = 1e-3;
+/// Overlay edges are pulled this fraction of their distance towards the camera before the depth
+/// test (a polygon offset), so they win against the surface they lie on
+const OVERLAY_DEPTH_OFFSET: f32 = 0.01;
+/// Radius of the spheres marking point and spot lights, in world units
+const LIGHT_MARKER_RADIUS: f32 = 0.15;
+/// Light edit mode steps: intensity and attenuation are multiplied, the spot cone widens by
+/// the angle (radians)
+pub const LIGHT_INTENSITY_STEP: f32 = 1.25;
+pub const LIGHT_ATTENUATION_STEP: f32 = 1.25;
+pub const LIGHT_CONE_STEP: f32 = 0.035;
+/// Shown at the bottom of the HUD, every binding of both input handlers
+const HUD_KEY_HINTS: &str = "wasd/space/shift move  arrows look  o orbit  u reset  0 1 spin
+[ ] select  j move entity  r render mode  p wireframe
+l lighting  t tone map  -/= exposure  g bloom  k ssao  f fog
+v debug view  m markers  b bounds  c freeze frustum
+i edit lights  3 orbit light  e camera info  / matrices
+tab hud  ` log  q quit";
+/// Shown instead of `HUD_KEY_HINTS` while editing lights, where several keys change meaning
+const HUD_LIGHT_KEY_HINTS: &str = "[ ] select light  3 orbit light  i stop editing
+wasd/space/shift move light  -/= intensity  h color
+, . cone  ; ' range  m markers  tab hud  ` log  q quit";
+/// Most lines the log panel shows, it never takes more than a third of the screen
+const LOG_PANEL_LINES: usize = 10;
+/// Colors the selected light cycles through in light edit mode
+const LIGHT_COLORS: [Color; 7] = [
+    Color::WHITE,
+    Color::RED,
+    Color::ORANGE,
+    Color::YELLOW,
+    Color::GREEN,
+    Color::CYAN,
+    Color::BLUE,
+];
+
 pub struct States {
     pub draw_wireframe: bool,
     pub bake_normals: bool,
@@ -34,6 +84,40 @@ pub struct States {
     pub is_mouse_pan_enabled: bool,
     pub last_mouse_pos: Option<(f32, f32)>,
     pub draw_light_marker: bool,
+    /// Movement, intensity and selection keys act on `current_light` instead of the
+    /// camera/entities, light markers are always drawn while it's on
+    pub edit_light: bool,
+    /// Index into `Scene::lights` of the light being edited
+    pub current_light: usize,
+    /// Linear multiplier applied to the HDR lighting result before tone mapping
+    pub exposure: f32,
+    pub tone_mapping: ToneMapping,
+    pub bloom: BloomSettings,
+    pub ssao: SsaoSettings,
+    /// Band counts used by `LightMode::Toon`
+    pub toon: ToonShading,
+    /// Edge lines drawn on top of `LightMode::Toon`
+    pub outline: OutlineSettings,
+    /// GBuffer channel presented instead of the lit image
+    pub debug_view: DebugView,
+    /// Color of the edges drawn by `RenderMode::HiddenLine` and `RenderMode::SolidWireframe`
+    pub wire_color: Color,
+    /// Draw the stats/mode/key hint overlay
+    pub draw_hud: bool,
+    /// Draw the most recent log messages above the key hints
+    pub draw_log: bool,
+    /// Draw the bounding box, axes and name of every scene entity, the selected one highlighted
+    pub draw_bounds: bool,
+    /// A camera view-projection captured with the frustum key, drawn until it is released
+    pub frozen_frustum: Option<Mat4>,
+}
+
+/// A transformed triangle waiting for the clipper
+struct ClipInput {
+    triangle: ClipTriangle,
+    material_id: Option<usize>,
+    /// Vertex ids of the triangle in its mesh
+    world_pos: [usize; 3],
 }
 
 /// A graphics rendering pipeline that processes 3D geometry into 2D screen output
@@ -43,59 +127,87 @@ pub struct States {
 /// - Triangle clipping against view frustum
 /// - Rasterization of triangles to fragments
 /// - Fragment processing and writing to framebuffer
+///
+/// All of its state is owned plain data, so a pipeline can be moved to a render thread and handed
+/// `Scene` snapshots (clones) from a thread that keeps simulating the live scene.
 pub struct Pipeline<B: Buffer> {
-    pub width: usize,                          // Screen width in pixels
-    pub height: usize,                         // Screen height in pixels
-    pub scene: Scene,                          // 3D scene with camera and objects
-    pub states: RefCell<States>,               // Pipeline state flags
-    pub scale_factor: usize,                   // Scale factor, 2 means render at half resolution
-    front_buffer: RefCell<B>,                  // Currently displayed buffer
-    back_buffer: RefCell<B>,                   // Buffer being rendered to
-    geometry: RefCell<Vec<ProcessedGeometry>>, // Transformed geometry ready for rasterization
-    rasterizer: RefCell<Rasterizer>,           // Converts triangles to fragments
-    clipper: RefCell<Clipper>,                 // Clips triangles against view frustum
-    fragments: RefCell<Vec<Fragment>>,         // Output fragments from rasterization
-    metrics: RefCell<Metrics>,                          // Performance metrics
-    gbuffer: RefCell<GBuffer>,                 // Pre-Lighting pass buffer of fragments
+    pub width: usize,                 // Screen width in pixels
+    pub height: usize,                // Screen height in pixels
+    pub scene: Scene,                 // 3D scene with camera and objects
+    pub states: States,               // Pipeline state flags
+    pub scale_factor: usize,          // Scale factor, 2 means render at half resolution
+    pixel_aspect: f32,                // Height over width of one pixel (terminal cell)
+    front_buffer: B,                  // Currently displayed buffer
+    back_buffer: B,                   // Buffer being rendered to
+    geometry: Vec<ProcessedGeometry>, // Transformed geometry ready for rasterization
+    clip_input: Vec<ClipInput>,       // Clip space triangles of the entity being processed
+    rasterizer: Rasterizer,           // Converts triangles to fragments
+    clipper: Clipper,                 // Clips triangles against view frustum
+    fragments: Vec<Fragment>,         // Output fragments from rasterization
+    metrics: Metrics,                 // Performance metrics
+    gbuffer: GBuffer,                 // Pre-Lighting pass buffer of fragments
+    hdr: Vec<Color>,                  // Linear lighting result, before tone mapping
+    bloom: Bloom,                     // Scratch buffers of the bloom pass
+    ssao: Ssao,                       // Ambient occlusion computed from the gbuffer
+    outline: Outline,                 // Edge detection for the toon outlines
+    debug_draw: DebugDraw,            // Shapes drawn over the scene, cleared every frame
+    profiler: Profiler,               // Per stage timings and triangle counts
 }
 
 impl<B: Buffer> Pipeline<B> {
-    pub fn front_buffer(&self) -> &RefCell<B> {
+    pub fn front_buffer(&self) -> &B {
         &self.front_buffer
     }
 
-    pub fn back_buffer(&self) -> &RefCell<B> {
+    pub fn front_buffer_mut(&mut self) -> &mut B {
+        &mut self.front_buffer
+    }
+
+    pub fn back_buffer(&self) -> &B {
         &self.back_buffer
     }
 
-    pub fn geometry(&self) -> &RefCell<Vec<ProcessedGeometry>> {
+    pub fn geometry(&self) -> &[ProcessedGeometry] {
         &self.geometry
     }
 
-    pub fn rasterizer(&self) -> &RefCell<Rasterizer> {
+    pub fn rasterizer(&self) -> &Rasterizer {
         &self.rasterizer
     }
 
-    pub fn fragments(&self) -> &RefCell<Vec<Fragment>> {
+    pub fn fragments(&self) -> &[Fragment] {
         &self.fragments
     }
 
+    pub fn profiler(&self) -> &Profiler {
+        &self.profiler
+    }
+
+    pub fn profiler_mut(&mut self) -> &mut Profiler {
+        &mut self.profiler
+    }
+
+    /// Debug shapes pushed here get drawn over the next frame
+    pub fn debug_draw(&mut self) -> &mut DebugDraw {
+        &mut self.debug_draw
+    }
 }
 
 impl<B: Buffer> Pipeline<B> {
     pub fn new(width: usize, height: usize, scene: Scene) -> Self {
-        Self {
+        let mut pipeline = Self {
             width,
             height,
-            front_buffer: RefCell::new(B::new(width, height)),
-            back_buffer: RefCell::new(B::new(width, height)),
+            front_buffer: B::new(width, height),
+            back_buffer: B::new(width, height),
             scene,
-            metrics: RefCell::new(Metrics::new()),
-            geometry: RefCell::new(Vec::with_capacity(1024)),
-            rasterizer: RefCell::new(Rasterizer::new(width, height)),
-            clipper: RefCell::new(Clipper::new()), // Add this
-            fragments: RefCell::new(Vec::with_capacity(1024)),
-            states: RefCell::new(States {
+            metrics: Metrics::new(),
+            geometry: Vec::with_capacity(1024),
+            clip_input: Vec::with_capacity(1024),
+            rasterizer: Rasterizer::new(width, height),
+            clipper: Clipper::new(), // Add this
+            fragments: Vec::with_capacity(1024),
+            states: States {
                 draw_wireframe: false,
                 bake_normals: false,
                 backface_culling: true,
@@ -106,10 +218,33 @@ impl<B: Buffer> Pipeline<B> {
                 last_mouse_pos: None,
                 is_mouse_pan_enabled: false,
                 draw_light_marker: false,
-            }),
-            gbuffer: RefCell::new(GBuffer::new(width * height)),
+                edit_light: false,
+                current_light: 0,
+                exposure: 1.0,
+                tone_mapping: ToneMapping::default(),
+                bloom: BloomSettings::default(),
+                ssao: SsaoSettings::default(),
+                toon: ToonShading::default(),
+                outline: OutlineSettings::default(),
+                debug_view: DebugView::default(),
+                wire_color: Color::WHITE,
+                draw_hud: true,
+                draw_log: false,
+                draw_bounds: false,
+                frozen_frustum: None,
+            },
+            gbuffer: GBuffer::new(width * height),
+            hdr: vec![Color::BLACK; width * height],
+            bloom: Bloom::new(),
+            ssao: Ssao::new(width * height),
+            outline: Outline::new(),
+            debug_draw: DebugDraw::new(),
+            profiler: Profiler::new(),
             scale_factor: 1,
-        }
+            pixel_aspect: B::PIXEL_ASPECT,
+        };
+        pipeline.update_camera_aspect();
+        pipeline
     }
     pub fn new_with_scale(width: usize, height: usize, scene: Scene, scale_factor: usize) -> Self {
         let width = width / scale_factor;
@@ -117,44 +252,142 @@ impl<B: Buffer> Pipeline<B> {
         Self::new(width, height, scene)
     }
 
+    /// Changes the output size to `width`x`height` pixels, keeping the scene, states, metrics and
+    /// profile. Every per pixel buffer is reallocated and the camera gets the new aspect ratio.
+    /// Sizes are clamped to at least 1, a minimized window can report 0.
+    pub fn resize(&mut self, width: usize, height: usize) {
+        let (width, height) = (width.max(1), height.max(1));
+        if (width, height) == (self.width, self.height) {
+            return;
+        }
+        log::debug!("Resizing pipeline to {width}x{height}");
+        self.width = width;
+        self.height = height;
+        self.front_buffer = B::new(width, height);
+        self.back_buffer = B::new(width, height);
+        self.rasterizer = Rasterizer::new(width, height);
+        self.gbuffer = GBuffer::new(width * height);
+        self.hdr = vec![Color::BLACK; width * height];
+        // Bloom, SSAO and outline scratch buffers follow the size they're computed at
+        self.update_camera_aspect();
+    }
+
+    /// Height over width of one pixel, `B::PIXEL_ASPECT` unless set
+    pub fn pixel_aspect(&self) -> f32 {
+        self.pixel_aspect
+    }
+
+    /// Sets the shape of a pixel, e.g. a terminal's cell height over width from
+    /// `TerminalCapabilities::cell_aspect`. The camera's projection gets the aspect ratio of the
+    /// screen this makes up so geometry keeps its proportions on non square pixels.
+    pub fn set_pixel_aspect(&mut self, pixel_aspect: f32) {
+        self.pixel_aspect = pixel_aspect;
+        self.update_camera_aspect();
+    }
+
+    /// Matches the camera to the screen's real shape, width over height in physical units
+    fn update_camera_aspect(&mut self) {
+        let aspect = self.width as f32 / (self.height as f32 * self.pixel_aspect);
+        if aspect.is_finite() && aspect > 0.0 && self.scene.camera.aspect_ratio() != aspect {
+            self.scene.camera.set_aspect_ratio(aspect);
+        }
+    }
+
     /// Main render loop function - processes one frame
     ///
     /// Steps:
     /// 1. Clear back buffer
-    /// 2. Process environment geometry
+    /// 2. Process scene and environment geometry
     /// 3. Transform vertices to clip space and clip triangles
     /// 4. Rasterize visible triangles to fragments
     /// 5. Process fragments and write to gbuffer
-    /// 6. Do Lighting Pass on Gbuffer, and then write to back buffer
-    /// 7. Present back buffer to window or output
-    /// 8. Swap front and back buffers
-    pub fn render_frame(&self, window: Option<&mut Window>, frame_delta: std::time::Duration) -> io::Result<()> {
-        self.back_buffer.borrow_mut().clear();
-        self.gbuffer.borrow_mut().clear();
+    /// 6. Do Lighting Pass on Gbuffer into the HDR buffer
+    /// 7. Post processing (bloom) on the HDR buffer
+    /// 8. Tone map the HDR buffer into the back buffer
+    /// 9. Draw overlay edges (hidden-line, solid + wire) on top
+    /// 10. Draw the debug shapes (light markers, bounds, ...) on top
+    /// 11. Draw the HUD
+    /// 12. Hand the front buffer to the presenters
+    /// 13. Swap front and back buffers
+    ///
+    /// Every presenter gets the finished frame, pass none to only render.
+    pub fn render_frame(
+        &mut self,
+        presenters: &mut [&mut dyn Presenter],
+        frame_delta: std::time::Duration,
+    ) -> io::Result<()> {
+        self.profiler.begin_frame();
+        self.back_buffer.clear();
+        self.gbuffer.clear();
+        self.hdr.fill(Color::BLACK);
 
         // 1. Process vertices to clip space
+        // 2. Clip triangles, both in process_geometry which records the two stages itself
         self.process_geometry();
 
-        // 2. Clip triangles (already integrated in process_geometry)
-        // The clipper operates during geometry processing
-
         // 3. Rasterize clipped triangles
+        let started = Instant::now();
         self.rasterize();
+        self.profiler.record(Stage::Rasterize, started);
+
+        {
+            let metrics = &mut self.metrics;
+            metrics.triangles = self.geometry.len();
+            metrics.fragments = self.fragments.len();
+            self.profiler.current().fragments = metrics.fragments;
+        }
 
         // 4. Process fragments into gbuffer
-        self.process_fragments(&self.fragments.borrow());
+        let started = Instant::now();
+        // Taken out for the call so the fragments can be read while the GBuffer gets written
+        let fragments = std::mem::take(&mut self.fragments);
+        self.process_fragments(&fragments);
+        self.fragments = fragments;
+        self.profiler.record(Stage::Fragments, started);
         // 5. Lighting pass (will automatically skip if lighting is disabled
-        self.lighting_pass();
+        let debug_view = self.states.debug_view;
+        let started = Instant::now();
+        if debug_view != DebugView::Lit {
+            // Looking at a raw GBuffer channel, nothing to light
+            self.debug_view_pass(debug_view);
+            self.profiler.record(Stage::Lighting, started);
+        } else if self.lighting_pass() {
+            self.profiler.record(Stage::Lighting, started);
+            // 6. Post processing and tone mapping, only when lighting produced HDR output
+            let started = Instant::now();
+            self.post_process();
+            self.resolve();
+            self.profiler.record(Stage::PostProcess, started);
+        } else {
+            self.profiler.record(Stage::Lighting, started);
+        }
+        let started = Instant::now();
+        self.overlay_pass();
+        self.draw_light_markers();
+        self.draw_scene_debug();
+        self.debug_draw_pass();
+        self.hud_pass();
+        self.log_panel_pass();
+        self.profiler.record(Stage::Overlay, started);
 
         // Present
-        if let Some(window) = window {
-            self.front_buffer.borrow().present_window(window)?;
-        } else {
-            self.front_buffer.borrow().present()?;
+        let started = Instant::now();
+        {
+            let front = &self.front_buffer;
+            let frame = front.frame();
+            for presenter in presenters.iter_mut() {
+                presenter.present(&frame)?;
+            }
+            self.profiler.current().present_bytes = presenters
+                .iter()
+                .filter_map(|presenter| presenter.bytes_written())
+                .sum();
         }
+        self.profiler.record(Stage::Present, started);
 
         self.swap_buffers();
         self.update_metrics(frame_delta);
+        self.profiler.end_frame();
         Ok(())
     }
 
@@ -165,27 +398,33 @@ impl<B: Buffer> Pipeline<B> {
     /// 2. Transform vertices to clip space
     /// 3. Clip triangles against view frustum
     /// 4. Store processed geometry for rasterization
-    pub fn process_geometry(&self) {
+    ///
+    /// The transform and clip loops are timed separately per entity, as the Geometry and
+    /// Clipping stages.
+    pub fn process_geometry(&mut self) {
+        let started = Instant::now();
+        // The scene may have been swapped for a snapshot with a camera set up for other screens
+        self.update_camera_aspect();
         let view_matrix = self.scene.camera.view_matrix();
         let projection_matrix = self.scene.camera.projection_matrix();
 
-        // Update clipper with current frustum planes
-        self.clipper
-            .borrow_mut()
-            .update_frustum_planes(&self.scene.camera.frustum_planes());
-
-        self.geometry.borrow_mut().clear();
-        debug_print!(
+        // NOTE: The clipper works on clip space vertices, so it uses the fixed clip space
+        // frustum planes rather than the camera's world space ones
+        self.geometry.clear();
+        log::trace!(
             "Processing geometry for {} entities",
             self.scene.entities.len()
         );
 
-        for (i, entity) in self.scene.entities.iter().enumerate() {
-            entity.update();
+        self.scene.update();
+        self.profiler.record(Stage::Geometry, started);
+        for (i, entity) in self.scene.renderable_entities().enumerate() {
+            let started = Instant::now();
             let model_matrix = Mat4::from(*entity.transform());
             let mvp_matrix = projection_matrix * view_matrix * model_matrix;
 
-            // Process each triangle
+            // Transform each triangle
+            self.clip_input.clear();
             for tri in &entity.mesh.tris {
                 // Get the material’s base color (if available)
                 let material_color = tri.material.and_then(|mat_id| {
@@ -195,7 +434,7 @@ impl<B: Buffer> Pipeline<B> {
                         .get(mat_id)
                         .map(|mat| mat.get_base_color())
                 });
-                // println!("MATERIAL COLOR: {:?}", material_color);
+                // log::info!("MATERIAL COLOR: {:?}", material_color);
 
                 // For each vertex, if no per-vertex color is provided then use the material's base color (or white)
                 let v0 = &entity.mesh.vertices[tri.vertices[0]];
@@ -234,34 +473,53 @@ impl<B: Buffer> Pipeline<B> {
                 let clip_triangle = ClipTriangle {
                     vertices: clip_verts,
                 };
+                self.clip_input.push(ClipInput {
+                    triangle: clip_triangle,
+                    material_id: tri.material,
+                    world_pos: tri.vertices,
+                });
+            }
+            self.profiler.record(Stage::Geometry, started);
+
+            // Clip the triangles (using the clipper) and add the results to the geometry buffer
+            // for rasterization
+            let started = Instant::now();
+            let stats = self.profiler.current();
+            for input in &self.clip_input {
+                let (outcome, clipped_triangles) = self.clipper.clip(&input.triangle);
+                stats.triangles_in += 1;
+                stats.triangles_out += clipped_triangles.len();
+                match outcome {
+                    ClipOutcome::Culled => stats.triangles_culled += 1,
+                    ClipOutcome::Clipped => stats.triangles_clipped += 1,
+                    ClipOutcome::Outside => stats.triangles_outside += 1,
+                    ClipOutcome::Inside => {}
+                }
 
-                // Clip the triangle (using the clipper)
-                let clipped_triangles = self.clipper.borrow().clip_triangle(&clip_triangle);
-
-                // Add resulting triangles to the geometry buffer for rasterization
                 for triangle in clipped_triangles {
-                    self.geometry.borrow_mut().push(ProcessedGeometry {
+                    self.geometry.push(ProcessedGeometry {
                         transform: mvp_matrix,
                         entity_id: i,
                         vertices: triangle.vertices,
-                        material_id: tri.material,
-                        world_pos: tri.vertices,
+                        material_id: input.material_id,
+                        world_pos: input.world_pos,
                     });
                 }
             }
+            self.profiler.record(Stage::Clipping, started);
         }
     }
 
-    pub fn rasterize(&self) {
-        self.rasterizer.borrow_mut().rasterize(
-            &self.geometry.borrow(),
+    pub fn rasterize(&mut self) {
+        self.rasterizer.rasterize(
+            &self.geometry,
             &self.scene,
-            &mut self.fragments.borrow_mut(),
+            &mut self.fragments,
         );
     }
-    pub fn process_fragments(&self, fragments: &[Fragment]) {
+    pub fn process_fragments(&mut self, fragments: &[Fragment]) {
         // IMPORTANT, THIS NOW PROCESSES INTO A GBUFFER, WHICH THEN PROCESSES INTO THE BACK BUFFER
-        let mut gbuffer = self.gbuffer.borrow_mut();
+        let gbuffer = &mut self.gbuffer;
         for (idx, fragment) in fragments.iter().enumerate() {
             let x = fragment.screen_pos.x as usize;
             let y = fragment.screen_pos.y as usize;
@@ -269,6 +527,10 @@ impl<B: Buffer> Pipeline<B> {
                 continue;
             } // duh
             let idx = y * self.width + x;
+            if fragment.kind == FragmentKind::Overlay {
+                // Drawn after lighting by `overlay_pass`
+                continue;
+            }
             if fragment.depth < gbuffer.depth[idx] {
                 // depth test
                 gbuffer.albedo[idx] = fragment.albedo;
@@ -276,37 +538,51 @@ impl<B: Buffer> Pipeline<B> {
                 gbuffer.depth[idx] = fragment.depth;
                 gbuffer.specular[idx] = fragment.specular;
                 gbuffer.shininess[idx] = fragment.shininess;
+                gbuffer.dissolve[idx] = fragment.dissolve;
+                gbuffer.uv[idx] = fragment.uv;
+                gbuffer.depth_only[idx] = fragment.kind == FragmentKind::DepthOnly;
                 gbuffer.matid[idx] = fragment.mat_id;
             }
         }
     }
 
-    pub fn swap_buffers(&self) {
+    pub fn swap_buffers(&mut self) {
         std::mem::swap(
-            &mut *self.front_buffer.borrow_mut(),
-            &mut *self.back_buffer.borrow_mut(),
+            &mut self.front_buffer,
+            &mut self.back_buffer,
         );
     }
 
-    pub fn update_metrics(&self, frame_delta: std::time::Duration) {
-        self.metrics.borrow_mut().update(frame_delta);
+    pub fn update_metrics(&mut self, frame_delta: std::time::Duration) {
+        self.metrics.update(frame_delta);
     }
-    pub fn get_front_buffer(&self) -> &RefCell<B> {
+    pub fn get_front_buffer(&self) -> &B {
         &self.front_buffer
     }
-    pub fn get_back_buffer(&self) -> &RefCell<B> {
+    pub fn get_back_buffer(&self) -> &B {
         &self.back_buffer
     }
 
-    pub fn lighting_pass(&self) {
+    /// Shades the GBuffer into the HDR buffer, returns false when it wrote straight to the back
+    /// buffer instead (unlit/wireframe modes) and there is nothing left to resolve
+    pub fn lighting_pass(&mut self) -> bool {
+        // Obtain inverse view_proj Matrix  (helps us reconstruct world space positions, by applying the inverse dot to the vector we basically "un project" but after doing/applying clipping and a depth buffer pass and stuff. This way we ultimately minimize the amount of things we have to shade
+        let view = self.scene.camera.view_matrix();
+        let proj = self.scene.camera.projection_matrix();
+        let inv_viewproj = (proj * view).inverse();
+
         // Early Exit (cases include drawing wireframes for debugging, or just not doing any lighting)
         {
             // cheeky scope so the value gets dropped
-            let states = self.states.borrow();
+            let states = &self.states;
             if states.draw_wireframe || states.light_mode == LightMode::None {
+                self.sky_pass(&inv_viewproj);
                 // Just populate the back buffer as is (copying old code directly over)
-                let mut buffer = self.back_buffer.borrow_mut();
-                for fragment in self.fragments.borrow().iter() {
+                let buffer = &mut self.back_buffer;
+                for fragment in self.fragments.iter() {
+                    if fragment.kind != FragmentKind::Surface {
+                        continue;
+                    }
                     let pixel = B::create_pixel(fragment.albedo);
                     let pos = (
                         fragment.screen_pos.x as usize,
@@ -314,19 +590,39 @@ impl<B: Buffer> Pipeline<B> {
                     );
                     buffer.set_pixel(pos, &fragment.depth, pixel);
                 }
-                return;
+                return false;
             }
         }
-        // Obtain inverse view_proj Matrix  (helps us reconstruct world space positions, by applying the inverse dot to the vector we basically "un project" but after doing/applying clipping and a depth buffer pass and stuff. This way we ultimately minimize the amount of things we have to shade
-        let view = self.scene.camera.view_matrix();
-        let proj = self.scene.camera.projection_matrix();
-        let inv_viewproj = (proj * view).inverse();
-
-        let gbuffer = self.gbuffer.borrow_mut();
+        let sky = self.scene.environment.sky();
+        let image_light = self.scene.environment.image_light();
+        let fog = self.scene.environment.fog();
+        let eye = self.scene.camera.position();
+        let toon = self.states.toon;
+        let gbuffer = &self.gbuffer;
+        let ssao_settings = self.states.ssao;
+        let ssao = &mut self.ssao;
+        ssao.compute(
+            gbuffer,
+            self.width,
+            self.height,
+            &view,
+            &proj,
+            &ssao_settings,
+        );
+        let occlusion = ssao.occlusion();
+        let hdr = &mut self.hdr;
         for y in 0..self.height {
             for x in 0..self.width {
                 let idx = y * self.width + x;
-                if gbuffer.depth[idx] == f32::INFINITY {
+                if gbuffer.depth[idx] == f32::INFINITY || gbuffer.depth_only[idx] {
+                    if let Some(color) =
+                        sky_color(sky, (x, y), (self.width, self.height), eye, &inv_viewproj)
+                    {
+                        hdr[idx] = color;
+                    } else if fog.is_enabled() {
+                        // Without a sky, geometry fades into the fog color
+                        hdr[idx] = fog.color;
+                    }
                     continue;
                 }
 
@@ -347,63 +643,436 @@ impl<B: Buffer> Pipeline<B> {
                 let mut mat = None;
                 if let Some(matid) = matid {
                     let (entid, matid) = matid;
-                    mat = Some(&self.scene.entities[entid].mesh.materials[matid]);
+                    mat = self.scene.entity(entid).mesh.materials.get(matid);
                 }
 
+                let input = ShadeInput {
+                    albedo,
+                    normal,
+                    specular,
+                    shininess,
+                    position: world_pos,
+                    view_dir,
+                    material: mat,
+                    occlusion: occlusion[idx],
+                };
                 let final_color = Color::BLACK;
-                let final_color = match self.states.borrow().light_mode {
+                let final_color = match self.states.light_mode {
                     LightMode::None => final_color,
-                    LightMode::BlinnPhong => BlinnPhongShading.shade(
-                        albedo,
-                        normal,
-                        specular,
-                        shininess,
-                        world_pos,
-                        view_dir,
-                        &self.scene.lights,
-                        mat,
-                    ),
-                    LightMode::Flat => FlatShading.shade(
-                        albedo,
-                        normal,
-                        specular,
-                        shininess,
-                        world_pos,
-                        view_dir,
-                        &self.scene.lights,
-                        mat,
-                    ),
+                    LightMode::BlinnPhong => {
+                        BlinnPhongShading.shade(input, &self.scene.lights, image_light)
+                    }
+                    LightMode::Flat => FlatShading.shade(input, &self.scene.lights, image_light),
+                    LightMode::Toon => toon.shade(input, &self.scene.lights, image_light),
                 };
-                let pixel = B::create_pixel(final_color); //FUUUUUUUUUUUUUUU
-                self.back_buffer
-                    .borrow_mut()
-                    .set_pixel((x, y), &gbuffer.depth[idx], pixel)
+                let emissive = mat.and_then(|m| m.emissive).unwrap_or(Color::BLACK);
+                hdr[idx] = fog.apply(final_color + emissive, eye, world_pos); //FUUUUUUUUUUUUUUU
             }
         }
+        true
+    }
+
+    /// Screen space effects that run on the linear HDR image
+    fn post_process(&mut self) {
+        let (settings, exposure) = {
+            let states = &self.states;
+            (states.bloom, states.exposure)
+        };
+        self.bloom.apply(
+            &mut self.hdr,
+            self.width,
+            self.height,
+            exposure,
+            &settings,
+        );
     }
 
-    // TODO: This
-    fn draw_light_markers(&self) {
-        if !self.states.borrow().draw_light_marker {
+    /// Tone maps the HDR buffer and writes it to the back buffer, along with the toon outlines
+    fn resolve(&mut self) {
+        let (exposure, tone_mapping, outline_settings) = {
+            let states = &self.states;
+            let outline = (states.light_mode == LightMode::Toon && states.outline.enabled)
+                .then_some(states.outline);
+            (states.exposure, states.tone_mapping, outline)
+        };
+        let outline = &mut self.outline;
+        if let Some(settings) = &outline_settings {
+            outline.detect(
+                &self.gbuffer,
+                self.width,
+                self.height,
+                &self.scene.camera.projection_matrix(),
+                settings,
+            );
+        }
+        let edges = outline_settings.map(|settings| (outline.edges(), settings.color));
+        let has_background = !self.scene.environment.sky().is_none()
+            || self.scene.environment.fog().is_enabled();
+        let gbuffer = &self.gbuffer;
+        let hdr = &self.hdr;
+        let buffer = &mut self.back_buffer;
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let idx = y * self.width + x;
+                if let Some((Some(depth), color)) = edges.map(|(edges, color)| (edges[idx], color)) {
+                    buffer.set_pixel((x, y), &depth, B::create_pixel(color));
+                    continue;
+                }
+                let color = hdr[idx];
+                let depth = if gbuffer.depth[idx] != f32::INFINITY {
+                    gbuffer.depth[idx]
+                } else if has_background || color.luminance() > GLOW_CUTOFF {
+                    // Sky/fog, or bloom bleeding into otherwise empty pixels
+                    SKY_DEPTH
+                } else {
+                    continue;
+                };
+                let pixel = B::create_pixel(tone_mapping.apply(color, exposure));
+                buffer.set_pixel((x, y), &depth, pixel);
+            }
+        }
+    }
+
+    /// Draws the edges of hidden-line and solid + wire entities over the finished image.
+    /// Edges are depth tested against the GBuffer, so anything behind a surface (including the
+    /// invisible ones of hidden-line entities) stays hidden.
+    fn overlay_pass(&mut self) {
+        let color = self.states.wire_color;
+        let gbuffer = &self.gbuffer;
+        let buffer = &mut self.back_buffer;
+        for fragment in self.fragments.iter() {
+            if fragment.kind != FragmentKind::Overlay {
+                continue;
+            }
+            let x = fragment.screen_pos.x as usize;
+            let y = fragment.screen_pos.y as usize;
+            if x >= self.width || y >= self.height {
+                continue;
+            }
+            // Depth is non linear, 1 - depth scales with the inverse of the view distance
+            let depth = fragment.depth - (1.0 - fragment.depth) * OVERLAY_DEPTH_OFFSET;
+            if depth <= gbuffer.depth[y * self.width + x] {
+                buffer.set_pixel((x, y), &depth, B::create_pixel(color));
+            }
+        }
+    }
+
+    /// Writes a single GBuffer channel straight to the back buffer, see `DebugView`
+    fn debug_view_pass(&mut self, view: DebugView) {
+        let gbuffer = &self.gbuffer;
+        let depth_range = DepthRange::new(gbuffer, &self.scene.camera.projection_matrix());
+        let buffer = &mut self.back_buffer;
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let idx = y * self.width + x;
+                if let Some(color) = view.color(gbuffer, idx, &depth_range) {
+                    buffer.set_pixel((x, y), &gbuffer.depth[idx], B::create_pixel(color));
+                }
+            }
+        }
+    }
+
+    /// Fills every pixel the GBuffer has no geometry for with the environment's sky
+    fn sky_pass(&mut self, inv_viewproj: &Mat4) {
+        let sky = self.scene.environment.sky();
+        if sky.is_none() {
             return;
         }
-        let view_proj = self.scene.camera.view_matrix() * self.scene.camera.projection_matrix();
-        let buff = self.back_buffer.borrow_mut();
+        let (exposure, tone_mapping) = {
+            let states = &self.states;
+            (states.exposure, states.tone_mapping)
+        };
+        let eye = self.scene.camera.position();
+        let gbuffer = &self.gbuffer;
+        let buffer = &mut self.back_buffer;
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let idx = y * self.width + x;
+                let depth = if gbuffer.depth[idx] == f32::INFINITY {
+                    SKY_DEPTH
+                } else if gbuffer.depth_only[idx] {
+                    // Hidden-line fill shows the sky, but still occludes at its own depth
+                    gbuffer.depth[idx]
+                } else {
+                    continue;
+                };
+                if let Some(color) =
+                    sky_color(sky, (x, y), (self.width, self.height), eye, inv_viewproj)
+                {
+                    let color = tone_mapping.apply(color, exposure);
+                    buffer.set_pixel((x, y), &depth, B::create_pixel(color));
+                }
+            }
+        }
+    }
 
-        for light in &self.scene.lights {
+    /// Pushes a marker for every light onto the debug draw list: a sphere for point lights, an
+    /// arrow towards the scene for directional lights and an arrow with its cone for spot lights.
+    /// In light edit mode the selected light's label shows what can be edited.
+    fn draw_light_markers(&mut self) {
+        let states = &self.states;
+        if !states.draw_light_marker && !states.edit_light {
+            return;
+        }
+        let debug = &mut self.debug_draw;
+
+        for (i, light) in self.scene.lights.iter().enumerate() {
+            let color = light.color;
+            let selected = states.edit_light && i == states.current_light;
+            let label = |kind: &str| {
+                if selected {
+                    format!("> {kind} {i}\nintensity {:.2}", light.intensity)
+                } else {
+                    format!("{kind} {i}")
+                }
+            };
             match &light.light_type {
-                LightType::Point { position, .. } => {}
-                LightType::Directional(direction) => {}
+                LightType::Point { position, .. } => {
+                    debug.sphere(*position, LIGHT_MARKER_RADIUS, color);
+                    debug.text(*position, label("point"), color);
+                }
+                LightType::Directional(direction) => {
+                    // No position, so the arrow points at the origin from the side the light
+                    // comes from
+                    let dir = direction.normalize_or_zero();
+                    debug.arrow(-dir * 3.0, -dir * 2.0, color);
+                    debug.text(-dir * 3.0, label("directional"), color);
+                }
                 LightType::Spot {
-                   position,
+                    position,
                     direction,
+                    outer_cutoff,
                     ..
-                } => {}
+                } => {
+                    let dir = direction.normalize_or_zero();
+                    let tip = *position + dir;
+                    debug.sphere(*position, LIGHT_MARKER_RADIUS, color);
+                    debug.arrow(*position, tip, color);
+                    // Where the outer cone crosses one unit along the axis
+                    let angle = outer_cutoff.clamp(-1.0, 1.0).acos();
+                    debug.circle(tip, dir, angle.tan().min(10.0), color);
+                    let mut text = label("spot");
+                    if selected {
+                        text += &format!("\ncone {:.0} deg", angle.to_degrees());
+                    }
+                    debug.text(*position, text, color);
+                }
+            }
+        }
+    }
+
+    /// Frame stats, the selected entity and the active modes in the top left corner, key hints
+    /// in the bottom left
+    fn hud_pass(&mut self) {
+        let states = &self.states;
+        if !states.draw_hud {
+            return;
+        }
+        let metrics = &self.metrics;
+        let mut lines = vec![
+            format!(
+                "{:.1} fps  {:.1} ms (min {:.1} / max {:.1})",
+                metrics.current_fps,
+                metrics.frame_time.as_secs_f32() * 1000.0,
+                metrics.min_frame_ms(),
+                metrics.max_frame_ms()
+            ),
+            format!("{} tris  {} fragments", metrics.triangles, metrics.fragments),
+        ];
+        let profile = &self.profiler;
+        let stage_ms = |stage: Stage| profile.last().stage(stage).as_secs_f32() * 1000.0;
+        lines.push(format!(
+            "geo {:.1}  raster {:.1}  frag {:.1}  light {:.1}  post {:.1}  present {:.1} ms",
+            stage_ms(Stage::Geometry),
+            stage_ms(Stage::Rasterize),
+            stage_ms(Stage::Fragments),
+            stage_ms(Stage::Lighting),
+            stage_ms(Stage::PostProcess),
+            stage_ms(Stage::Present)
+        ));
+        let present_bytes = profile.last().present_bytes;
+        if present_bytes > 0 {
+            lines.push(format!("{:.1} KiB written", present_bytes as f32 / 1024.0));
+        }
+        if let Some(entity) = self.scene.entities.get(states.current_obj) {
+            let moving = if states.move_obj { " (moving)" } else { "" };
+            lines.push(format!("entity {}: {}{moving}", states.current_obj, entity.name));
+            lines.push(format!(
+                "{}  {:?}  {:?}",
+                entity.render_mode(),
+                states.light_mode,
+                states.tone_mapping
+            ));
+        }
+        if states.debug_view != DebugView::Lit {
+            lines.push(format!("view {:?}", states.debug_view));
+        }
+        if states.edit_light {
+            lines.push(format!("editing light {}", states.current_light));
+        }
+
+        let hints = self.key_hints();
+        let buffer = &mut self.back_buffer;
+        let (_, char_height) = buffer.char_size();
+        let hints_top = self
+            .height
+            .saturating_sub(hints.lines().count() * char_height);
+        for (pos, text) in [((1, 0), lines.join("\n")), ((1, hints_top), hints.into())] {
+            // A drop shadow keeps the text readable over bright pixels, terminal cells replace
+            // whatever was under them anyway
+            if char_height > 1 {
+                buffer.draw_text((pos.0 + 1, pos.1 + 1), &text, Color::BLACK);
+            }
+            buffer.draw_text(pos, &text, Color::WHITE);
+        }
+    }
+
+    /// Key hints for the current input mode
+    fn key_hints(&self) -> &'static str {
+        if self.states.edit_light {
+            HUD_LIGHT_KEY_HINTS
+        } else {
+            HUD_KEY_HINTS
+        }
+    }
+
+    /// The last few log messages, colored by level, just above the HUD's key hints
+    fn log_panel_pass(&mut self) {
+        let hints = self.key_hints();
+        let states = &self.states;
+        if !states.draw_log {
+            return;
+        }
+        let buffer = &mut self.back_buffer;
+        let (_, char_height) = buffer.char_size();
+        let rows = self.height / char_height;
+        let hint_rows = if states.draw_hud {
+            hints.lines().count()
+        } else {
+            0
+        };
+        let recent = crate::logging::recent(LOG_PANEL_LINES.min(rows / 3));
+        let top = rows.saturating_sub(hint_rows + recent.len()) * char_height;
+        for (row, line) in recent.iter().enumerate() {
+            let color = match line.level {
+                log::Level::Error => Color::RED,
+                log::Level::Warn => Color::YELLOW,
+                log::Level::Info => Color::WHITE,
+                log::Level::Debug | log::Level::Trace => Color::LIGHT_GRAY,
+            };
+            // Multi line messages only get their first line, the panel has one row per message
+            let text = line.message.lines().next().unwrap_or_default();
+            let pos = (1, top + row * char_height);
+            if char_height > 1 {
+                buffer.draw_text((pos.0 + 1, pos.1 + 1), text, Color::BLACK);
+            }
+            buffer.draw_text(pos, text, color);
+        }
+    }
+
+    /// The light being edited in light edit mode, None when the scene has no lights
+    pub fn selected_light(&mut self) -> Option<&mut Light> {
+        let current = self.states.current_light;
+        self.scene.lights.get_mut(current)
+    }
+
+    /// Moves the light edit selection one light forward or back, wrapping around
+    pub fn cycle_light(&mut self, forward: bool) -> usize {
+        let count = self.scene.lights.len().max(1);
+        let states = &mut self.states;
+        states.current_light = if forward {
+            (states.current_light + 1) % count
+        } else {
+            (states.current_light + count - 1) % count
+        };
+        states.current_light
+    }
+
+    /// Moves the selected light, or tilts it when it's directional
+    pub fn nudge_light(&mut self, offset: Vec3) {
+        if let Some(light) = self.selected_light() {
+            light.nudge(offset);
+        }
+    }
+
+    /// Gives the selected light the next color of `LIGHT_COLORS`
+    pub fn cycle_light_color(&mut self) {
+        if let Some(light) = self.selected_light() {
+            let next = LIGHT_COLORS
+                .iter()
+                .position(|color| *color == light.color)
+                .map_or(0, |i| (i + 1) % LIGHT_COLORS.len());
+            light.color = LIGHT_COLORS[next];
+        }
+    }
+
+    /// Pushes entity bounds and the frozen camera frustum onto the debug draw list
+    fn draw_scene_debug(&mut self) {
+        let states = &self.states;
+        let debug = &mut self.debug_draw;
+
+        if states.draw_bounds {
+            for (i, entity) in self.scene.entities.iter().enumerate() {
+                let Some((min, max)) = entity.world_bounds() else {
+                    continue;
+                };
+                let color = if i == states.current_obj {
+                    Color::YELLOW
+                } else {
+                    Color::GRAY
+                };
+                debug.aabb(min, max, color);
+                debug.axes(entity.transform(), (max - min).length() * 0.25);
+                debug.text(max, entity.name.clone(), color);
             }
         }
+        if let Some(view_proj) = states.frozen_frustum {
+            debug.frustum(&view_proj, Color::CYAN);
+        }
+    }
+
+    /// Draws and clears the debug draw list
+    fn debug_draw_pass(&mut self) {
+        let debug = &mut self.debug_draw;
+        if debug.is_empty() {
+            return;
+        }
+        let view_proj = self.scene.camera.projection_matrix() * self.scene.camera.view_matrix();
+        debug.render(&mut self.back_buffer, &view_proj);
+        debug.clear();
+    }
+
+    /// Logs the camera matrices and those of the first entity
+    #[cfg(any(feature = "window", feature = "terminal"))]
+    fn log_matrices(&self) {
+        log::info!("Printing out Matrices:");
+        log::info!(
+            "{}",
+            format_mat4("Camera View Matrix", &self.scene.camera.view_matrix())
+        );
+        log::info!(
+            "{}",
+            format_mat4(
+                "Camera Projection Matrix",
+                &self.scene.camera.projection_matrix()
+            )
+        );
+        let Some(entity) = self.scene.entities.first() else {
+            return;
+        };
+        let model = Mat4::from(*entity.transform());
+        log::info!("{}", format_mat4("Model Matrix (first entity)", &model));
+        log::info!(
+            "{}",
+            format_mat4(
+                "MVP matrix of first entity",
+                &(self.scene.camera.projection_matrix() * self.scene.camera.view_matrix() * model)
+            )
+        );
     }
 
     // TODO: Move this to a separate file along witht the input handling for the terminal environment
+    #[cfg(feature = "window")]
 pub fn window_handle_input(&mut self, input: &minifb::Window, last_frame: std::time::Instant) {
         let delta = 0.1;
         let move_speed = 1.0;
@@ -414,26 +1083,21 @@ pub fn window_handle_input(&mut self, input: &minifb::Window, last_frame: std::t
         let rotate_amount = rotate_speed * delta;
 
         if input.get_mouse_down(MouseButton::Left) {
-            if !self.states.borrow().is_mouse_pan_enabled {
-                self.states.borrow_mut().is_mouse_pan_enabled = true;
-                self.states.borrow_mut().last_mouse_pos = input.get_mouse_pos(MouseMode::Clamp);
+            if !self.states.is_mouse_pan_enabled {
+                self.states.is_mouse_pan_enabled = true;
+                self.states.last_mouse_pos = input.get_mouse_pos(MouseMode::Clamp);
             }
-        } else if self.states.borrow().is_mouse_pan_enabled
+        } else if self.states.is_mouse_pan_enabled
             && !input.get_mouse_down(MouseButton::Right)
         {
-            self.states.borrow_mut().is_mouse_pan_enabled = false;
-            self.states.borrow_mut().last_mouse_pos = None;
+            self.states.is_mouse_pan_enabled = false;
+            self.states.last_mouse_pos = None;
         }
 
         // Mouse Look Rotation (if enabled)
-        if self.states.borrow().is_mouse_pan_enabled {
+        if self.states.is_mouse_pan_enabled {
             if let Some(current_mouse_pos) = input.get_mouse_pos(minifb::MouseMode::Clamp) {
-                if let Some(last_mouse_pos) = self
-                    .states
-                    .borrow_mut()
-                    .last_mouse_pos
-                    .replace(current_mouse_pos)
-                {
+                if let Some(last_mouse_pos) = self.states.last_mouse_pos.replace(current_mouse_pos) {
                     let current_mouse_pos = Vec2::new(current_mouse_pos.0, current_mouse_pos.1);
                     let last_mouse_pos = Vec2::new(last_mouse_pos.0, last_mouse_pos.1);
                     let mouse_delta = current_mouse_pos - last_mouse_pos;
@@ -449,27 +1113,22 @@ pub fn window_handle_input(&mut self, input: &minifb::Window, last_frame: std::t
 
         // Mouse Look Toggle (Right Mouse Button)
         if input.get_mouse_down(MouseButton::Right) {
-            if !self.states.borrow().is_mouse_look_enabled {
-                self.states.borrow_mut().is_mouse_look_enabled = true;
-                self.states.borrow_mut().last_mouse_pos =
+            if !self.states.is_mouse_look_enabled {
+                self.states.is_mouse_look_enabled = true;
+                self.states.last_mouse_pos =
                     input.get_mouse_pos(minifb::MouseMode::Clamp)
             }
-        } else if self.states.borrow().is_mouse_look_enabled
+        } else if self.states.is_mouse_look_enabled
             && !input.get_mouse_down(MouseButton::Right)
         {
-            self.states.borrow_mut().is_mouse_look_enabled = false;
-            self.states.borrow_mut().last_mouse_pos = None;
+            self.states.is_mouse_look_enabled = false;
+            self.states.last_mouse_pos = None;
         }
 
         // Mouse Look Rotation (if enabled)
-        if self.states.borrow().is_mouse_look_enabled {
+        if self.states.is_mouse_look_enabled {
             if let Some(current_mouse_pos) = input.get_mouse_pos(minifb::MouseMode::Clamp) {
-                if let Some(last_mouse_pos) = self
-                    .states
-                    .borrow_mut()
-                    .last_mouse_pos
-                    .replace(current_mouse_pos)
-                {
+                if let Some(last_mouse_pos) = self.states.last_mouse_pos.replace(current_mouse_pos) {
                     let current_mouse_pos = Vec2::new(current_mouse_pos.0, current_mouse_pos.1);
                     let last_mouse_pos = Vec2::new(last_mouse_pos.0, last_mouse_pos.1);
                     let mouse_delta = current_mouse_pos - last_mouse_pos;
@@ -489,36 +1148,58 @@ pub fn window_handle_input(&mut self, input: &minifb::Window, last_frame: std::t
         //}
 
         if input.is_key_pressed(minifb::Key::P, KeyRepeat::No) {
-            let current = self.states.borrow().draw_wireframe;
-            self.states.borrow_mut().draw_wireframe = !current;
-            println!("Draw wireframe: {}", !current);
+            let current = self.states.draw_wireframe;
+            self.states.draw_wireframe = !current;
+            log::info!("Draw wireframe: {}", !current);
         }
         if input.is_key_pressed(minifb::Key::J, KeyRepeat::No) {
-            let cur = self.states.borrow().move_obj;
-            self.states.borrow_mut().move_obj = !cur;
-            println!("Move obj: {}", !cur);
+            let cur = self.states.move_obj;
+            self.states.move_obj = !cur;
+            log::info!("Move obj: {}", !cur);
         }
-        if input.is_key_pressed(minifb::Key::LeftBracket, KeyRepeat::No) {
-            let mut current = self.states.borrow().current_obj;
+        if input.is_key_pressed(Key::Tab, KeyRepeat::No) {
+            let states = &mut self.states;
+            states.draw_hud = !states.draw_hud;
+            log::info!("HUD: {}", states.draw_hud);
+        }
+        if input.is_key_pressed(Key::Backquote, KeyRepeat::No) {
+            let states = &mut self.states;
+            states.draw_log = !states.draw_log;
+            log::info!("Log panel: {}", states.draw_log);
+        }
+        let edit_light = self.states.edit_light;
+        if input.is_key_pressed(Key::I, KeyRepeat::No) {
+            let states = &mut self.states;
+            states.edit_light = !states.edit_light;
+            log::info!("Light edit: {}", states.edit_light);
+        }
+        if edit_light && input.is_key_pressed(minifb::Key::LeftBracket, KeyRepeat::No) {
+            log::info!("Current light: {}", self.cycle_light(false));
+        }
+        if edit_light && input.is_key_pressed(minifb::Key::RightBracket, KeyRepeat::No) {
+            log::info!("Current light: {}", self.cycle_light(true));
+        }
+        if !edit_light && input.is_key_pressed(minifb::Key::LeftBracket, KeyRepeat::No) {
+            let mut current = self.states.current_obj;
             current = current.saturating_sub(1);
             if current > self.scene.entities.len() - 1 {
                 current = self.scene.entities.len() - 1;
             }
-            self.states.borrow_mut().current_obj = current;
-            println!("Current object: {}", current);
+            self.states.current_obj = current;
+            log::info!("Current object: {}", current);
         }
-        if input.is_key_pressed(minifb::Key::RightBracket, KeyRepeat::No) {
-            let mut current = self.states.borrow().current_obj;
+        if !edit_light && input.is_key_pressed(minifb::Key::RightBracket, KeyRepeat::No) {
+            let mut current = self.states.current_obj;
             current += 1;
             if current > self.scene.entities.len() - 1 {
                 current %= self.scene.entities.len();
             }
-            self.states.borrow_mut().current_obj = current;
-            println!("Current object: {}", current);
+            self.states.current_obj = current;
+            log::info!("Current object: {}", current);
         }
 
         if input.is_key_pressed(minifb::Key::U, KeyRepeat::No) {
-            if self.states.borrow().move_obj {
+            if self.states.move_obj {
                 return;
             } else {
                 self.scene.camera.reset();
@@ -526,86 +1207,143 @@ pub fn window_handle_input(&mut self, input: &minifb::Window, last_frame: std::t
         }
         if input.is_key_pressed(minifb::Key::Slash, KeyRepeat::No) {
             // Treat this as a question mark to print out debug info
-            println!("Printing out Matrices:");
-            println!(
-                "{}",
-                format_mat4("Camera View Matrix", &self.scene.camera.view_matrix())
-            );
-            println!(
-                "{}",
-                format_mat4(
-                    "Camera Projection Matrix",
-                    &self.scene.camera.projection_matrix()
-                )
-            );
-            println!(
-                "{}",
-                format_mat4(
-                    "Model Matrix (first entity)",
-                    &Mat4::from(*self.scene.entities[0].transform())
-                )
-            );
-            println!(
-                "{}",
-                format_mat4(
-                    "MVP matrix of first entity",
-                    &(self.scene.camera.projection_matrix()
-                        * self.scene.camera.view_matrix()
-                        * Mat4::from(*self.scene.entities[0].transform()))
-                )
-            );
+            self.log_matrices();
         }
         if input.is_key_pressed(minifb::Key::E, KeyRepeat::No) {
-            println!("Camera Debug Info:");
-            println!("Camera position: {:?}", self.scene.camera.position());
-            println!("Camera target: {:?}\n", self.scene.camera.target());
+            log::info!("Camera Debug Info:");
+            log::info!("Camera position: {:?}", self.scene.camera.position());
+            log::info!("Camera target: {:?}", self.scene.camera.target());
 
-            println!("{:?}", self.scene.camera.orientation());
+            log::info!("{:?}", self.scene.camera.orientation());
 
-            println!("Camera forward: {:?}", self.scene.camera.forward());
-            println!("Camera right: {:?}\n", self.scene.camera.right());
-            println!("Camera up: {:?}\n", self.scene.camera.up());
+            log::info!("Camera forward: {:?}", self.scene.camera.forward());
+            log::info!("Camera right: {:?}", self.scene.camera.right());
+            log::info!("Camera up: {:?}", self.scene.camera.up());
 
-            println!(
+            log::info!(
                 "{}",
                 format_mat4("Camera View Matrix", &self.scene.camera.view_matrix())
             );
-            println!(
+            log::info!(
                 "{}",
                 format_mat4(
                     "Camera Projection Matrix",
                     &self.scene.camera.projection_matrix()
                 )
             );
-            println!("\n\n");
         }
         if input.is_key_pressed(Key::R, KeyRepeat::No) {
-            println!("Updating Render Mode of selected object");
             // We'll do it cyclicly for now
-            let obj = &self.scene.entities[self.states.borrow().current_obj];
-
-            print!("Selected Object: {:?} -> ", obj.name);
+            let obj = &mut self.scene.entities[self.states.current_obj];
 
-            if let Ok(mut mode) = obj.render_mode().lock() {
-                // Cycle through the render modes
-                *mode = match *mode {
-                    RenderMode::Solid => RenderMode::Wireframe,
-                    RenderMode::Wireframe => RenderMode::Solid,
-                    //RenderMode::FixedPoint => RenderMode::Solid,
-                    _ => RenderMode::Solid,
-                };
-                println!("New render mode: {:?}", *mode);
-            }
+            // Cycle through the render modes
+            obj.set_render_mode(obj.render_mode().next());
+            log::info!("Render mode of {:?}: {:?}", obj.name, obj.render_mode());
         }
 
         if input.is_key_pressed(Key::NumPad0, KeyRepeat::No) {
-            let obj = &self.scene.entities[self.states.borrow().current_obj];
-            println!("Material Info of selected object {:?}", obj.name);
+            let obj = &self.scene.entities[self.states.current_obj];
+            log::info!("Material Info of selected object {:?}", obj.name);
             for (i, mat) in obj.mesh.materials.iter().enumerate() {
-                println!("Material {}: {}", i, mat);
+                log::info!("Material {}: {}", i, mat);
             }
         }
 
+        if edit_light && input.is_key_pressed(Key::Minus, KeyRepeat::Yes)
+            && let Some(light) = self.selected_light()
+        {
+            light.intensity /= LIGHT_INTENSITY_STEP;
+            log::info!("Light intensity: {:.2}", light.intensity);
+        }
+        if edit_light && input.is_key_pressed(Key::Equal, KeyRepeat::Yes)
+            && let Some(light) = self.selected_light()
+        {
+            light.intensity *= LIGHT_INTENSITY_STEP;
+            log::info!("Light intensity: {:.2}", light.intensity);
+        }
+        if edit_light && input.is_key_pressed(Key::H, KeyRepeat::No) {
+            self.cycle_light_color();
+        }
+        if edit_light && input.is_key_pressed(Key::Comma, KeyRepeat::Yes)
+            && let Some(light) = self.selected_light()
+        {
+            light.widen_cone(-LIGHT_CONE_STEP);
+        }
+        if edit_light && input.is_key_pressed(Key::Period, KeyRepeat::Yes)
+            && let Some(light) = self.selected_light()
+        {
+            light.widen_cone(LIGHT_CONE_STEP);
+        }
+        if edit_light && input.is_key_pressed(Key::Semicolon, KeyRepeat::Yes)
+            && let Some(light) = self.selected_light()
+        {
+            light.scale_attenuation(LIGHT_ATTENUATION_STEP);
+        }
+        if edit_light && input.is_key_pressed(Key::Apostrophe, KeyRepeat::Yes)
+            && let Some(light) = self.selected_light()
+        {
+            light.scale_attenuation(1.0 / LIGHT_ATTENUATION_STEP);
+        }
+        if !edit_light && input.is_key_pressed(Key::Minus, KeyRepeat::Yes) {
+            let states = &mut self.states;
+            states.exposure = (states.exposure / EXPOSURE_STEP).max(MIN_EXPOSURE);
+            log::info!("Exposure: {:.2}", states.exposure);
+        }
+        if !edit_light && input.is_key_pressed(Key::Equal, KeyRepeat::Yes) {
+            let states = &mut self.states;
+            states.exposure = (states.exposure * EXPOSURE_STEP).min(MAX_EXPOSURE);
+            log::info!("Exposure: {:.2}", states.exposure);
+        }
+        if input.is_key_pressed(Key::T, KeyRepeat::No) {
+            let states = &mut self.states;
+            states.tone_mapping = states.tone_mapping.next();
+            log::info!("Tone mapping: {:?}", states.tone_mapping);
+        }
+        if input.is_key_pressed(Key::K, KeyRepeat::No) {
+            let states = &mut self.states;
+            states.ssao.enabled = !states.ssao.enabled;
+            log::info!("SSAO: {}", states.ssao.enabled);
+        }
+        if input.is_key_pressed(Key::L, KeyRepeat::No) {
+            let states = &mut self.states;
+            states.light_mode = states.light_mode.next();
+            log::info!("Light mode: {:?}", states.light_mode);
+        }
+        if input.is_key_pressed(Key::F, KeyRepeat::No) {
+            let fog = self.scene.environment.fog_mut();
+            fog.mode = fog.mode.next();
+            log::info!("Fog: {:?}", fog.mode);
+        }
+        if input.is_key_pressed(Key::V, KeyRepeat::No) {
+            let states = &mut self.states;
+            states.debug_view = states.debug_view.next();
+            log::info!("Debug view: {:?}", states.debug_view);
+        }
+        if input.is_key_pressed(Key::G, KeyRepeat::No) {
+            let states = &mut self.states;
+            states.bloom.enabled = !states.bloom.enabled;
+            log::info!("Bloom: {}", states.bloom.enabled);
+        }
+        if input.is_key_pressed(Key::M, KeyRepeat::No) {
+            let states = &mut self.states;
+            states.draw_light_marker = !states.draw_light_marker;
+            log::info!("Light markers: {}", states.draw_light_marker);
+        }
+        if input.is_key_pressed(Key::B, KeyRepeat::No) {
+            let states = &mut self.states;
+            states.draw_bounds = !states.draw_bounds;
+            log::info!("Bounds: {}", states.draw_bounds);
+        }
+        if input.is_key_pressed(Key::C, KeyRepeat::No) {
+            let view_proj = self.scene.camera.projection_matrix() * self.scene.camera.view_matrix();
+            let states = &mut self.states;
+            states.frozen_frustum = match states.frozen_frustum {
+                Some(_) => None,
+                None => Some(view_proj),
+            };
+            log::info!("Frozen frustum: {}", states.frozen_frustum.is_some());
+        }
+
         // FIX: Update input handling to be less "fast" like if I try and just tap a button it
         // seems to register that I hit it like 4 times (due to fast framerate) need to slow down
         // polling I presume, or handle it using the key_pressed instead of some other thing
@@ -613,9 +1351,18 @@ pub fn window_handle_input(&mut self, input: &minifb::Window, last_frame: std::t
         if let Some(keys) = Some(input.get_keys()) {
             for key in keys.iter() {
                 match key {
+                    // Light edit mode moves the selected light instead
+                    minifb::Key::W if edit_light => self.nudge_light(Vec3::Z * move_amount),
+                    minifb::Key::S if edit_light => self.nudge_light(-Vec3::Z * move_amount),
+                    minifb::Key::A if edit_light => self.nudge_light(-Vec3::X * move_amount),
+                    minifb::Key::D if edit_light => self.nudge_light(Vec3::X * move_amount),
+                    minifb::Key::Space if edit_light => self.nudge_light(Vec3::Y * move_amount),
+                    minifb::Key::LeftShift if edit_light => {
+                        self.nudge_light(-Vec3::Y * move_amount)
+                    }
                     minifb::Key::W => {
-                        let move_obj = self.states.borrow().move_obj;
-                        let current_obj = self.states.borrow().current_obj;
+                        let move_obj = self.states.move_obj;
+                        let current_obj = self.states.current_obj;
                         if move_obj {
                             let ent = &self.scene.entities[current_obj];
                             let mut t = *ent.transform();
@@ -626,8 +1373,8 @@ pub fn window_handle_input(&mut self, input: &minifb::Window, last_frame: std::t
                         }
                     }
                     minifb::Key::S => {
-                        let move_obj = self.states.borrow().move_obj;
-                        let current_obj = self.states.borrow().current_obj;
+                        let move_obj = self.states.move_obj;
+                        let current_obj = self.states.current_obj;
                         if move_obj {
                             let ent = &self.scene.entities[current_obj];
                             let mut t = *ent.transform();
@@ -638,8 +1385,8 @@ pub fn window_handle_input(&mut self, input: &minifb::Window, last_frame: std::t
                         }
                     }
                     minifb::Key::A => {
-                        let move_obj = self.states.borrow().move_obj;
-                        let current_obj = self.states.borrow().current_obj;
+                        let move_obj = self.states.move_obj;
+                        let current_obj = self.states.current_obj;
                         if move_obj {
                             let ent = &self.scene.entities[current_obj];
                             let mut t = *ent.transform();
@@ -650,8 +1397,8 @@ pub fn window_handle_input(&mut self, input: &minifb::Window, last_frame: std::t
                         }
                     }
                     minifb::Key::D => {
-                        let move_obj = self.states.borrow().move_obj;
-                        let current_obj = self.states.borrow().current_obj;
+                        let move_obj = self.states.move_obj;
+                        let current_obj = self.states.current_obj;
                         if move_obj {
                             let ent = &self.scene.entities[current_obj];
                             let mut t = *ent.transform();
@@ -666,11 +1413,13 @@ pub fn window_handle_input(&mut self, input: &minifb::Window, last_frame: std::t
                         self.scene.camera.orbit(orbit_amount);
                     }
                     minifb::Key::Key3 => {
-                       self.scene.lights[0].orbit(Vec3::ZERO, 5.0, 0.01, 0.0001);
+                        if let Some(light) = self.selected_light() {
+                            light.orbit(Vec3::ZERO, 5.0, 0.01, 0.0001);
+                        }
                     }
                     minifb::Key::Space => {
-                        let move_obj = self.states.borrow().move_obj;
-                        let current_obj = self.states.borrow().current_obj;
+                        let move_obj = self.states.move_obj;
+                        let current_obj = self.states.current_obj;
                         if move_obj {
                             return;
                         } else {
@@ -678,8 +1427,8 @@ pub fn window_handle_input(&mut self, input: &minifb::Window, last_frame: std::t
                         }
                     }
                     minifb::Key::LeftShift => {
-                        let move_obj = self.states.borrow().move_obj;
-                        let current_obj = self.states.borrow().current_obj;
+                        let move_obj = self.states.move_obj;
+                        let current_obj = self.states.current_obj;
                         if move_obj {
                             let ent = &self.scene.entities[current_obj];
                             let mut t = *ent.transform();
@@ -691,8 +1440,10 @@ pub fn window_handle_input(&mut self, input: &minifb::Window, last_frame: std::t
                     }
                     minifb::Key::Up => self.scene.camera.rotate(rotate_amount, 0.0),
                     minifb::Key::Down => self.scene.camera.rotate(-rotate_amount, 0.0),
+                    minifb::Key::Left => self.scene.camera.rotate(0.0, rotate_amount),
+                    minifb::Key::Right => self.scene.camera.rotate(0.0, -rotate_amount),
                     minifb::Key::Key0 => {
-                        let current_obj = self.states.borrow().current_obj;
+                        let current_obj = self.states.current_obj;
                         let ent = &self.scene.entities[current_obj];
                         let t = *ent.transform();
                         for entity in &mut self.scene.entities {
@@ -718,6 +1469,7 @@ pub fn window_handle_input(&mut self, input: &minifb::Window, last_frame: std::t
 
         // getting rid of the big ass match statement? maybe?
     }
+    #[cfg(feature = "terminal")]
 pub fn handle_crossterm_input(&mut self, event: Event, _last_frame: Instant) -> bool {
         // Constants (adjust as needed)
         let delta = 0.1;
@@ -735,116 +1487,202 @@ pub fn handle_crossterm_input(&mut self, event: Event, _last_frame: Instant) ->
             event::Event::Key(KeyEvent {
                 code, modifiers, ..
             }) => {
+                let edit_light = self.states.edit_light;
                 match code {
+                    // Toggle the HUD
+                    KeyCode::Tab => {
+                        let states = &mut self.states;
+                        states.draw_hud = !states.draw_hud;
+                    }
+                    // Toggle the log panel
+                    KeyCode::Char('`') => {
+                        let states = &mut self.states;
+                        states.draw_log = !states.draw_log;
+                    }
+                    // Toggle light edit mode
+                    KeyCode::Char('i') => {
+                        let states = &mut self.states;
+                        states.edit_light = !states.edit_light;
+                    }
+                    // Light edit mode: select, move, and change the selected light
+                    KeyCode::Char('[') if edit_light => {
+                        self.cycle_light(false);
+                    }
+                    KeyCode::Char(']') if edit_light => {
+                        self.cycle_light(true);
+                    }
+                    KeyCode::Char('w') if edit_light => self.nudge_light(Vec3::Z * move_amount),
+                    KeyCode::Char('s') if edit_light => self.nudge_light(-Vec3::Z * move_amount),
+                    KeyCode::Char('a') if edit_light => self.nudge_light(-Vec3::X * move_amount),
+                    KeyCode::Char('d') if edit_light => self.nudge_light(Vec3::X * move_amount),
+                    KeyCode::Char(' ') if edit_light => {
+                        if modifiers.contains(event::KeyModifiers::SHIFT) {
+                            self.nudge_light(-Vec3::Y * move_amount);
+                        } else {
+                            self.nudge_light(Vec3::Y * move_amount);
+                        }
+                    }
+                    KeyCode::Char('-') if edit_light => {
+                        if let Some(light) = self.selected_light() {
+                            light.intensity /= LIGHT_INTENSITY_STEP;
+                        }
+                    }
+                    KeyCode::Char('=') | KeyCode::Char('+') if edit_light => {
+                        if let Some(light) = self.selected_light() {
+                            light.intensity *= LIGHT_INTENSITY_STEP;
+                        }
+                    }
+                    KeyCode::Char('h') if edit_light => self.cycle_light_color(),
+                    KeyCode::Char(',') if edit_light => {
+                        if let Some(light) = self.selected_light() {
+                            light.widen_cone(-LIGHT_CONE_STEP);
+                        }
+                    }
+                    KeyCode::Char('.') if edit_light => {
+                        if let Some(light) = self.selected_light() {
+                            light.widen_cone(LIGHT_CONE_STEP);
+                        }
+                    }
+                    KeyCode::Char(';') if edit_light => {
+                        if let Some(light) = self.selected_light() {
+                            light.scale_attenuation(LIGHT_ATTENUATION_STEP);
+                        }
+                    }
+                    KeyCode::Char('\'') if edit_light => {
+                        if let Some(light) = self.selected_light() {
+                            light.scale_attenuation(1.0 / LIGHT_ATTENUATION_STEP);
+                        }
+                    }
                     // Toggle wireframe
                     KeyCode::Char('p') => {
-                        let current = self.states.borrow().draw_wireframe;
-                        self.states.borrow_mut().draw_wireframe = !current;
-                        // println!("Draw wireframe: {}", !current);
+                        let current = self.states.draw_wireframe;
+                        self.states.draw_wireframe = !current;
+                        // log::info!("Draw wireframe: {}", !current);
                     }
                     // Toggle move_obj
                     KeyCode::Char('j') => {
-                        let cur = self.states.borrow().move_obj;
-                        self.states.borrow_mut().move_obj = !cur;
-                        // println!("Move obj: {}", !cur);
+                        let cur = self.states.move_obj;
+                        self.states.move_obj = !cur;
+                        // log::info!("Move obj: {}", !cur);
                     }
                     // Decrement current object index
                     KeyCode::Char('[') => {
-                        let mut current = self.states.borrow().current_obj;
+                        let mut current = self.states.current_obj;
                         current = current.saturating_sub(1);
                         if current > self.scene.entities.len() - 1 {
                             current = self.scene.entities.len() - 1;
                         }
-                        self.states.borrow_mut().current_obj = current;
-                        //println!("Current object: {}", current);
+                        self.states.current_obj = current;
+                        //log::info!("Current object: {}", current);
                     }
                     // Increment current object index
                     KeyCode::Char(']') => {
-                        let mut current = self.states.borrow().current_obj;
+                        let mut current = self.states.current_obj;
                         current += 1;
                         if current > self.scene.entities.len() - 1 {
                             current %= self.scene.entities.len();
                         }
-                        self.states.borrow_mut().current_obj = current;
-                        //println!("Current object: {}", current);
+                        self.states.current_obj = current;
+                        //log::info!("Current object: {}", current);
                     }
                     // Reset camera (if not moving an object)
                     KeyCode::Char('u') => {
-                        if self.states.borrow().move_obj {
+                        if self.states.move_obj {
                             // do nothing
                         } else {
                             self.scene.camera.reset();
                         }
                     }
                     // Print matrices debug info
-                    KeyCode::Char('/') => {
-                        //println!("Printing out Matrices:");
-                        //println!(
-                        //    "{}",
-                        //    format_mat4("Camera View Matrix", &self.scene.camera.view_matrix())
-                        //);
-                        //println!(
-                        //    "{}",
-                        //    format_mat4(
-                        //        "Camera Projection Matrix",
-                        //        &self.scene.camera.projection_matrix()
-                        //    )
-                        //);
-                        //println!(
-                        //    "{}",
-                        //    format_mat4(
-                        //        "Model Matrix (first entity)",
-                        //        &Mat4::from(*self.scene.entities[0].transform())
-                        //    )
-                        //);
-                        //println!(
-                        //    "{}",
-                        //    format_mat4(
-                        //        "MVP matrix of first entity",
-                        //        &(self.scene.camera.projection_matrix()
-                        //            * self.scene.camera.view_matrix()
-                        //            * Mat4::from(*self.scene.entities[0].transform()))
-                        //    )
-                        //);
-                    }
+                    KeyCode::Char('/') => self.log_matrices(),
                     // Print camera debug info
                     KeyCode::Char('e') => {
-                        println!("Camera Debug Info:");
-                        println!("Camera position: {:?}", self.scene.camera.position());
-                        println!("Camera target: {:?}\n", self.scene.camera.target());
+                        log::info!("Camera Debug Info:");
+                        log::info!("Camera position: {:?}", self.scene.camera.position());
+                        log::info!("Camera target: {:?}", self.scene.camera.target());
 
-                        println!("{:?}", self.scene.camera.orientation());
+                        log::info!("{:?}", self.scene.camera.orientation());
 
-                        println!("Camera forward: {:?}", self.scene.camera.forward());
-                        println!("Camera right: {:?}\n", self.scene.camera.right());
-                        println!("Camera up: {:?}\n", self.scene.camera.up());
+                        log::info!("Camera forward: {:?}", self.scene.camera.forward());
+                        log::info!("Camera right: {:?}", self.scene.camera.right());
+                        log::info!("Camera up: {:?}", self.scene.camera.up());
 
-                        println!(
+                        log::info!(
                             "{}",
                             format_mat4("Camera View Matrix", &self.scene.camera.view_matrix())
                         );
-                        println!(
+                        log::info!(
                             "{}",
                             format_mat4(
                                 "Camera Projection Matrix",
                                 &self.scene.camera.projection_matrix()
                             )
                         );
-                        println!("\n\n");
                     }
                     // Cycle render mode on selected object
                     KeyCode::Char('r') => {
-                        println!("Updating Render Mode of selected object");
-                        let obj = &self.scene.entities[self.states.borrow().current_obj];
-                        print!("Selected Object: {:?} -> ", obj.name);
-                        if let Ok(mut mode) = obj.render_mode().lock() {
-                            *mode = match *mode {
-                                RenderMode::Solid => RenderMode::Wireframe,
-                                RenderMode::Wireframe => RenderMode::Solid,
-                                _ => RenderMode::Solid,
-                            };
-                            println!("New render mode: {:?}", *mode);
-                        }
+                        let obj = &mut self.scene.entities[self.states.current_obj];
+                        obj.set_render_mode(obj.render_mode().next());
+                        log::info!("Render mode of {:?}: {:?}", obj.name, obj.render_mode());
+                    }
+                    // Exposure down/up
+                    KeyCode::Char('-') => {
+                        let states = &mut self.states;
+                        states.exposure = (states.exposure / EXPOSURE_STEP).max(MIN_EXPOSURE);
+                    }
+                    KeyCode::Char('=') | KeyCode::Char('+') => {
+                        let states = &mut self.states;
+                        states.exposure = (states.exposure * EXPOSURE_STEP).min(MAX_EXPOSURE);
+                    }
+                    // Cycle tone mapping operator
+                    KeyCode::Char('t') => {
+                        let states = &mut self.states;
+                        states.tone_mapping = states.tone_mapping.next();
+                    }
+                    // Toggle ambient occlusion
+                    KeyCode::Char('k') => {
+                        let states = &mut self.states;
+                        states.ssao.enabled = !states.ssao.enabled;
+                    }
+                    // Cycle lighting model
+                    KeyCode::Char('l') => {
+                        let states = &mut self.states;
+                        states.light_mode = states.light_mode.next();
+                    }
+                    // Cycle fog mode
+                    KeyCode::Char('f') => {
+                        let fog = self.scene.environment.fog_mut();
+                        fog.mode = fog.mode.next();
+                    }
+                    // Cycle GBuffer debug view
+                    KeyCode::Char('v') => {
+                        let states = &mut self.states;
+                        states.debug_view = states.debug_view.next();
+                    }
+                    // Toggle bloom
+                    KeyCode::Char('g') => {
+                        let states = &mut self.states;
+                        states.bloom.enabled = !states.bloom.enabled;
+                    }
+                    // Toggle light markers
+                    KeyCode::Char('m') => {
+                        let states = &mut self.states;
+                        states.draw_light_marker = !states.draw_light_marker;
+                    }
+                    // Toggle entity bounds
+                    KeyCode::Char('b') => {
+                        let states = &mut self.states;
+                        states.draw_bounds = !states.draw_bounds;
+                    }
+                    // Freeze the camera frustum where it is, or release it
+                    KeyCode::Char('c') => {
+                        let view_proj =
+                            self.scene.camera.projection_matrix() * self.scene.camera.view_matrix();
+                        let states = &mut self.states;
+                        states.frozen_frustum = match states.frozen_frustum {
+                            Some(_) => None,
+                            None => Some(view_proj),
+                        };
                     }
                     // Rotate all entities (example for key '0')
                     KeyCode::Char('0') => {
@@ -866,8 +1704,8 @@ pub fn handle_crossterm_input(&mut self, event: Event, _last_frame: Instant) ->
                     }
                     // Movement and object translation keys
                     KeyCode::Char('w') => {
-                        let move_obj = self.states.borrow().move_obj;
-                        let current_obj = self.states.borrow().current_obj;
+                        let move_obj = self.states.move_obj;
+                        let current_obj = self.states.current_obj;
                         if move_obj {
                             let ent = &self.scene.entities[current_obj];
                             let mut t = *ent.transform();
@@ -878,8 +1716,8 @@ pub fn handle_crossterm_input(&mut self, event: Event, _last_frame: Instant) ->
                         }
                     }
                     KeyCode::Char('s') => {
-                        let move_obj = self.states.borrow().move_obj;
-                        let current_obj = self.states.borrow().current_obj;
+                        let move_obj = self.states.move_obj;
+                        let current_obj = self.states.current_obj;
                         if move_obj {
                             let ent = &self.scene.entities[current_obj];
                             let mut t = *ent.transform();
@@ -890,8 +1728,8 @@ pub fn handle_crossterm_input(&mut self, event: Event, _last_frame: Instant) ->
                         }
                     }
                     KeyCode::Char('a') => {
-                        let move_obj = self.states.borrow().move_obj;
-                        let current_obj = self.states.borrow().current_obj;
+                        let move_obj = self.states.move_obj;
+                        let current_obj = self.states.current_obj;
                         if move_obj {
                             let ent = &self.scene.entities[current_obj];
                             let mut t = *ent.transform();
@@ -902,8 +1740,8 @@ pub fn handle_crossterm_input(&mut self, event: Event, _last_frame: Instant) ->
                         }
                     }
                     KeyCode::Char('d') => {
-                        let move_obj = self.states.borrow().move_obj;
-                        let current_obj = self.states.borrow().current_obj;
+                        let move_obj = self.states.move_obj;
+                        let current_obj = self.states.current_obj;
                         if move_obj {
                             let ent = &self.scene.entities[current_obj];
                             let mut t = *ent.transform();
@@ -913,10 +1751,11 @@ pub fn handle_crossterm_input(&mut self, event: Event, _last_frame: Instant) ->
                             self.scene.camera.move_right(move_amount);
                         }
                     }
-                    // Orbit the first light in the light array around the origin
+                    // Orbit the selected light around the origin
                     KeyCode::Char('3') => {
-                        let mut light = self.scene.lights[0].clone();
-                        light.orbit(Vec3::ZERO, 5.0, 0.1, 0.1);
+                        if let Some(light) = self.selected_light() {
+                            light.orbit(Vec3::ZERO, 5.0, 0.1, 0.1);
+                        }
                     }
                     // Orbit the camera
                     KeyCode::Char('o') => {
@@ -926,8 +1765,8 @@ pub fn handle_crossterm_input(&mut self, event: Event, _last_frame: Instant) ->
                     KeyCode::Char(' ') => {
                         if modifiers.contains(event::KeyModifiers::SHIFT) {
                             // SHIFT+SPACE: move down
-                            let move_obj = self.states.borrow().move_obj;
-                            let current_obj = self.states.borrow().current_obj;
+                            let move_obj = self.states.move_obj;
+                            let current_obj = self.states.current_obj;
                             if move_obj {
                                 let ent = &self.scene.entities[current_obj];
                                 let mut t = *ent.transform();
@@ -938,8 +1777,8 @@ pub fn handle_crossterm_input(&mut self, event: Event, _last_frame: Instant) ->
                             }
                         } else {
                             // SPACE: move up
-                            let move_obj = self.states.borrow().move_obj;
-                            let current_obj = self.states.borrow().current_obj;
+                            let move_obj = self.states.move_obj;
+                            let current_obj = self.states.current_obj;
                             if move_obj {
                                 let ent = &self.scene.entities[current_obj];
                                 let mut t = *ent.transform();
@@ -957,6 +1796,12 @@ pub fn handle_crossterm_input(&mut self, event: Event, _last_frame: Instant) ->
                     KeyCode::Down => {
                         self.scene.camera.rotate(-rotate_amount, 0.0);
                     }
+                    KeyCode::Left => {
+                        self.scene.camera.rotate(0.0, rotate_amount);
+                    }
+                    KeyCode::Right => {
+                        self.scene.camera.rotate(0.0, -rotate_amount);
+                    }
                     // Exit on Esc or q (or Q)
                     KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('Q') => {
                         should_break = true;
@@ -969,29 +1814,29 @@ pub fn handle_crossterm_input(&mut self, event: Event, _last_frame: Instant) ->
                     // On mouse button down events...
                     MouseEventKind::Down(btn) => match btn {
                         crossterm::event::MouseButton::Left => {
-                            if !self.states.borrow().is_mouse_pan_enabled {
-                                self.states.borrow_mut().is_mouse_pan_enabled = true;
-                                self.states.borrow_mut().last_mouse_pos =
+                            if !self.states.is_mouse_pan_enabled {
+                                self.states.is_mouse_pan_enabled = true;
+                                self.states.last_mouse_pos =
                                     Some((mouse_event.column.into(), mouse_event.row.into()));
                             }
-                            println!(
+                            log::debug!(
                                 "Left click at ({}, {})",
                                 mouse_event.column, mouse_event.row
                             );
                         }
                         crossterm::event::MouseButton::Right => {
-                            if !self.states.borrow().is_mouse_look_enabled {
-                                self.states.borrow_mut().is_mouse_look_enabled = true;
-                                self.states.borrow_mut().last_mouse_pos =
+                            if !self.states.is_mouse_look_enabled {
+                                self.states.is_mouse_look_enabled = true;
+                                self.states.last_mouse_pos =
                                     Some((mouse_event.column.into(), mouse_event.row.into()));
                             }
-                            println!(
+                            log::debug!(
                                 "Right click at ({}, {})",
                                 mouse_event.column, mouse_event.row
                             );
                         }
                         crossterm::event::MouseButton::Middle => {
-                            println!(
+                            log::debug!(
                                 "Middle click at ({}, {})",
                                 mouse_event.column, mouse_event.row
                             );
@@ -1002,14 +1847,14 @@ pub fn handle_crossterm_input(&mut self, event: Event, _last_frame: Instant) ->
                     MouseEventKind::Up(btn) => {
                         match btn {
                             crossterm::event::MouseButton::Left => {
-                                self.states.borrow_mut().is_mouse_pan_enabled = false;
-                                self.states.borrow_mut().last_mouse_pos = None;
-                                //println!("Left button released");
+                                self.states.is_mouse_pan_enabled = false;
+                                self.states.last_mouse_pos = None;
+                                //log::info!("Left button released");
                             }
                             crossterm::event::MouseButton::Right => {
-                                self.states.borrow_mut().is_mouse_look_enabled = false;
-                                self.states.borrow_mut().last_mouse_pos = None;
-                                //println!("Right button released");
+                                self.states.is_mouse_look_enabled = false;
+                                self.states.last_mouse_pos = None;
+                                //log::info!("Right button released");
                             }
                             crossterm::event::MouseButton::Middle => {}
                             _ => {}
@@ -1019,8 +1864,8 @@ pub fn handle_crossterm_input(&mut self, event: Event, _last_frame: Instant) ->
                     MouseEventKind::Drag(btn) => {
                         match btn {
                             crossterm::event::MouseButton::Left => {
-                                if self.states.borrow().is_mouse_pan_enabled {
-                                    match self.states.borrow_mut().last_mouse_pos.take()
+                                if self.states.is_mouse_pan_enabled {
+                                    match self.states.last_mouse_pos.take()
                                     { Some(last_pos) => {
                                         let current_pos = (mouse_event.column, mouse_event.row);
                                         let current_mouse =
@@ -1040,10 +1885,10 @@ pub fn handle_crossterm_input(&mut self, event: Event, _last_frame: Instant) ->
                                         }
                                         let (a, b) = current_pos;
 
-                                        self.states.borrow_mut().last_mouse_pos =
+                                        self.states.last_mouse_pos =
                                             Some((a.into(), b.into()));
                                     } _ => {
-                                        self.states.borrow_mut().last_mouse_pos = Some((
+                                        self.states.last_mouse_pos = Some((
                                             mouse_event.column.into(),
                                             mouse_event.row.into(),
                                         ));
@@ -1051,8 +1896,8 @@ pub fn handle_crossterm_input(&mut self, event: Event, _last_frame: Instant) ->
                                 }
                             }
                             crossterm::event::MouseButton::Right => {
-                                if self.states.borrow().is_mouse_look_enabled {
-                                    match self.states.borrow_mut().last_mouse_pos.take()
+                                if self.states.is_mouse_look_enabled {
+                                    match self.states.last_mouse_pos.take()
                                     { Some(last_pos) => {
                                         let current_pos = (mouse_event.column, mouse_event.row);
                                         let current_mouse =
@@ -1065,10 +1910,10 @@ pub fn handle_crossterm_input(&mut self, event: Event, _last_frame: Instant) ->
                                             .camera
                                             .pitch(mouse_delta.y * rotate_speed * 0.005);
                                         let (a, b) = current_pos;
-                                        self.states.borrow_mut().last_mouse_pos =
+                                        self.states.last_mouse_pos =
                                             Some((a.into(), b.into()));
                                     } _ => {
-                                        self.states.borrow_mut().last_mouse_pos = Some((
+                                        self.states.last_mouse_pos = Some((
                                             mouse_event.column.into(),
                                             mouse_event.row.into(),
                                         ));
@@ -1077,26 +1922,26 @@ pub fn handle_crossterm_input(&mut self, event: Event, _last_frame: Instant) ->
                             }
                             _ => {}
                         }
-                        //println!(
+                        //log::info!(
                         //    "Dragging with {:?} button at ({}, {})",
                         //    btn, mouse_event.column, mouse_event.row
                         //);
                     }
                     MouseEventKind::Moved => {
-                        //println!(
+                        //log::info!(
                         //    "Mouse moved to ({}, {})",
                         //    mouse_event.column, mouse_event.row
                         //);
                     }
                     MouseEventKind::ScrollUp => {
-                        //println!(
+                        //log::info!(
                         //    "Scrolled up at ({}, {})",
                         //    mouse_event.column, mouse_event.row
                         //);
                         // Optionally implement zoom in
                     }
                     MouseEventKind::ScrollDown => {
-                        //println!(
+                        //log::info!(
                         //    "Scrolled down at ({}, {})",
                         //    mouse_event.column, mouse_event.row
                         //);
@@ -1110,3 +1955,108 @@ pub fn handle_crossterm_input(&mut self, event: Event, _last_frame: Instant) ->
         should_break
     }
 }
+
+/// Reconstructs the view ray from `eye` through pixel `pos` of a `size` sized screen and samples
+/// the sky along it
+fn sky_color(
+    sky: &Sky,
+    pos: (usize, usize),
+    size: (usize, usize),
+    eye: Vec3,
+    inv_viewproj: &Mat4,
+) -> Option<Color> {
+    if sky.is_none() {
+        return None;
+    }
+    let ndc_x = (pos.0 as f32 / size.0 as f32) * 2.0 - 1.0;
+    let ndc_y = 1.0 - (pos.1 as f32 / size.1 as f32) * 2.0;
+    // Any point along the ray works, the far plane just avoids precision trouble near the eye
+    let far = *inv_viewproj * Vec4::new(ndc_x, ndc_y, 1.0, 1.0);
+    let dir = (far.truncate() / far.w - eye).normalize();
+    sky.sample(dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{Camera, Entity, Environment, Light};
+    use crate::pipeline::TermBuffer;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn renders_scene_snapshots_on_another_thread() {
+        assert_send_sync::<Scene>();
+        assert_send_sync::<Pipeline<TermBuffer>>();
+
+        let mut scene = Scene::default();
+        scene.set_environment(Environment::blender_floor());
+        let mut pipeline = Pipeline::<TermBuffer>::new(40, 20, scene.clone());
+        let (snapshots, received) = mpsc::channel::<Scene>();
+        let renderer = std::thread::spawn(move || {
+            let mut frames = 0;
+            for snapshot in received {
+                pipeline.scene = snapshot;
+                pipeline.render_frame(&mut [], Duration::ZERO).unwrap();
+                frames += 1;
+            }
+            (frames, pipeline.profiler().last().triangles_in)
+        });
+
+        for _ in 0..3 {
+            scene.camera.move_forward(0.1);
+            snapshots.send(scene.clone()).unwrap();
+        }
+        drop(snapshots);
+        let (frames, triangles) = renderer.join().unwrap();
+        assert_eq!(frames, 3);
+        assert!(triangles > 0);
+    }
+
+    #[test]
+    fn spheres_stay_round_on_tall_cells() {
+        let camera = Camera::new(Vec3::new(0.0, 0.0, 6.0), Vec3::ZERO, 1.0);
+        let mut scene = Scene::new(camera);
+        for entity in Entity::new_icosphere().unwrap() {
+            scene.add_entity(entity);
+        }
+        scene.add_light(Light::easy_point(Vec3::new(0.0, 0.0, 6.0)));
+        let mut pipeline = Pipeline::<TermBuffer>::new(80, 40, scene);
+        pipeline.states.draw_hud = false;
+        pipeline.render_frame(&mut [], Duration::ZERO).unwrap();
+
+        // Cells are twice as tall as wide, so the sphere covers twice as many columns as rows
+        let frame = pipeline.front_buffer().frame();
+        let (mut columns, mut rows) = (Vec::new(), Vec::new());
+        for y in 0..40 {
+            for x in 0..80 {
+                if frame.cell((x, y)).0 != ' ' {
+                    columns.push(x);
+                    rows.push(y);
+                }
+            }
+        }
+        let extent = |v: &[usize]| (v.iter().max().unwrap() - v.iter().min().unwrap() + 1) as f32;
+        let ratio = extent(&columns) / extent(&rows);
+        assert!((1.8..=2.2).contains(&ratio), "columns / rows = {ratio}");
+    }
+
+    #[test]
+    fn resize_keeps_states_and_profile() {
+        let mut scene = Scene::default();
+        scene.set_environment(Environment::blender_floor());
+        let mut pipeline = Pipeline::<TermBuffer>::new(40, 20, scene);
+        pipeline.states.draw_wireframe = true;
+        pipeline.profiler_mut().set_recording(true);
+        pipeline.render_frame(&mut [], Duration::ZERO).unwrap();
+
+        pipeline.resize(100, 30);
+        pipeline.render_frame(&mut [], Duration::ZERO).unwrap();
+        assert_eq!(pipeline.front_buffer().size(), (100, 30));
+        assert_eq!(pipeline.scene.camera.aspect_ratio(), 100.0 / 60.0);
+        assert!(pipeline.states.draw_wireframe);
+        assert_eq!(pipeline.profiler().records().count(), 2);
+    }
+}
@@ -0,0 +1,71 @@
+use crate::core::Color;
+
+/// Operator used to squash linear HDR lighting results into displayable 0-1 range
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ToneMapping {
+    /// Plain clamp, anything brighter than 1 saturates
+    None,
+    /// `c / (1 + c)`, never clips but flattens highlights
+    Reinhard,
+    /// Narkowicz's fit of the ACES filmic curve, a bit of contrast and a soft shoulder
+    #[default]
+    Aces,
+}
+
+impl ToneMapping {
+    pub fn next(self) -> Self {
+        match self {
+            ToneMapping::None => ToneMapping::Reinhard,
+            ToneMapping::Reinhard => ToneMapping::Aces,
+            ToneMapping::Aces => ToneMapping::None,
+        }
+    }
+
+    /// Applies `exposure` (a linear multiplier) and then the operator, result is linear 0-1
+    pub fn apply(self, color: Color, exposure: f32) -> Color {
+        let c = color * exposure;
+        match self {
+            ToneMapping::None => c.clamped(),
+            ToneMapping::Reinhard => Color::new(reinhard(c.r), reinhard(c.g), reinhard(c.b)),
+            ToneMapping::Aces => Color::new(aces(c.r), aces(c.g), aces(c.b)),
+        }
+    }
+}
+
+fn reinhard(x: f32) -> f32 {
+    // Same as `x / (1 + x)`, but 1 rather than NaN for infinite inputs
+    1.0 - 1.0 / (1.0 + x.max(0.0))
+}
+
+fn aces(x: f32) -> f32 {
+    // The fit is made for the full ACES pipeline, which runs about 0.6x darker
+    // The curve is flat at 1 long before 1e4, and x² would overflow for huge inputs
+    let x = x.clamp(0.0, 1e4) * 0.6;
+    ((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14)).clamp(0.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tone_mapping_stays_in_range() {
+        let operators = [ToneMapping::None, ToneMapping::Reinhard, ToneMapping::Aces];
+        for operator in operators {
+            assert_eq!(
+                operator.apply(Color::BLACK, 1.0),
+                Color::BLACK,
+                "{operator:?}"
+            );
+            for x in [1.0, 10.0, 1e6, f32::MAX, f32::INFINITY] {
+                let c = operator.apply(Color::new(x, x, x), 1.0);
+                for channel in [c.r, c.g, c.b] {
+                    assert!(
+                        (0.0..=1.0).contains(&channel),
+                        "{operator:?}({x}) = {channel}"
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,579 @@
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::io::{self, Stdout, Write};
+use std::ops::Range;
+use std::path::PathBuf;
+
+#[cfg(feature = "window")]
+use minifb::Window;
+
+use crate::core::{Color, Pixel};
+
+/// Read only view of a finished frame, what presenters get to send somewhere. Colors are sRGB.
+#[derive(Clone, Copy, Debug)]
+pub enum Frame<'a> {
+    /// Packed 0RGB pixels, from a `FrameBuffer`
+    Rgb {
+        width: usize,
+        height: usize,
+        data: &'a [u32],
+    },
+    /// A character and its color per cell, from a `TermBuffer`
+    Cells {
+        width: usize,
+        height: usize,
+        data: &'a [Pixel],
+    },
+}
+
+impl<'a> Frame<'a> {
+    pub fn size(&self) -> (usize, usize) {
+        match *self {
+            Frame::Rgb { width, height, .. } | Frame::Cells { width, height, .. } => {
+                (width, height)
+            }
+        }
+    }
+
+    /// Character and color of the pixel at `pos`, pixels of RGB frames are full blocks
+    pub fn cell(&self, pos: (usize, usize)) -> (char, Color) {
+        let (width, _) = self.size();
+        let index = pos.0 + pos.1 * width;
+        match self {
+            Frame::Rgb { data, .. } => ('█', Pixel::Framebuffer(data[index]).color()),
+            Frame::Cells { data, .. } => (data[index].ch(), data[index].color()),
+        }
+    }
+
+    /// Color of the pixel at `pos` as an image would show it, blank cells are black like the
+    /// (presumably dark) terminal background they leave visible
+    pub fn color(&self, pos: (usize, usize)) -> Color {
+        match self.cell(pos) {
+            (' ', _) => Color::BLACK,
+            (_, color) => color,
+        }
+    }
+
+    /// The frame as packed 0RGB, only copied when it isn't stored that way already
+    pub fn to_rgb(&self) -> Cow<'a, [u32]> {
+        let (width, height) = self.size();
+        match *self {
+            Frame::Rgb { data, .. } => Cow::Borrowed(data),
+            Frame::Cells { .. } => Cow::Owned(
+                (0..width * height)
+                    .map(|i| self.color((i % width, i / width)).to_u32())
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// Somewhere finished frames go: a window, the terminal, image files, ...
+///
+/// `Pipeline::render_frame` hands the same frame to every presenter it gets, so several can be
+/// active at once. Closures taking a `&Frame` are presenters too.
+pub trait Presenter {
+    fn present(&mut self, frame: &Frame) -> io::Result<()>;
+
+    /// Bytes the last `present` wrote, for presenters whose output bandwidth is worth watching.
+    /// `Pipeline::render_frame` adds them up in the profiler.
+    fn bytes_written(&self) -> Option<usize> {
+        None
+    }
+}
+
+impl<F> Presenter for F
+where
+    F: FnMut(&Frame) -> io::Result<()>,
+{
+    fn present(&mut self, frame: &Frame) -> io::Result<()> {
+        self(frame)
+    }
+}
+
+const BEGIN_SYNCHRONIZED_UPDATE: &[u8] = b"\x1B[?2026h";
+const END_SYNCHRONIZED_UPDATE: &[u8] = b"\x1B[?2026l";
+
+/// Unchanged cells a cursor move may overwrite with their own contents instead of jumping over
+const MAX_OVERWRITE: usize = 8;
+
+/// Colors a `TerminalPresenter` can send
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColorMode {
+    /// 24 bit `38;2;r;g;b` escapes
+    #[default]
+    TrueColor,
+    /// Nearest entry of the xterm 256 color palette, for terminals without truecolor
+    Ansi256,
+}
+
+impl ColorMode {
+    /// What decides whether two packed colors need different escapes
+    fn key(self, rgb: u32) -> u32 {
+        match self {
+            ColorMode::TrueColor => rgb,
+            ColorMode::Ansi256 => ansi256(rgb) as u32,
+        }
+    }
+
+    fn write_escape(self, buffer: &mut Vec<u8>, rgb: u32) -> io::Result<()> {
+        match self {
+            ColorMode::TrueColor => {
+                // Same escape as `Color::to_ansii_escape`, without the allocation
+                let [_, r, g, b] = rgb.to_be_bytes();
+                write!(buffer, "\x1B[38;2;{r};{g};{b}m")
+            }
+            ColorMode::Ansi256 => write!(buffer, "\x1B[38;5;{}m", ansi256(rgb)),
+        }
+    }
+}
+
+/// Nearest xterm palette index of a packed 0RGB color, from the 6x6x6 cube or the gray ramp
+fn ansi256(rgb: u32) -> u8 {
+    const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+    let [_, r, g, b] = rgb.to_be_bytes();
+    let level = |c: u8| match c {
+        0..48 => 0,
+        48..115 => 1,
+        _ => (c as usize - 35) / 40,
+    };
+    let (cr, cg, cb) = (level(r), level(g), level(b));
+    let cube = (LEVELS[cr], LEVELS[cg], LEVELS[cb]);
+    let average = (r as usize + g as usize + b as usize) / 3;
+    let gray = if average > 238 {
+        23
+    } else {
+        average.saturating_sub(3) / 10
+    };
+    let gray_level = (8 + gray * 10) as u8;
+
+    let distance = |(cr, cg, cb): (u8, u8, u8)| {
+        let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+        d(cr, r) + d(cg, g) + d(cb, b)
+    };
+    if distance((gray_level, gray_level, gray_level)) < distance(cube) {
+        232 + gray as u8
+    } else {
+        (16 + 36 * cr + 6 * cg + cb) as u8
+    }
+}
+
+/// Writes frames as ANSI colored text, one character per cell.
+///
+/// Only the cells that changed since the last frame get written. The cursor gets from one
+/// changed cell to the next by the cheapest of an absolute move, a relative one or rewriting the
+/// unchanged cells in between, and color escapes are only sent when the color changes (blank
+/// cells don't care about it). Output goes through a byte buffer reused between frames.
+///
+/// With synchronized output on, every frame is wrapped in a DEC 2026 synchronized update so the
+/// terminal shows it all at once instead of tearing halfway through. Terminals that don't know
+/// the mode ignore it, `TerminalCapabilities` tells whether it's worth the 16 bytes.
+pub struct TerminalPresenter<W: Write = Stdout> {
+    out: W,
+    color_mode: ColorMode,
+    synchronized: bool,
+    /// Character and packed color of every cell as last presented
+    previous: Vec<(char, u32)>,
+    size: (usize, usize),
+    buffer: Vec<u8>,
+    /// Where the terminal cursor is while writing a frame, None when it isn't known
+    cursor: Option<(usize, usize)>,
+    /// Foreground color of the last color escape written this frame
+    color: Option<u32>,
+    last_bytes: usize,
+    total_bytes: u64,
+}
+
+impl TerminalPresenter {
+    /// Presents to stdout
+    pub fn new() -> Self {
+        Self::with_writer(io::stdout())
+    }
+}
+
+impl Default for TerminalPresenter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<W: Write> TerminalPresenter<W> {
+    pub fn with_writer(out: W) -> Self {
+        Self {
+            out,
+            color_mode: ColorMode::default(),
+            synchronized: false,
+            previous: Vec::new(),
+            size: (0, 0),
+            buffer: Vec::new(),
+            cursor: None,
+            color: None,
+            last_bytes: 0,
+            total_bytes: 0,
+        }
+    }
+
+    pub fn writer(&self) -> &W {
+        &self.out
+    }
+
+    pub fn color_mode(&self) -> ColorMode {
+        self.color_mode
+    }
+
+    pub fn set_color_mode(&mut self, color_mode: ColorMode) {
+        if self.color_mode != color_mode {
+            self.color_mode = color_mode;
+            self.invalidate();
+        }
+    }
+
+    pub fn synchronized(&self) -> bool {
+        self.synchronized
+    }
+
+    /// Wraps frames in synchronized updates (DEC mode 2026)
+    pub fn set_synchronized(&mut self, synchronized: bool) {
+        self.synchronized = synchronized;
+    }
+
+    /// Bytes written for the last frame, 0 when nothing changed
+    pub fn last_frame_bytes(&self) -> usize {
+        self.last_bytes
+    }
+
+    /// Bytes written since the presenter was created
+    pub fn total_bytes(&self) -> u64 {
+        self.total_bytes
+    }
+
+    /// Forgets what's on screen, the next frame gets written in full
+    pub fn invalidate(&mut self) {
+        self.previous.clear();
+        self.size = (0, 0);
+    }
+
+    /// Moves the cursor to `to` with the shortest escape sequence (or unchanged cells) available
+    fn move_to(&mut self, frame: &Frame, to: (usize, usize)) {
+        let Some(from) = self.cursor else {
+            write_absolute(&mut self.buffer, to);
+            return;
+        };
+        if from == to {
+            return;
+        }
+        let absolute = absolute_len(to);
+        let (x, y) = to;
+        let vertical = match y.cmp(&from.1) {
+            Ordering::Greater => relative_len(y - from.1),
+            Ordering::Less => relative_len(from.1 - y),
+            Ordering::Equal => 0,
+        };
+        // Horizontal options starting from the cursor's column, or from the first one after a CR
+        let horizontal = match x.cmp(&from.0) {
+            Ordering::Greater => relative_len(x - from.0),
+            Ordering::Less => relative_len(from.0 - x),
+            Ordering::Equal => 0,
+        };
+        let carriage_return = 1 + if x == 0 { 0 } else { relative_len(x) };
+        let overwrite = (y == from.1 && x > from.0 && x - from.0 <= MAX_OVERWRITE)
+            .then(|| self.overwrite_len(frame, from.0..x, y))
+            .flatten();
+
+        let relative = vertical + horizontal.min(carriage_return);
+        if overwrite.is_some_and(|len| len <= relative.min(absolute)) {
+            for cx in from.0..x {
+                let (ch, _) = frame.cell((cx, y));
+                push_char(&mut self.buffer, ch);
+            }
+        } else if relative < absolute {
+            match y.cmp(&from.1) {
+                Ordering::Greater => write_relative(&mut self.buffer, y - from.1, b'B'),
+                Ordering::Less => write_relative(&mut self.buffer, from.1 - y, b'A'),
+                Ordering::Equal => {}
+            }
+            if carriage_return < horizontal {
+                self.buffer.push(b'\r');
+                if x > 0 {
+                    write_relative(&mut self.buffer, x, b'C');
+                }
+            } else if x > from.0 {
+                write_relative(&mut self.buffer, x - from.0, b'C');
+            } else if x < from.0 {
+                write_relative(&mut self.buffer, from.0 - x, b'D');
+            }
+        } else {
+            write_absolute(&mut self.buffer, to);
+        }
+        self.cursor = Some(to);
+    }
+
+    /// Bytes needed to rewrite cells `xs` of row `y` as they are, None if that would need a color
+    /// change
+    fn overwrite_len(&self, frame: &Frame, xs: Range<usize>, y: usize) -> Option<usize> {
+        xs.map(|x| match frame.cell((x, y)) {
+            (' ', _) => Some(1),
+            (ch, color) if Some(self.color_mode.key(color.to_u32())) == self.color => {
+                Some(ch.len_utf8())
+            }
+            _ => None,
+        })
+        .sum()
+    }
+}
+
+impl<W: Write> Presenter for TerminalPresenter<W> {
+    fn present(&mut self, frame: &Frame) -> io::Result<()> {
+        let (width, height) = frame.size();
+        // Everything needs redrawing after a resize
+        if self.size != (width, height) {
+            self.size = (width, height);
+            self.previous.clear();
+            self.previous.resize(width * height, ('\0', 0));
+        }
+        self.buffer.clear();
+        self.cursor = None;
+        self.color = None;
+
+        for y in 0..height {
+            for x in 0..width {
+                let (ch, color) = frame.cell((x, y));
+                let cell = (ch, color.to_u32());
+                let previous = &mut self.previous[x + y * width];
+                // The color of a blank cell doesn't show
+                if *previous == cell || (ch == ' ' && previous.0 == ' ') {
+                    continue;
+                }
+                *previous = cell;
+
+                if self.buffer.is_empty() {
+                    if self.synchronized {
+                        self.buffer.extend_from_slice(BEGIN_SYNCHRONIZED_UPDATE);
+                    }
+                    // Hide cursor once per frame that has updates
+                    self.buffer.extend_from_slice(b"\x1B[?25l");
+                }
+                self.move_to(frame, (x, y));
+                let key = self.color_mode.key(cell.1);
+                if ch != ' ' && self.color != Some(key) {
+                    self.color_mode.write_escape(&mut self.buffer, cell.1)?;
+                    self.color = Some(key);
+                }
+                push_char(&mut self.buffer, ch);
+                // Writing the last column leaves the cursor in a terminal dependent state
+                self.cursor = (x + 1 < width).then_some((x + 1, y));
+            }
+        }
+
+        if self.synchronized && !self.buffer.is_empty() {
+            self.buffer.extend_from_slice(END_SYNCHRONIZED_UPDATE);
+        }
+        self.last_bytes = self.buffer.len();
+        self.total_bytes += self.buffer.len() as u64;
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        self.out.write_all(&self.buffer)?;
+        self.out.flush()
+    }
+
+    fn bytes_written(&self) -> Option<usize> {
+        Some(self.last_bytes)
+    }
+}
+
+fn push_char(buffer: &mut Vec<u8>, ch: char) {
+    buffer.extend_from_slice(ch.encode_utf8(&mut [0; 4]).as_bytes());
+}
+
+fn digits(n: usize) -> usize {
+    n.checked_ilog10().map_or(1, |log| log as usize + 1)
+}
+
+/// Length of `write_absolute`'s escape
+fn absolute_len((x, y): (usize, usize)) -> usize {
+    match (x, y) {
+        (0, 0) => 3,
+        (0, _) => 3 + digits(y + 1),
+        _ => 4 + digits(y + 1) + digits(x + 1),
+    }
+}
+
+/// CUP, the row and column (1 based) are left out where they're 1
+fn write_absolute(buffer: &mut Vec<u8>, (x, y): (usize, usize)) {
+    match (x, y) {
+        (0, 0) => buffer.extend_from_slice(b"\x1B[H"),
+        (0, _) => write!(buffer, "\x1B[{}H", y + 1).unwrap(),
+        _ => write!(buffer, "\x1B[{};{}H", y + 1, x + 1).unwrap(),
+    }
+}
+
+/// Length of `write_relative`'s escape
+fn relative_len(n: usize) -> usize {
+    if n == 1 {
+        3
+    } else {
+        3 + digits(n)
+    }
+}
+
+/// CUU/CUD/CUF/CUB (`direction` A/B/C/D) by `n` cells, the count is left out when it's 1
+fn write_relative(buffer: &mut Vec<u8>, n: usize, direction: u8) {
+    if n == 1 {
+        buffer.extend_from_slice(&[0x1B, b'[', direction]);
+    } else {
+        write!(buffer, "\x1B[{n}{}", direction as char).unwrap();
+    }
+}
+
+/// Shows frames in a minifb window. Presenting is also what makes minifb process the window's
+/// events, so input should be read after `Pipeline::render_frame`.
+#[cfg(feature = "window")]
+pub struct WindowPresenter {
+    window: Window,
+}
+
+#[cfg(feature = "window")]
+impl WindowPresenter {
+    pub fn new(window: Window) -> Self {
+        Self { window }
+    }
+
+    pub fn window(&self) -> &Window {
+        &self.window
+    }
+
+    pub fn window_mut(&mut self) -> &mut Window {
+        &mut self.window
+    }
+}
+
+#[cfg(feature = "window")]
+impl Presenter for WindowPresenter {
+    fn present(&mut self, frame: &Frame) -> io::Result<()> {
+        let (width, height) = frame.size();
+        self.window
+            .update_with_buffer(&frame.to_rgb(), width, height)
+            .map_err(|e| io::Error::other(e.to_string()))
+    }
+}
+
+/// Saves frames as images, the format follows the extension. A `{}` in the path gets replaced by
+/// the frame number (zero padded), without one every frame overwrites the same file.
+pub struct FilePresenter {
+    path: String,
+    frame: u64,
+}
+
+impl FilePresenter {
+    pub fn new(path: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            frame: 0,
+        }
+    }
+
+    /// Where the next frame goes
+    pub fn next_path(&self) -> PathBuf {
+        PathBuf::from(self.path.replace("{}", &format!("{:06}", self.frame)))
+    }
+}
+
+impl Presenter for FilePresenter {
+    fn present(&mut self, frame: &Frame) -> io::Result<()> {
+        let (width, height) = frame.size();
+        let image = image::RgbImage::from_fn(width as u32, height as u32, |x, y| {
+            let color = frame.color((x as usize, y as usize)).to_u32();
+            image::Rgb([(color >> 16) as u8, (color >> 8) as u8, color as u8])
+        });
+        image
+            .save(self.next_path())
+            .map_err(|e| io::Error::other(format!("{}: {e}", self.next_path().display())))?;
+        self.frame += 1;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(data: &[Pixel]) -> Frame<'_> {
+        Frame::Cells {
+            width: 6,
+            height: 3,
+            data,
+        }
+    }
+
+    /// Applies the escapes `TerminalPresenter` uses to a grid of (char, packed color) cells
+    fn emulate(output: &[u8], grid: &mut [(char, u32)], width: usize) {
+        let (mut x, mut y, mut color) = (0, 0, 0);
+        let mut chars = std::str::from_utf8(output).unwrap().chars();
+        while let Some(ch) = chars.next() {
+            match ch {
+                '\x1B' => {
+                    assert_eq!(chars.next(), Some('['));
+                    let mut params = String::new();
+                    let command = chars.by_ref().find(|c| {
+                        let done = c.is_ascii_alphabetic();
+                        if !done {
+                            params.push(*c);
+                        }
+                        done
+                    });
+                    let n: Vec<usize> = params.split(';').filter_map(|p| p.parse().ok()).collect();
+                    let count = n.first().copied().unwrap_or(1);
+                    match command.unwrap() {
+                        'H' => (y, x) = (count - 1, n.get(1).copied().unwrap_or(1) - 1),
+                        'A' => y -= count,
+                        'B' => y += count,
+                        'C' => x += count,
+                        'D' => x -= count,
+                        'm' => color = ((n[2] << 16) | (n[3] << 8) | n[4]) as u32,
+                        'l' => {}
+                        other => panic!("unexpected escape {other}"),
+                    }
+                }
+                '\r' => x = 0,
+                _ => {
+                    grid[x + y * width] = (ch, color);
+                    x = (x + 1).min(width - 1);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn terminal_only_writes_changed_cells() {
+        let mut cells = vec![Pixel::new_terminal(' ', Color::BLACK); 6 * 3];
+        let mut grid = vec![('?', 0); 6 * 3];
+        let mut presenter = TerminalPresenter::with_writer(Vec::new());
+        let mut written = 0;
+        let mut present = |presenter: &mut TerminalPresenter<Vec<u8>>, cells: &[Pixel]| {
+            presenter.present(&frame(cells)).unwrap();
+            emulate(&presenter.writer()[written..], &mut grid, 6);
+            written = presenter.writer().len();
+            for (cell, pixel) in grid.iter().zip(cells) {
+                let expected = (pixel.ch(), pixel.color().to_u32());
+                assert!(cell.0 == expected.0 && (cell.0 == ' ' || cell.1 == expected.1));
+            }
+            presenter.last_frame_bytes()
+        };
+
+        for (i, ch) in "ab cd".chars().enumerate() {
+            cells[i + 6] = Pixel::new_terminal(ch, Color::RED);
+        }
+        cells[17] = Pixel::new_terminal('z', Color::BLUE);
+        assert!(present(&mut presenter, &cells) > 0);
+        assert_eq!(present(&mut presenter, &cells), 0);
+
+        // Scattered changes, including a blank cell whose color doesn't matter
+        cells[0] = Pixel::new_terminal('x', Color::RED);
+        cells[10] = Pixel::new_terminal('e', Color::RED);
+        cells[8] = Pixel::new_terminal(' ', Color::GREEN);
+        cells[16] = Pixel::new_terminal('y', Color::BLUE);
+        let bytes = present(&mut presenter, &cells);
+        assert!(bytes > 0 && bytes < 64, "{bytes} bytes for three cells");
+    }
+}
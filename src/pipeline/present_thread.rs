@@ -0,0 +1,345 @@
+use std::collections::VecDeque;
+use std::io;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use std::thread::{self, JoinHandle};
+
+use super::{Frame, Presenter};
+use crate::core::Pixel;
+
+/// What `ThreadedPresenter::present` does when every buffer is still queued or being written
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DropPolicy {
+    /// Replace the oldest frame that hasn't been presented yet, the output always shows the
+    /// newest frame at the cost of skipping some
+    #[default]
+    DropOldest,
+    /// Skip the frame being presented, whatever is already queued goes out first
+    DropNewest,
+    /// Wait for the output thread, rendering slows down to what the output can take
+    Block,
+}
+
+/// Frames handed to and dropped by a `ThreadedPresenter`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PresentStats {
+    /// Frames the wrapped presenter finished
+    pub presented: u64,
+    /// Frames the drop policy threw away
+    pub dropped: u64,
+}
+
+/// Owned copy of a `Frame`, kept around and reused by the output thread's buffers
+enum FrameData {
+    Rgb(Vec<u32>),
+    Cells(Vec<Pixel>),
+}
+
+struct OwnedFrame {
+    width: usize,
+    height: usize,
+    data: FrameData,
+}
+
+impl OwnedFrame {
+    fn copy_from(&mut self, frame: &Frame) {
+        (self.width, self.height) = frame.size();
+        match (frame, &mut self.data) {
+            (Frame::Rgb { data, .. }, FrameData::Rgb(buffer)) => {
+                buffer.clear();
+                buffer.extend_from_slice(data);
+            }
+            (Frame::Cells { data, .. }, FrameData::Cells(buffer)) => {
+                buffer.clear();
+                buffer.extend_from_slice(data);
+            }
+            (Frame::Rgb { data, .. }, buffer) => *buffer = FrameData::Rgb(data.to_vec()),
+            (Frame::Cells { data, .. }, buffer) => *buffer = FrameData::Cells(data.to_vec()),
+        }
+    }
+
+    fn frame(&self) -> Frame<'_> {
+        let (width, height) = (self.width, self.height);
+        match &self.data {
+            FrameData::Rgb(data) => Frame::Rgb {
+                width,
+                height,
+                data,
+            },
+            FrameData::Cells(data) => Frame::Cells {
+                width,
+                height,
+                data,
+            },
+        }
+    }
+}
+
+#[derive(Default)]
+struct State {
+    /// Copied frames waiting for the output thread, oldest first
+    queue: VecDeque<OwnedFrame>,
+    /// Buffers the output thread is done with
+    free: Vec<OwnedFrame>,
+    /// Buffers created so far, never more than `ThreadedPresenter::buffers`
+    allocated: usize,
+    closed: bool,
+    /// The output thread is gone, after a panic in the wrapped presenter or once closed
+    exited: bool,
+    /// First error the wrapped presenter ran into, returned by the next `present`
+    error: Option<io::Error>,
+    stats: PresentStats,
+    /// What the wrapped presenter reported for the last frame it finished
+    bytes_written: Option<usize>,
+}
+
+#[derive(Default)]
+struct Shared {
+    state: Mutex<State>,
+    /// Signalled whenever a frame gets queued, a buffer gets freed or the presenter closes
+    changed: Condvar,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+fn exited_error() -> io::Error {
+    io::Error::other("the present thread exited")
+}
+
+/// Tells the render thread when the output thread stops, even if the wrapped presenter panics
+struct ExitGuard<'a>(&'a Shared);
+
+impl Drop for ExitGuard<'_> {
+    fn drop(&mut self) {
+        let mut state = self.0.lock();
+        state.exited = true;
+        if thread::panicking() {
+            state.error.get_or_insert_with(exited_error);
+        }
+        self.0.changed.notify_all();
+    }
+}
+
+/// Runs another presenter on a dedicated output thread.
+///
+/// `present` only copies the frame into a free buffer and queues it, so the pipeline can start
+/// on the next frame while the previous one is still being written out. `buffers` is the number
+/// of frame copies in flight: 2 for double buffering (one being written, one queued), 3 for
+/// triple buffering. When the output can't keep up `policy` decides which frames get dropped.
+pub struct ThreadedPresenter<P: Presenter + Send + 'static> {
+    shared: Arc<Shared>,
+    buffers: usize,
+    policy: DropPolicy,
+    worker: Option<JoinHandle<P>>,
+}
+
+impl<P: Presenter + Send + 'static> ThreadedPresenter<P> {
+    /// Moves `presenter` to a new output thread, `buffers` is clamped to at least 2
+    pub fn new(presenter: P, buffers: usize, policy: DropPolicy) -> Self {
+        let shared = Arc::new(Shared::default());
+        let worker = {
+            let shared = Arc::clone(&shared);
+            thread::Builder::new()
+                .name("present".into())
+                .spawn(move || output_loop(presenter, &shared))
+                .expect("failed to spawn the present thread")
+        };
+        Self {
+            shared,
+            buffers: buffers.max(2),
+            policy,
+            worker: Some(worker),
+        }
+    }
+
+    pub fn stats(&self) -> PresentStats {
+        self.shared.lock().stats
+    }
+
+    pub fn policy(&self) -> DropPolicy {
+        self.policy
+    }
+
+    pub fn set_policy(&mut self, policy: DropPolicy) {
+        self.policy = policy;
+    }
+
+    /// Presents whatever is still queued, stops the output thread and hands the presenter back
+    pub fn finish(mut self) -> io::Result<P> {
+        let presenter = self.stop();
+        match self.shared.lock().error.take() {
+            Some(e) => Err(e),
+            None => presenter.ok_or_else(exited_error),
+        }
+    }
+
+    fn stop(&mut self) -> Option<P> {
+        self.shared.lock().closed = true;
+        self.shared.changed.notify_all();
+        self.worker.take().and_then(|worker| worker.join().ok())
+    }
+
+    /// A buffer to copy the next frame into, None when the frame should be dropped. Fails once
+    /// the output thread is gone, nothing would ever present or free a buffer again.
+    fn acquire(&self) -> io::Result<Option<OwnedFrame>> {
+        let mut state = self.shared.lock();
+        loop {
+            if let Some(e) = state.error.take() {
+                return Err(e);
+            }
+            if state.exited {
+                return Err(exited_error());
+            }
+            if let Some(buffer) = state.free.pop() {
+                return Ok(Some(buffer));
+            }
+            if state.allocated < self.buffers {
+                state.allocated += 1;
+                return Ok(Some(OwnedFrame {
+                    width: 0,
+                    height: 0,
+                    data: FrameData::Rgb(Vec::new()),
+                }));
+            }
+            match self.policy {
+                DropPolicy::DropOldest => {
+                    // Only the frame being written is out of reach, with two or more buffers
+                    // there is always a queued one once none are free
+                    if let Some(buffer) = state.queue.pop_front() {
+                        state.stats.dropped += 1;
+                        return Ok(Some(buffer));
+                    }
+                }
+                DropPolicy::DropNewest => {
+                    state.stats.dropped += 1;
+                    return Ok(None);
+                }
+                DropPolicy::Block => {}
+            }
+            state = self
+                .shared
+                .changed
+                .wait(state)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+    }
+}
+
+impl<P: Presenter + Send + 'static> Presenter for ThreadedPresenter<P> {
+    fn present(&mut self, frame: &Frame) -> io::Result<()> {
+        let Some(mut buffer) = self.acquire()? else {
+            return Ok(());
+        };
+        // Copied without holding the lock, the output thread keeps going meanwhile
+        buffer.copy_from(frame);
+        self.shared.lock().queue.push_back(buffer);
+        self.shared.changed.notify_all();
+        Ok(())
+    }
+
+    /// The wrapped presenter's count for the last frame the output thread finished, which lags
+    /// behind the frames handed to `present`
+    fn bytes_written(&self) -> Option<usize> {
+        self.shared.lock().bytes_written
+    }
+}
+
+impl<P: Presenter + Send + 'static> Drop for ThreadedPresenter<P> {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn output_loop<P: Presenter>(mut presenter: P, shared: &Shared) -> P {
+    let _exit = ExitGuard(shared);
+    let mut state = shared.lock();
+    loop {
+        let Some(buffer) = state.queue.pop_front() else {
+            if state.closed {
+                return presenter;
+            }
+            state = shared
+                .changed
+                .wait(state)
+                .unwrap_or_else(PoisonError::into_inner);
+            continue;
+        };
+        drop(state);
+        let result = presenter.present(&buffer.frame());
+        state = shared.lock();
+        state.bytes_written = presenter.bytes_written();
+        match result {
+            Ok(()) => state.stats.presented += 1,
+            Err(e) => {
+                state.error.get_or_insert(e);
+            }
+        }
+        state.free.push(buffer);
+        shared.changed.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn drop_oldest_keeps_the_newest_frame() {
+        // The output reports each frame it starts on, then waits until the test lets it go
+        let (started, output_started) = mpsc::channel::<()>();
+        let (release, blocked) = mpsc::channel::<()>();
+        let (seen, received) = mpsc::channel::<u32>();
+        let slow_output = move |frame: &Frame| {
+            started.send(()).unwrap();
+            blocked.recv().unwrap();
+            seen.send(frame.to_rgb()[0]).unwrap();
+            Ok(())
+        };
+        let mut presenter = ThreadedPresenter::new(slow_output, 2, DropPolicy::DropOldest);
+        let present = |presenter: &mut ThreadedPresenter<_>, i: u32| {
+            let data = [i];
+            let frame = Frame::Rgb {
+                width: 1,
+                height: 1,
+                data: &data,
+            };
+            presenter.present(&frame).unwrap();
+        };
+
+        present(&mut presenter, 0);
+        output_started.recv().unwrap();
+        // Frame 0 is stuck in the output, so 1 to 3 get replaced by the next frame while queued
+        for i in 1..5 {
+            present(&mut presenter, i);
+        }
+        assert_eq!(presenter.stats().dropped, 3);
+        release.send(()).unwrap();
+        release.send(()).unwrap();
+        assert!(presenter.finish().is_ok());
+
+        assert_eq!(received.iter().collect::<Vec<_>>(), [0, 4]);
+    }
+
+    #[test]
+    fn panicking_output_fails_instead_of_blocking() {
+        let panicking_output = |_: &Frame| -> io::Result<()> { panic!("output failed") };
+        let mut presenter = ThreadedPresenter::new(panicking_output, 2, DropPolicy::Block);
+        let data = [0];
+        let frame = Frame::Rgb {
+            width: 1,
+            height: 1,
+            data: &data,
+        };
+
+        // The first frame is queued, by the third both buffers are used up and only the output
+        // thread could free one, so it has to report that the thread is gone
+        let results: Vec<_> = (0..4).map(|_| presenter.present(&frame)).collect();
+        assert!(results[0].is_ok());
+        assert!(results[2..].iter().all(Result::is_err));
+        assert!(presenter.finish().is_err());
+    }
+}
@@ -0,0 +1,412 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Frames kept for export, about ten minutes at 60 fps. Older frames get dropped.
+const MAX_RECORDS: usize = 36_000;
+
+/// The timed parts of `Pipeline::render_frame`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stage {
+    /// Scene updates and vertex transformation, timed per entity and summed over the frame
+    Geometry,
+    /// Frustum clipping and culling, timed per entity (after its `Geometry` block) and summed
+    /// over the frame
+    Clipping,
+    Rasterize,
+    /// Writing fragments into the GBuffer
+    Fragments,
+    /// Shading the GBuffer (or the debug view/unlit fallback)
+    Lighting,
+    /// Bloom and tone mapping
+    PostProcess,
+    /// Overlay edges, debug shapes and the HUD
+    Overlay,
+    Present,
+}
+
+impl Stage {
+    pub const COUNT: usize = 8;
+    pub const ALL: [Stage; Stage::COUNT] = [
+        Stage::Geometry,
+        Stage::Clipping,
+        Stage::Rasterize,
+        Stage::Fragments,
+        Stage::Lighting,
+        Stage::PostProcess,
+        Stage::Overlay,
+        Stage::Present,
+    ];
+
+    /// Track of the stage in Chrome traces. `Geometry` and `Clipping` alternate, so their
+    /// summed blocks would partially overlap on one track.
+    fn trace_thread(&self) -> u32 {
+        match self {
+            Stage::Clipping => 2,
+            _ => 1,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Stage::Geometry => "geometry",
+            Stage::Clipping => "clipping",
+            Stage::Rasterize => "rasterize",
+            Stage::Fragments => "fragments",
+            Stage::Lighting => "lighting",
+            Stage::PostProcess => "post_process",
+            Stage::Overlay => "overlay",
+            Stage::Present => "present",
+        }
+    }
+}
+
+/// When a stage first started (relative to the profiler's creation) and how long it ran in total
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StageTime {
+    pub start: Option<Duration>,
+    pub duration: Duration,
+}
+
+/// Timings and counts of one frame
+#[derive(Clone, Debug, Default)]
+pub struct FrameStats {
+    pub frame: u64,
+    /// Relative to the profiler's creation
+    pub start: Duration,
+    pub total: Duration,
+    pub stages: [StageTime; Stage::COUNT],
+    /// Triangles of every renderable entity
+    pub triangles_in: usize,
+    /// Back facing or degenerate triangles
+    pub triangles_culled: usize,
+    /// Triangles that crossed a frustum plane and got cut
+    pub triangles_clipped: usize,
+    /// Triangles entirely outside the frustum
+    pub triangles_outside: usize,
+    /// Triangles handed to the rasterizer, clipping can turn one into several
+    pub triangles_out: usize,
+    pub fragments: usize,
+    /// Bytes the presenters wrote, for those that report it
+    pub present_bytes: usize,
+}
+
+impl FrameStats {
+    pub fn stage(&self, stage: Stage) -> Duration {
+        self.stages[stage as usize].duration
+    }
+}
+
+/// Per stage timings and triangle/fragment counts of every frame. The last finished frame is
+/// always available, the full history only gets kept while recording.
+pub struct Profiler {
+    epoch: Instant,
+    frame_start: Instant,
+    current: FrameStats,
+    last: FrameStats,
+    recording: bool,
+    records: VecDeque<FrameStats>,
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        let now = Instant::now();
+        Self {
+            epoch: now,
+            frame_start: now,
+            current: FrameStats::default(),
+            last: FrameStats::default(),
+            recording: false,
+            records: VecDeque::new(),
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording
+    }
+
+    /// Starts or stops keeping every frame for export, starting clears the previous recording
+    pub fn set_recording(&mut self, recording: bool) {
+        if recording && !self.recording {
+            self.records.clear();
+        }
+        self.recording = recording;
+    }
+
+    pub fn begin_frame(&mut self) {
+        self.frame_start = Instant::now();
+        self.current = FrameStats {
+            frame: self.last.frame + 1,
+            start: self.frame_start - self.epoch,
+            ..FrameStats::default()
+        };
+    }
+
+    /// Adds the time since `started` to `stage`. Calling it several times for the same stage in
+    /// one frame sums the times.
+    pub fn record(&mut self, stage: Stage, started: Instant) {
+        let time = &mut self.current.stages[stage as usize];
+        time.start.get_or_insert(started - self.epoch);
+        time.duration += started.elapsed();
+    }
+
+    /// Counts of the frame in progress
+    pub fn current(&mut self) -> &mut FrameStats {
+        &mut self.current
+    }
+
+    pub fn end_frame(&mut self) {
+        self.current.total = self.frame_start.elapsed();
+        self.last = std::mem::take(&mut self.current);
+        if self.recording {
+            if self.records.len() == MAX_RECORDS {
+                self.records.pop_front();
+            }
+            self.records.push_back(self.last.clone());
+        }
+    }
+
+    /// The most recent finished frame
+    pub fn last(&self) -> &FrameStats {
+        &self.last
+    }
+
+    pub fn records(&self) -> impl Iterator<Item = &FrameStats> {
+        self.records.iter()
+    }
+
+    /// Writes the recording to `path`, the extension picks the format: `.csv`, `.trace.json`
+    /// (Chrome trace, open with chrome://tracing or Perfetto) or `.json`
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        let name = path.to_string_lossy();
+        if name.ends_with(".trace.json") {
+            self.write_chrome_trace(&mut out)?;
+        } else if name.ends_with(".json") {
+            self.write_json(&mut out)?;
+        } else if name.ends_with(".csv") {
+            self.write_csv(&mut out)?;
+        } else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{name}: unknown profile format, use .csv, .json or .trace.json"),
+            ));
+        }
+        out.flush()
+    }
+
+    /// One row per frame, times in milliseconds
+    pub fn write_csv(&self, out: &mut impl Write) -> io::Result<()> {
+        write!(out, "frame,start_ms,total_ms")?;
+        for stage in Stage::ALL {
+            write!(out, ",{}_ms", stage.name())?;
+        }
+        writeln!(
+            out,
+            ",triangles_in,triangles_culled,triangles_clipped,triangles_outside,triangles_out,fragments,present_bytes"
+        )?;
+        for record in &self.records {
+            write!(
+                out,
+                "{},{:.3},{:.3}",
+                record.frame,
+                ms(record.start),
+                ms(record.total)
+            )?;
+            for stage in Stage::ALL {
+                write!(out, ",{:.3}", ms(record.stage(stage)))?;
+            }
+            writeln!(
+                out,
+                ",{},{},{},{},{},{},{}",
+                record.triangles_in,
+                record.triangles_culled,
+                record.triangles_clipped,
+                record.triangles_outside,
+                record.triangles_out,
+                record.fragments,
+                record.present_bytes
+            )?;
+        }
+        Ok(())
+    }
+
+    /// An array with one object per frame, times in milliseconds
+    pub fn write_json(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(out, "[")?;
+        for (i, record) in self.records.iter().enumerate() {
+            write!(
+                out,
+                "  {{\"frame\": {}, \"start_ms\": {:.3}, \"total_ms\": {:.3}, \"stages_ms\": {{",
+                record.frame,
+                ms(record.start),
+                ms(record.total)
+            )?;
+            for (j, stage) in Stage::ALL.iter().enumerate() {
+                let separator = if j == 0 { "" } else { ", " };
+                write!(
+                    out,
+                    "{separator}\"{}\": {:.3}",
+                    stage.name(),
+                    ms(record.stage(*stage))
+                )?;
+            }
+            write!(
+                out,
+                "}}, \"triangles_in\": {}, \"triangles_culled\": {}, \"triangles_clipped\": {}, \
+                 \"triangles_outside\": {}, \"triangles_out\": {}, \"fragments\": {}, \
+                 \"present_bytes\": {}}}",
+                record.triangles_in,
+                record.triangles_culled,
+                record.triangles_clipped,
+                record.triangles_outside,
+                record.triangles_out,
+                record.fragments,
+                record.present_bytes
+            )?;
+            writeln!(out, "{}", if i + 1 < self.records.len() { "," } else { "" })?;
+        }
+        writeln!(out, "]")
+    }
+
+    /// Chrome trace event format, a complete ("X") event per frame and per stage with the
+    /// counts as counter ("C") events. Geometry and clipping run in alternating blocks per
+    /// entity and are summed over the frame, so each shows up as one block at the start of its
+    /// first block. Clipping gets a track of its own to keep the two from overlapping.
+    pub fn write_chrome_trace(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(out, "{{\"traceEvents\": [")?;
+        let mut first = true;
+        let mut event = |out: &mut dyn Write, event: String| -> io::Result<()> {
+            let separator = if first { "" } else { ",\n" };
+            first = false;
+            write!(out, "{separator}  {event}")
+        };
+        for (tid, name) in [(1, "pipeline"), (2, "clipping")] {
+            event(
+                out,
+                format!(
+                    "{{\"name\": \"thread_name\", \"ph\": \"M\", \"pid\": 1, \"tid\": {tid}, \
+                     \"args\": {{\"name\": \"{name}\"}}}}"
+                ),
+            )?;
+        }
+        for record in &self.records {
+            event(
+                out,
+                complete_event(
+                    &format!("frame {}", record.frame),
+                    1,
+                    record.start,
+                    record.total,
+                ),
+            )?;
+            for stage in Stage::ALL {
+                let time = record.stages[stage as usize];
+                if let Some(start) = time.start {
+                    let tid = stage.trace_thread();
+                    event(out, complete_event(stage.name(), tid, start, time.duration))?;
+                }
+            }
+            event(
+                out,
+                format!(
+                    "{{\"name\": \"triangles\", \"ph\": \"C\", \"ts\": {:.3}, \"pid\": 1, \"args\": \
+                     {{\"in\": {}, \"culled\": {}, \"clipped\": {}, \"outside\": {}, \"out\": {}}}}}",
+                    us(record.start),
+                    record.triangles_in,
+                    record.triangles_culled,
+                    record.triangles_clipped,
+                    record.triangles_outside,
+                    record.triangles_out
+                ),
+            )?;
+            event(
+                out,
+                format!(
+                    "{{\"name\": \"fragments\", \"ph\": \"C\", \"ts\": {:.3}, \"pid\": 1, \"args\": \
+                     {{\"fragments\": {}}}}}",
+                    us(record.start),
+                    record.fragments
+                ),
+            )?;
+            event(
+                out,
+                format!(
+                    "{{\"name\": \"present_bytes\", \"ph\": \"C\", \"ts\": {:.3}, \"pid\": 1, \
+                     \"args\": {{\"bytes\": {}}}}}",
+                    us(record.start),
+                    record.present_bytes
+                ),
+            )?;
+        }
+        writeln!(out, "\n]}}")
+    }
+}
+
+fn complete_event(name: &str, tid: u32, start: Duration, duration: Duration) -> String {
+    format!(
+        "{{\"name\": \"{name}\", \"ph\": \"X\", \"ts\": {:.3}, \"dur\": {:.3}, \"pid\": 1, \"tid\": {tid}}}",
+        us(start),
+        us(duration)
+    )
+}
+
+fn ms(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+fn us(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1_000_000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_has_a_row_per_recorded_frame() {
+        let mut profiler = Profiler::new();
+        profiler.set_recording(true);
+        for _ in 0..3 {
+            profiler.begin_frame();
+            let started = Instant::now();
+            profiler.record(Stage::Clipping, started);
+            profiler.record(Stage::Clipping, started);
+            profiler.current().triangles_in = 12;
+            profiler.end_frame();
+        }
+        assert_eq!(profiler.last().frame, 3);
+        assert!(profiler.last().stages[Stage::Clipping as usize]
+            .start
+            .is_some());
+
+        let mut csv = Vec::new();
+        profiler.write_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let rows: Vec<_> = csv.lines().collect();
+        assert_eq!(rows.len(), 4);
+        let columns = rows[0].split(',').count();
+        assert!(rows[1..]
+            .iter()
+            .all(|row| row.split(',').count() == columns));
+        assert!(rows[3].starts_with("3,"));
+
+        // Clipping alternates with geometry, so it goes on its own track
+        let mut trace = Vec::new();
+        profiler.write_chrome_trace(&mut trace).unwrap();
+        let trace = String::from_utf8(trace).unwrap();
+        assert!(trace.contains("\"name\": \"clipping\", \"ph\": \"X\""));
+        assert!(trace
+            .lines()
+            .filter(|line| line.contains("\"name\": \"clipping\", \"ph\": \"X\""))
+            .all(|line| line.contains("\"tid\": 2")));
+    }
+}
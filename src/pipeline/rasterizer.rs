@@ -1,9 +1,7 @@
-use std::sync::{Arc, Mutex};
 
 use crate::core::{Color, RenderMode, Scene};
-use crate::debug_print;
 use crate::geometry::Material;
-use crate::pipeline::{to_fixed, Fragment, ProcessedGeometry, FP_ONE, FP_SHIFT};
+use crate::pipeline::{to_fixed, Fragment, FragmentKind, ProcessedGeometry, FP_ONE, FP_SHIFT};
 use glam::{Mat4, Vec2, Vec3, Vec4};
 use rayon::prelude::*;
 
@@ -50,28 +48,26 @@ impl Rasterizer {
         frags: &mut Vec<Fragment>,
     ) {
         frags.clear();
-        debug_print!("Processing {} geometries", geometry.len());
+        log::trace!("Processing {} geometries", geometry.len());
 
-        // Separate environment geometry from regular geometry
-
-        // Then process regular scene geometry
+        // Environment geometry goes through the same path, `Scene::entity` resolves its ids
         let scene_fragments: Vec<_> = geometry
             .par_iter()
             .flat_map(|geo: &ProcessedGeometry| {
                 let id = geo.entity_id;
-                self.process_mesh_triangles(geo, scene.entities[id].render_mode(), &scene)
+                self.process_mesh_triangles(geo, scene.entity(id).render_mode(), &scene)
             })
             .collect();
 
         frags.extend(scene_fragments);
 
-        debug_print!("Generated {} fragments", frags.len());
+        log::trace!("Generated {} fragments", frags.len());
     }
 
     fn process_mesh_triangles(
         &self,
         geo: &ProcessedGeometry,
-        mode: Arc<Mutex<RenderMode>>,
+        render_mode: RenderMode,
         scene: &Scene,
     ) -> Vec<Fragment> {
         let vertices = [
@@ -80,9 +76,6 @@ impl Rasterizer {
             geo.vertices[2].position,
         ];
 
-        // Get the render mode ONCE and release it's lock immediately
-        let render_mode = *mode.lock().unwrap();
-
         let screen_verts = self.project_to_screen(&vertices);
 
         let colors = [
@@ -93,44 +86,55 @@ impl Rasterizer {
 
         // MATCHING RENDER MODE TO DETERMINE HOW TO DRAW
         // HACK:  This is fucked
+        let entity = scene.entity(geo.entity_id);
         let world_pos = [
-            scene.entities[geo.entity_id].mesh.vertices[geo.world_pos[0]].pos,
-            scene.entities[geo.entity_id].mesh.vertices[geo.world_pos[1]].pos,
-            scene.entities[geo.entity_id].mesh.vertices[geo.world_pos[2]].pos,
+            entity.mesh.vertices[geo.world_pos[0]].pos,
+            entity.mesh.vertices[geo.world_pos[1]].pos,
+            entity.mesh.vertices[geo.world_pos[2]].pos,
         ];
 
-        let normals = {
-            let normal_buffer = scene.entities[geo.entity_id].mesh.normals.lock().unwrap();
-            [
-                normal_buffer[geo.world_pos[0]],
-                normal_buffer[geo.world_pos[1]],
-                normal_buffer[geo.world_pos[2]],
-            ]
-        };
+        let normals = [
+            entity.mesh.normals[geo.world_pos[0]],
+            entity.mesh.normals[geo.world_pos[1]],
+            entity.mesh.normals[geo.world_pos[2]],
+        ];
         let material = match geo.material_id {
-            Some(mat_id) if mat_id < scene.entities[geo.entity_id].mesh.materials.len() => {
-                &scene.entities[geo.entity_id].mesh.materials[mat_id]
-            }
+            Some(mat_id) if mat_id < entity.mesh.materials.len() => &entity.mesh.materials[mat_id],
             _ => &Material::default(),
         };
 
+        let solid = || {
+            // TODO: cleanup this method signature from hell
+            // self.rasterize_triangle_barycentric(screen_verts, colors, &vertices)
+            self.rasterize_triangle_barycentric_2(
+                screen_verts,
+                &geo.vertices,
+                &world_pos,
+                &normals,
+                material, // TODO: fix this as well. gonna leave all these bugs in cause I just wanna see shading damnit
+                geo.material_id.map(|mat_id| (geo.entity_id, mat_id)),
+            )
+        };
+
         match render_mode {
-            RenderMode::Solid => {
-                // TODO: cleanup this method signature from hell
-                // self.rasterize_triangle_barycentric(screen_verts, colors, &vertices)
-                self.rasterize_triangle_barycentric_2(
-                    screen_verts,
-                    &geo.vertices,
-                    &world_pos,
-                    &normals,
-                    material, // TODO: fix this as well. gonna leave all these bugs in cause I just wanna see shading damnit
-                    geo.material_id.map(|mat_id| (geo.entity_id, mat_id)),
-                )
-            }
+            RenderMode::Solid => solid(),
             RenderMode::FixedPoint => self.rasterize_fixed_point(screen_verts, colors, &vertices),
             RenderMode::Wireframe => {
                 self.rasterize_triangle_wireframe(screen_verts, &geo.vertices, material)
             }
+            RenderMode::HiddenLine | RenderMode::SolidWireframe => {
+                let surface = if matches!(render_mode, RenderMode::HiddenLine) {
+                    FragmentKind::DepthOnly
+                } else {
+                    FragmentKind::Surface
+                };
+                let mut fragments = with_kind(solid(), surface);
+                fragments.extend(with_kind(
+                    self.rasterize_triangle_wireframe(screen_verts, &geo.vertices, material),
+                    FragmentKind::Overlay,
+                ));
+                fragments
+            }
         }
     }
 
@@ -152,9 +156,9 @@ impl Rasterizer {
     fn is_triangle_visible(&self, vertices: &[Vec4; 3]) -> bool {
         for v in vertices {
             let ndc_z = v.z / v.w;
-            debug_print!("Vertex NDC z: {}", ndc_z);
+            log::trace!("Vertex NDC z: {}", ndc_z);
             if ndc_z > 1.0 || ndc_z < -1.0 {
-                debug_print!("Culling triangle: vertex z/w = {} outside [-1,1]", ndc_z);
+                log::trace!("Culling triangle: vertex z/w = {} outside [-1,1]", ndc_z);
                 return false;
             }
         }
@@ -412,6 +416,7 @@ impl Rasterizer {
                                     dissolve: material.dissolve.unwrap_or(1.0),
                                     uv,
                                     mat_id,
+                                    kind: FragmentKind::Surface,
                                 });
                                 continue;
                             }
@@ -427,6 +432,7 @@ impl Rasterizer {
                                 dissolve: material.dissolve.unwrap_or(1.0),
                                 uv,
                                 mat_id,
+                                kind: FragmentKind::Surface,
                             });
                             continue;
                         }
@@ -441,6 +447,7 @@ impl Rasterizer {
                             dissolve: material.dissolve.unwrap_or(1.0),
                             uv,
                             mat_id,
+                            kind: FragmentKind::Surface,
                         });
                     }
                 }
@@ -526,6 +533,7 @@ impl Rasterizer {
                             dissolve: material.dissolve.unwrap_or(0.),
                             uv,
                             mat_id: mat_id,
+                            kind: FragmentKind::Surface,
                         });
                     }
                 }
@@ -736,40 +744,10 @@ fn inside(v0: &Vec2, v1: &Vec2, v2: &Vec2, p: &Vec2) -> bool {
     inside
 }
 
-///  Bresenham's line algorithm
-/// https://en.wikipedia.org/wiki/Bresenham%27s_line_algorithm
-///
-/// This function is used to draw lines in the rasterizer
-pub fn bresenham<F>(start: glam::Vec2, end: glam::Vec2, p: crate::core::Pixel, mut plot: F)
-where
-    F: FnMut(glam::Vec2, f32, crate::core::Pixel),
-{
-    let mut x0 = start.x as i32;
-    let mut y0 = start.y as i32;
-    let x1 = end.x as i32;
-    let y1 = end.y as i32;
-
-    let dx = (x1 - x0).abs();
-    let sx = if x0 < x1 { 1 } else { -1 };
-    let dy = -(y1 - y0).abs();
-    let sy = if y0 < y1 { 1 } else { -1 };
-    let mut err = dx + dy;
-    let depth = 0.0; // You should probably compute actual depth, but for now just 0.0
-
-    loop {
-        plot(glam::Vec2::new(x0 as f32, y0 as f32), depth, p);
-
-        if x0 == x1 && y0 == y1 {
-            break;
-        }
-        let e2 = 2 * err;
-        if e2 >= dy {
-            err += dy;
-            x0 += sx;
-        }
-        if e2 <= dx {
-            err += dx;
-            y0 += sy;
-        }
+/// Tags every fragment with `kind`
+fn with_kind(mut fragments: Vec<Fragment>, kind: FragmentKind) -> Vec<Fragment> {
+    for fragment in &mut fragments {
+        fragment.kind = kind;
     }
+    fragments
 }
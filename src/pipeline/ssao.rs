@@ -0,0 +1,231 @@
+use glam::{Mat3, Mat4, Vec3, Vec4};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+
+use super::GBuffer;
+
+/// Side length of the tiled noise texture, the blur uses the same size to hide the pattern
+const NOISE_SIZE: usize = 4;
+
+#[derive(Clone, Copy, Debug)]
+pub struct SsaoSettings {
+    pub enabled: bool,
+    /// World space radius of the sampling hemisphere
+    pub radius: f32,
+    /// Samples per pixel, capped at `MAX_SAMPLES`
+    pub samples: usize,
+    /// Exponent applied to the occlusion term, higher values darken occluded areas more and
+    /// 0 disables the effect without skipping the pass
+    pub strength: f32,
+    /// Depth bias against self occlusion on flat surfaces
+    pub bias: f32,
+}
+
+impl SsaoSettings {
+    pub const MAX_SAMPLES: usize = 64;
+}
+
+impl Default for SsaoSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            radius: 0.5,
+            samples: 16,
+            strength: 2.0,
+            bias: 0.025,
+        }
+    }
+}
+
+/// Screen space ambient occlusion computed from the GBuffer's depth and normals
+///
+/// Samples a normal oriented hemisphere around every pixel's view space position, rotated per
+/// pixel with a small tiled noise texture, then box blurs the result over the noise tile.
+pub struct Ssao {
+    kernel: Vec<Vec3>,
+    noise: [Vec3; NOISE_SIZE * NOISE_SIZE],
+    /// View space position of every pixel, None where there is no geometry
+    positions: Vec<Option<Vec3>>,
+    raw: Vec<f32>,
+    /// 1.0 is fully lit, 0.0 fully occluded
+    occlusion: Vec<f32>,
+}
+
+impl Ssao {
+    pub fn new(size: usize) -> Self {
+        // Fixed seed, noise that changes every run makes comparing screenshots annoying
+        let mut rng = StdRng::seed_from_u64(0x55A0);
+        let noise = std::array::from_fn(|_| {
+            Vec3::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0), 0.0).normalize_or(Vec3::X)
+        });
+
+        Self {
+            kernel: Vec::new(),
+            noise,
+            positions: vec![None; size],
+            raw: vec![1.0; size],
+            occlusion: vec![1.0; size],
+        }
+    }
+
+    /// Regenerates the sample kernel when the sample count changed
+    fn update_kernel(&mut self, samples: usize) {
+        let samples = samples.clamp(1, SsaoSettings::MAX_SAMPLES);
+        if self.kernel.len() == samples {
+            return;
+        }
+        let mut rng = StdRng::seed_from_u64(0x55A0 + samples as u64);
+        self.kernel = (0..samples)
+            .map(|i| {
+                let dir = Vec3::new(
+                    rng.gen_range(-1.0..1.0),
+                    rng.gen_range(-1.0..1.0),
+                    rng.gen_range(0.0..1.0),
+                )
+                .normalize_or(Vec3::Z);
+                // Bunch the samples up near the center, close occluders matter most
+                let t = i as f32 / samples as f32;
+                let scale = 0.1 + 0.9 * t * t;
+                dir * rng.gen_range(0.1..1.0f32) * scale
+            })
+            .collect();
+    }
+
+    /// Per pixel occlusion factor from the last `compute` call
+    pub fn occlusion(&self) -> &[f32] {
+        &self.occlusion
+    }
+
+    pub fn compute(
+        &mut self,
+        gbuffer: &GBuffer,
+        width: usize,
+        height: usize,
+        view: &Mat4,
+        projection: &Mat4,
+        settings: &SsaoSettings,
+    ) {
+        let size = width * height;
+        self.positions.resize(size, None);
+        self.raw.resize(size, 1.0);
+        self.occlusion.resize(size, 1.0);
+        if !settings.enabled || settings.strength <= 0.0 {
+            self.occlusion.fill(1.0);
+            return;
+        }
+
+        // Same reconstruction as the lighting pass, just stopping at view space
+        let inv_projection = projection.inverse();
+        self.positions
+            .par_chunks_mut(width)
+            .enumerate()
+            .for_each(|(y, row)| {
+                for (x, out) in row.iter_mut().enumerate() {
+                    let depth = gbuffer.depth[y * width + x];
+                    *out = (depth != f32::INFINITY).then(|| {
+                        let ndc = Vec4::new(
+                            (x as f32 / width as f32) * 2.0 - 1.0,
+                            1.0 - (y as f32 / height as f32) * 2.0,
+                            depth * 2.0 - 1.0,
+                            1.0,
+                        );
+                        let view_pos = inv_projection * ndc;
+                        view_pos.truncate() / view_pos.w
+                    });
+                }
+            });
+
+        self.update_kernel(settings.samples);
+        let normal_matrix = Mat3::from_mat4(*view);
+        let kernel = &self.kernel;
+        let positions = &self.positions;
+        let noise = &self.noise;
+        self.raw
+            .par_chunks_mut(width)
+            .enumerate()
+            .for_each(|(y, row)| {
+                for (x, out) in row.iter_mut().enumerate() {
+                    let idx = y * width + x;
+                    let Some(origin) = positions[idx] else {
+                        *out = 1.0;
+                        continue;
+                    };
+                    let normal = (normal_matrix * gbuffer.normal[idx]).normalize_or_zero();
+                    if normal == Vec3::ZERO {
+                        *out = 1.0;
+                        continue;
+                    }
+
+                    // Gram-Schmidt the noise vector into a tangent frame around the normal
+                    let random = noise[(y % NOISE_SIZE) * NOISE_SIZE + x % NOISE_SIZE];
+                    let tangent = (random - normal * random.dot(normal)).normalize_or_zero();
+                    let tangent = if tangent == Vec3::ZERO {
+                        normal.any_orthonormal_vector()
+                    } else {
+                        tangent
+                    };
+                    let tbn = Mat3::from_cols(tangent, normal.cross(tangent), normal);
+
+                    let mut occluded = 0.0;
+                    for offset in kernel {
+                        let sample = origin + tbn * *offset * settings.radius;
+                        let clip = *projection * sample.extend(1.0);
+                        if clip.w <= 0.0 {
+                            continue;
+                        }
+                        let ndc = clip.truncate() / clip.w;
+                        let sx = ((ndc.x + 1.0) * 0.5 * width as f32) as isize;
+                        let sy = ((1.0 - ndc.y) * 0.5 * height as f32) as isize;
+                        if sx < 0 || sy < 0 || sx >= width as isize || sy >= height as isize {
+                            continue;
+                        }
+                        let Some(scene) = positions[sy as usize * width + sx as usize] else {
+                            continue;
+                        };
+                        // View space looks down -Z, larger z is closer to the camera
+                        if scene.z >= sample.z + settings.bias {
+                            // Fade out occluders far outside the radius (e.g. a wall far behind)
+                            let range = settings.radius / (origin.z - scene.z).abs().max(1e-4);
+                            occluded += range.min(1.0);
+                        }
+                    }
+                    let ao = 1.0 - occluded / kernel.len() as f32;
+                    *out = ao;
+                }
+            });
+
+        // Box blur over the noise tile, only between pixels that both have geometry
+        let raw = &self.raw;
+        self.occlusion
+            .par_chunks_mut(width)
+            .enumerate()
+            .for_each(|(y, row)| {
+                for (x, out) in row.iter_mut().enumerate() {
+                    if positions[y * width + x].is_none() {
+                        *out = 1.0;
+                        continue;
+                    }
+                    let half = NOISE_SIZE as isize / 2;
+                    let mut sum = 0.0;
+                    let mut count = 0;
+                    for dy in -half..half {
+                        for dx in -half..half {
+                            let sx = x as isize + dx;
+                            let sy = y as isize + dy;
+                            if sx < 0 || sy < 0 || sx >= width as isize || sy >= height as isize {
+                                continue;
+                            }
+                            let i = sy as usize * width + sx as usize;
+                            if positions[i].is_some() {
+                                sum += raw[i];
+                                count += 1;
+                            }
+                        }
+                    }
+                    let ao = if count > 0 { sum / count as f32 } else { 1.0 };
+                    *out = ao.clamp(0.0, 1.0).powf(settings.strength);
+                }
+            });
+    }
+}
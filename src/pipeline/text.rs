@@ -0,0 +1,77 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use fontdue::{Font, FontSettings, Metrics};
+
+/// Embedded so text works no matter which directory the renderer is started from
+static FONT_DATA: &[u8] = include_bytes!("../../assets/IBMPlexMono-Medium.ttf");
+
+/// Pixel size text gets rasterized at in window mode
+pub const TEXT_SIZE: f32 = 16.0;
+
+fn font() -> &'static Font {
+    static FONT: OnceLock<Font> = OnceLock::new();
+    FONT.get_or_init(|| {
+        Font::from_bytes(FONT_DATA, FontSettings::default()).expect("embedded font should parse")
+    })
+}
+
+/// Rasterized glyphs of the embedded monospace font, filled in as characters get used
+#[derive(Default)]
+pub struct GlyphCache {
+    glyphs: HashMap<char, (Metrics, Vec<u8>)>,
+}
+
+impl GlyphCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Width and height of one character cell in pixels (the font is monospaced)
+    pub fn cell_size() -> (usize, usize) {
+        let font = font();
+        let advance = font.metrics('M', TEXT_SIZE).advance_width;
+        let height = font
+            .horizontal_line_metrics(TEXT_SIZE)
+            .map_or(TEXT_SIZE, |line| line.new_line_size);
+        (advance.ceil() as usize, height.ceil() as usize)
+    }
+
+    /// Lays out `text` with its top left corner at `pos`, '\n' starts a new line. Calls `plot`
+    /// with every covered pixel and its coverage (0-1).
+    pub fn draw<F>(&mut self, pos: (usize, usize), text: &str, mut plot: F)
+    where
+        F: FnMut((usize, usize), f32),
+    {
+        let font = font();
+        let ascent = font
+            .horizontal_line_metrics(TEXT_SIZE)
+            .map_or(TEXT_SIZE, |line| line.ascent);
+        let (_, line_height) = Self::cell_size();
+
+        for (row, line) in text.lines().enumerate() {
+            let baseline = pos.1 as f32 + row as f32 * line_height as f32 + ascent;
+            let mut pen = pos.0 as f32;
+            for ch in line.chars() {
+                let (metrics, bitmap) = self
+                    .glyphs
+                    .entry(ch)
+                    .or_insert_with(|| font.rasterize(ch, TEXT_SIZE));
+                // Glyph bitmaps are top down, ymin is the offset of their bottom from the baseline
+                let left = pen.round() as i32 + metrics.xmin;
+                let top = baseline.round() as i32 - metrics.ymin - metrics.height as i32;
+                for (i, coverage) in bitmap.iter().enumerate() {
+                    if *coverage == 0 {
+                        continue;
+                    }
+                    let x = left + (i % metrics.width) as i32;
+                    let y = top + (i / metrics.width) as i32;
+                    if x >= 0 && y >= 0 {
+                        plot((x as usize, y as usize), *coverage as f32 / 255.0);
+                    }
+                }
+                pen += metrics.advance_width;
+            }
+        }
+    }
+}